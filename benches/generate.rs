@@ -0,0 +1,122 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use nalgebra::{Point3, Unit, UnitQuaternion, Vector2, Vector3};
+use ncollide3d::bounding_volume::AABB;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use arc_level_generator::{Feature, GeneratorConfig, Movement, Prefab, SpawnPeriodDistribution, VisibleWorld};
+
+fn prefab(prefab_id: i32, position: Vector3<f32>, velocity: Vector3<f32>, arc_angle: f32) -> Prefab {
+    Prefab {
+        prefab_id,
+        position,
+        rotation: UnitQuaternion::identity(),
+        bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+        collision_shape: None,
+        clearance: None,
+        collision_layer: 1,
+        collision_mask: u32::MAX,
+        movement: Movement {
+            baseline_velocity: velocity,
+            arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+            approach_arc_angle: arc_angle,
+            approach_arc_center_distance: 5.,
+            approach_arc_radius: 5.,
+            approach_rotation_strength: 1.,
+            departure_arc_angle: 0.0,
+            departure_arc_center_distance: 0.0,
+            departure_arc_radius: 0.0,
+            departure_rotation_strength: 0.0,
+        },
+    }
+}
+
+fn feature(prefabs: Vec<Prefab>, spawn_count: i32, spawn_period: f32) -> Feature {
+    Feature {
+        translate_x: true,
+        translate_x_using_bounds: false,
+        translate_x_bounds: Vector2::new(0., 0.),
+        translate_y: true,
+        translate_y_using_bounds: false,
+        translate_y_bounds: Vector2::new(0., 0.),
+        translate_z: false,
+        translate_z_using_bounds: false,
+        translate_z_bounds: Vector2::new(0., 0.),
+        rotate: false,
+        rotate_bounds: Vector2::new(0., 0.),
+        scale: false,
+        scale_bounds: Vector2::new(1., 1.),
+        mirror: false,
+        prefabs,
+        spawn_count,
+        spawn_period,
+        is_spawn_period_strict: false,
+        trigger_time: 0.0,
+        priority: 0,
+        missed_spawns: 0,
+        last_spawn_attempt: 0.0,
+        spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+        spawn_period_curve: None,
+        speed_multiplier_curve: None,
+        next_spawn_time: 0.0,
+        variants: vec![],
+    }
+}
+
+/// A wide world crowded with single entities, small formations and arcing entities
+fn dense_level() -> (VisibleWorld, Vec<Feature>) {
+    let world = VisibleWorld {
+        world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(40., 10., 20.)),
+        shape: None,
+        flow_direction: -Vector3::z_axis(),
+        exclusion_volumes: vec![],
+        player_lanes: vec![],
+    };
+    let features = vec![
+        feature(vec![prefab(0, nalgebra::zero(), Vector3::new(0., 0., -4.), 0.)], 300, 0.1),
+        feature(vec![
+            prefab(1, Vector3::new(-2., 0., 0.), Vector3::new(0., 0., -2.), 0.),
+            prefab(1, Vector3::new(2., 0., 0.), Vector3::new(0., 0., -2.), 0.),
+            prefab(1, Vector3::new(0., 2., 1.), Vector3::new(0., 0., -2.), 0.),
+        ], 100, 0.3),
+        feature(vec![prefab(2, nalgebra::zero(), Vector3::new(0., 0., -3.), 30.0_f32.to_radians())], 150, 0.2),
+    ];
+    (world, features)
+}
+
+/// A deep world with slow entities, so every spawn attempt is checked against many live obstacles
+fn slow_level() -> (VisibleWorld, Vec<Feature>) {
+    let world = VisibleWorld {
+        world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(20., 10., 60.)),
+        shape: None,
+        flow_direction: -Vector3::z_axis(),
+        exclusion_volumes: vec![],
+        player_lanes: vec![],
+    };
+    let features = vec![
+        feature(vec![prefab(0, nalgebra::zero(), Vector3::new(0., 0., -1.), 0.)], 200, 0.1),
+        feature(vec![prefab(1, nalgebra::zero(), Vector3::new(0., 0., -1.5), 20.0_f32.to_radians())], 100, 0.2),
+    ];
+    (world, features)
+}
+
+fn bench_level(c: &mut Criterion, name: &str, (world, features): (VisibleWorld, Vec<Feature>), config: &GeneratorConfig) {
+    c.bench_function(name, |bencher| bencher.iter(|| {
+        arc_level_generator::generate(&world, &features, config, &mut StdRng::seed_from_u64(0)).unwrap()
+    }));
+}
+
+fn bench_generate(c: &mut Criterion) {
+    bench_level(c, "exact checks only", dense_level(), &GeneratorConfig { broad_phase: false, ..GeneratorConfig::default() });
+    bench_level(c, "swept bounds broad phase", dense_level(), &GeneratorConfig { broad_phase: true, cache_swept_bounds: false, ..GeneratorConfig::default() });
+    bench_level(c, "cached swept bounds broad phase", dense_level(), &GeneratorConfig { broad_phase: true, cache_swept_bounds: true, ..GeneratorConfig::default() });
+    bench_level(c, "long-lived obstacles", slow_level(), &GeneratorConfig { cache_swept_bounds: false, ..GeneratorConfig::default() });
+    bench_level(c, "long-lived obstacles with cached swept bounds", slow_level(), &GeneratorConfig { cache_swept_bounds: true, ..GeneratorConfig::default() });
+}
+
+criterion_group! {
+    name = benches;
+    // A single generation takes long, so fewer samples keep the run short
+    config = Criterion::default().sample_size(10);
+    targets = bench_generate
+}
+criterion_main!(benches);
@@ -1,8 +1,9 @@
 use nalgebra::{Vector3, Isometry, UnitQuaternion, U3, Unit};
 use ncollide3d::interpolation::RigidMotion;
+use ncollide3d::bounding_volume::AABB;
 use std::ops::{Add, Sub};
 
-#[derive(Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct BiArcCurveMotion {
     /// The time at which this parametrization begins. Can be negative.
     pub t0: f32,
@@ -104,6 +105,76 @@ impl BiArcCurveMotion {
     }
 }
 
+impl BiArcCurveMotion {
+    /// Returns a copy of this motion that reaches each position later by a given delay
+    pub fn delayed(&self, delay: f32) -> BiArcCurveMotion {
+        BiArcCurveMotion {
+            t0: self.t0 + delay,
+            ..self.clone()
+        }
+    }
+
+    /// Checks if this motion follows a straight line. Collision motions never rotate, so a motion
+    /// without arc angles is a plain constant velocity motion
+    pub fn is_linear(&self) -> bool {
+        self.approach_arc_angle == 0. && self.departure_arc_angle == 0.
+    }
+
+    /// Returns a box containing the path of the motion origin between two times. Shifts caused by
+    /// each of the arcs change monotonically with time, so the path is bounded by the baseline
+    /// segment grown by the range of shifts at both ends
+    ///
+    /// * `start_time` - beginning of the bounded part of the path
+    /// * `end_time` - end of the bounded part of the path
+    ///
+    pub fn translation_bounds(&self, start_time: f32, end_time: f32) -> AABB<f32> {
+        let arc_direction = self.arcs_plane_normal.cross(&self.baseline_velocity.normalize());
+        let (start_approach_shift, start_departure_shift) = self.arc_shifts(start_time);
+        let (end_approach_shift, end_departure_shift) = self.arc_shifts(end_time);
+        let min_shift = arc_direction * (start_approach_shift.min(end_approach_shift) + start_departure_shift.min(end_departure_shift));
+        let max_shift = arc_direction * (start_approach_shift.max(end_approach_shift) + start_departure_shift.max(end_departure_shift));
+        let start_position = self.start.translation.vector + (start_time - self.t0) * self.baseline_velocity;
+        let end_position = self.start.translation.vector + (end_time - self.t0) * self.baseline_velocity;
+        AABB::new(
+            (start_position.inf(&end_position) + min_shift.inf(&max_shift)).into(),
+            (start_position.sup(&end_position) + min_shift.sup(&max_shift)).into(),
+        )
+    }
+
+    /// Returns shifts along the arc direction caused separately by the approach and the departure
+    /// arcs at a given time
+    fn arc_shifts(&self, t: f32) -> (f32, f32) {
+        let baseline_position = (t - self.t0) * &self.baseline_velocity;
+        let baseline_distance = (t - self.t0).signum() * baseline_position.magnitude();
+        let approach_easing_range = calculate_easing_range(self.approach_arc_radius, self.approach_arc_angle);
+        let departure_easing_range = calculate_easing_range(self.departure_arc_radius, self.departure_arc_angle);
+        let approach_easing_range_position = baseline_distance
+            .max(-self.approach_arc_center_distance - approach_easing_range)
+            .min(-self.approach_arc_center_distance)
+            .add(self.approach_arc_center_distance)
+            .abs();
+        let approach_linear_range_position = baseline_distance
+            .min(-self.approach_arc_center_distance - approach_easing_range)
+            .add(self.approach_arc_center_distance + approach_easing_range)
+            .abs();
+        let departure_easing_range_position = baseline_distance
+            .max(self.departure_arc_center_distance)
+            .min(self.departure_arc_center_distance + departure_easing_range)
+            .sub(self.departure_arc_center_distance)
+            .abs();
+        let departure_linear_range_position = baseline_distance
+            .max(self.departure_arc_center_distance + departure_easing_range)
+            .sub(self.departure_arc_center_distance + departure_easing_range)
+            .abs();
+        (
+            calculate_easing_range_shift(approach_easing_range_position, self.approach_arc_radius, self.approach_arc_angle)
+                + calculate_linear_range_shift(approach_linear_range_position, self.approach_arc_angle),
+            calculate_easing_range_shift(departure_easing_range_position, self.departure_arc_radius, self.departure_arc_angle)
+                + calculate_linear_range_shift(departure_linear_range_position, self.departure_arc_angle),
+        )
+    }
+}
+
 fn calculate_easing_range(radius: f32, angle: f32) -> f32 {
     angle.abs().sin() * radius
 }
@@ -123,6 +194,7 @@ fn calculate_linear_range_shift(linear_range_position: f32, angle: f32) -> f32 {
 mod tests {
     use super::*;
     use nalgebra::{Translation};
+    use ncollide3d::bounding_volume::BoundingVolume;
 
     #[test]
     fn test_position_at_time() {
@@ -354,4 +426,33 @@ mod tests {
         assert_relative_eq!(motion.rotation_at_time(0.01), UnitQuaternion::from_axis_angle(&Unit::new_normalize(Vector3::new(1., 0., 0.)), 45.0_f32.to_radians()));
         assert_relative_eq!(motion.rotation_at_time(10.), UnitQuaternion::from_axis_angle(&Unit::new_normalize(Vector3::new(1., 0., 0.)), 45.0_f32.to_radians()));
     }
+
+    #[test]
+    fn test_translation_bounds_contain_path() {
+        let motion = BiArcCurveMotion::new(
+            0.,
+            Isometry::from_parts(Translation::from(Vector3::new(1., 2., 3.)), UnitQuaternion::identity()),
+            Vector3::new(0., 0., -1.),
+            Unit::new_normalize(Vector3::new(1., 0., 0.)),
+            45.0_f32.to_radians() as f32,
+            10.,
+            10.,
+            1.,
+            -30.0_f32.to_radians() as f32,
+            5.,
+            10.,
+            1.,
+        );
+        for (start_time, end_time) in [(-30., 30.), (-15., -5.), (-5., 12.), (8., 25.)].iter() {
+            let bounds = motion.translation_bounds(*start_time, *end_time);
+            for step in 0..=100 {
+                let time = start_time + (end_time - start_time) * step as f32 / 100.;
+                let position = motion.position_at_time(time).translation.vector;
+                assert!(
+                    bounds.loosened(1.0e-4).contains(&AABB::new(position.into(), position.into())),
+                    "{:?} at {} is outside {:?}", position, time, bounds
+                );
+            }
+        }
+    }
 }
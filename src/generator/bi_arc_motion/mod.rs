@@ -1,3 +1,7 @@
 mod bi_arc_curve_motion;
+mod time_reversed_motion;
+mod path_motion;
 
 pub use bi_arc_curve_motion::BiArcCurveMotion;
+pub use time_reversed_motion::TimeReversedMotion;
+pub use path_motion::PathMotion;
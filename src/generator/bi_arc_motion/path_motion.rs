@@ -0,0 +1,175 @@
+use nalgebra::{Isometry3, Vector3, Translation3};
+use ncollide3d::interpolation::{RigidMotion, ConstantLinearVelocityRigidMotion};
+use ncollide3d::query;
+use ncollide3d::query::TOI;
+use ncollide3d::shape::Shape;
+use crate::generator::bi_arc_motion::{BiArcCurveMotion, TimeReversedMotion};
+
+/// Motion of an entity described so that time of impact queries can pick the cheapest method
+/// that is still exact for it
+pub enum PathMotion<'a> {
+    /// Motion with a constant velocity and a fixed rotation
+    Linear {
+        /// Isometry at `t = 0`
+        start: Isometry3<f32>,
+        velocity: Vector3<f32>,
+    },
+    Nonlinear(&'a dyn RigidMotion<f32>),
+}
+
+impl<'a> PathMotion<'a> {
+    /// Describes a bi-arc motion, which is linear when neither of its arcs bends the path
+    pub fn from_bi_arc(motion: &'a BiArcCurveMotion) -> PathMotion<'a> {
+        if motion.is_linear() {
+            PathMotion::Linear {
+                start: motion.position_at_time(0.),
+                velocity: motion.baseline_velocity,
+            }
+        } else {
+            PathMotion::Nonlinear(motion)
+        }
+    }
+
+    /// Describes a shape that doesn't move at all
+    pub fn fixed(position: Isometry3<f32>) -> PathMotion<'a> {
+        PathMotion::Linear {
+            start: position,
+            velocity: nalgebra::zero(),
+        }
+    }
+
+    /// Returns this motion played backwards from a given time. It lets time of impact queries find
+    /// the last contact of two motions instead of the first one
+    ///
+    /// * `end_time` - time of this motion that is reached at `t = 0`
+    /// * `reversed_motion` - place in which a reversed nonlinear motion is stored
+    ///
+    pub fn time_reversed<'b>(&'b self, end_time: f32, reversed_motion: &'b mut Option<TimeReversedMotion<'a>>) -> PathMotion<'b> {
+        match self {
+            PathMotion::Linear { velocity, .. } => PathMotion::Linear {
+                start: self.position_at_time(end_time),
+                velocity: -velocity,
+            },
+            PathMotion::Nonlinear(motion) => PathMotion::Nonlinear(reversed_motion.get_or_insert(TimeReversedMotion::new(*motion, end_time))),
+        }
+    }
+
+    /// Finds the first time at which two shapes following their motions come closer than a target
+    /// distance. Pairs of linear motions are checked with an exact linear sweep and the nonlinear
+    /// solver is only used when any of the motions is not linear
+    ///
+    /// * `shape` - shape following this motion
+    /// * `other` - motion of the other shape
+    /// * `other_shape` - the other shape
+    /// * `max_time_of_impact` - end of the checked time range that starts at zero
+    /// * `target_distance` - distance at which the shapes are considered to be in contact
+    ///
+    pub fn time_of_impact(
+        &self,
+        shape: &dyn Shape<f32>,
+        other: &PathMotion,
+        other_shape: &dyn Shape<f32>,
+        max_time_of_impact: f32,
+        target_distance: f32,
+    ) -> Option<TOI<f32>> {
+        let time_of_impact = match (self, other) {
+            (PathMotion::Linear { start, velocity }, PathMotion::Linear { start: other_start, velocity: other_velocity }) => {
+                query::time_of_impact(
+                    &query::DefaultTOIDispatcher,
+                    start,
+                    velocity,
+                    shape,
+                    other_start,
+                    other_velocity,
+                    other_shape,
+                    max_time_of_impact,
+                    target_distance,
+                )
+            }
+            _ => {
+                let (mut motion, mut other_motion) = (None, None);
+                query::nonlinear_time_of_impact(
+                    &query::DefaultTOIDispatcher,
+                    self.as_rigid_motion(&mut motion),
+                    shape,
+                    other.as_rigid_motion(&mut other_motion),
+                    other_shape,
+                    max_time_of_impact,
+                    target_distance,
+                )
+            }
+        };
+        match time_of_impact {
+            Ok(time_of_impact_option) => time_of_impact_option,
+            // Only unsupported pairs of shapes fail and the generator never builds those
+            Err(error) => panic!("time of impact query failed: {:?}", error),
+        }
+    }
+
+    /// Returns this motion as a rigid motion, storing the linear one in a given place
+    fn as_rigid_motion<'b>(&'b self, linear_motion: &'b mut Option<ConstantLinearVelocityRigidMotion<f32>>) -> &'b dyn RigidMotion<f32> {
+        match self {
+            PathMotion::Linear { start, velocity } => {
+                linear_motion.get_or_insert(ConstantLinearVelocityRigidMotion::new(0., *start, *velocity))
+            }
+            PathMotion::Nonlinear(motion) => *motion,
+        }
+    }
+
+    /// Returns the position at a given time
+    pub fn position_at_time(&self, t: f32) -> Isometry3<f32> {
+        match self {
+            PathMotion::Linear { start, velocity } => Translation3::from(velocity * t) * start,
+            PathMotion::Nonlinear(motion) => motion.position_at_time(t),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Unit, UnitQuaternion};
+    use ncollide3d::shape::Ball;
+
+    fn motion(position: Vector3<f32>, approach_arc_angle: f32) -> BiArcCurveMotion {
+        BiArcCurveMotion::new(
+            0.,
+            Isometry3::from_parts(Translation3::from(position), UnitQuaternion::identity()),
+            Vector3::new(0., 0., -1.),
+            Unit::new_normalize(Vector3::new(1., 0., 0.)),
+            approach_arc_angle,
+            5.,
+            5.,
+            1.,
+            0.,
+            0.,
+            0.,
+            0.,
+        )
+    }
+
+    #[test]
+    fn test_linear_and_nonlinear_time_of_impact_agree() {
+        let ball = Ball::new(0.5);
+        let leading = motion(Vector3::new(0., 0., -3.), 0.);
+        let following = motion(Vector3::new(0., 0., 0.), 0.);
+        assert!(matches!(PathMotion::from_bi_arc(&following), PathMotion::Linear { .. }));
+        assert!(matches!(PathMotion::from_bi_arc(&motion(nalgebra::zero(), 0.5)), PathMotion::Nonlinear(_)));
+
+        let wall = PathMotion::fixed(Isometry3::translation(0., 0., -6.));
+        let linear_time = PathMotion::from_bi_arc(&following)
+            .time_of_impact(&ball, &wall, &ball, 10., 0.)
+            .unwrap()
+            .toi;
+        let nonlinear_time = PathMotion::Nonlinear(&following)
+            .time_of_impact(&ball, &wall, &ball, 10., 0.)
+            .unwrap()
+            .toi;
+        assert_relative_eq!(linear_time, 5., epsilon = 1.0e-3);
+        assert_relative_eq!(linear_time, nonlinear_time, epsilon = 1.0e-3);
+        // Entities moving with the same velocity never meet
+        assert!(PathMotion::from_bi_arc(&following)
+            .time_of_impact(&ball, &PathMotion::Nonlinear(&leading), &ball, 10., 0.)
+            .is_none());
+    }
+}
@@ -0,0 +1,37 @@
+use nalgebra::{Isometry, UnitQuaternion, U3};
+use ncollide3d::interpolation::RigidMotion;
+
+/// Plays a motion backwards starting from a given time. It lets time of impact queries find the
+/// last contact of two motions instead of the first one
+pub struct TimeReversedMotion<'a> {
+    pub motion: &'a dyn RigidMotion<f32>,
+    /// The time of the wrapped motion that is reached at `t = 0`
+    pub end_time: f32,
+}
+
+impl<'a> TimeReversedMotion<'a> {
+    pub fn new(motion: &'a dyn RigidMotion<f32>, end_time: f32) -> Self {
+        TimeReversedMotion { motion, end_time }
+    }
+}
+
+impl<'a> RigidMotion<f32> for TimeReversedMotion<'a> {
+    fn position_at_time(&self, t: f32) -> Isometry<f32, U3, UnitQuaternion<f32>> {
+        self.motion.position_at_time(self.end_time - t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Isometry3, Vector3};
+    use ncollide3d::interpolation::ConstantLinearVelocityRigidMotion;
+
+    #[test]
+    fn test_time_reversed_motion() {
+        let motion = ConstantLinearVelocityRigidMotion::new(0., Isometry3::identity(), Vector3::new(0., 0., -1.));
+        let reversed_motion = TimeReversedMotion::new(&motion, 10.);
+        assert_eq!(reversed_motion.position_at_time(0.).translation.vector, Vector3::new(0., 0., -10.));
+        assert_eq!(reversed_motion.position_at_time(4.).translation.vector, Vector3::new(0., 0., -6.));
+    }
+}
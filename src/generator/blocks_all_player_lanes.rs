@@ -0,0 +1,345 @@
+use std::borrow::Cow;
+use std::cmp::Ordering::Equal;
+use rayon::prelude::*;
+use crate::generator::types::{CollidableEntity, VisibleWorld, Prefab, GeneratorConfig, FeaturePlan, ObstacleIndex, SweptBounds};
+use crate::generator::bi_arc_motion::{BiArcCurveMotion, PathMotion};
+
+/// Longest time during which all lanes may appear blocked at once. It absorbs the precision of time
+/// of impact queries so that entities just touching each other's blocked periods are accepted
+const ALL_LANES_BLOCKED_EPSILON: f32 = 0.001;
+
+/// Checks if spawning a feature would leave a player without any free lane at some point in time.
+/// Each entity blocks a lane from its first to its last contact with it, so entities that touch a
+/// lane more than once are treated as blocking it for the whole period in between. Like collisions,
+/// contacts are only looked for while entities are inside the world grown by the safety margin.
+/// Always returns false if the world has no player lanes
+///
+/// * `plan` - feature prepared to be spawned with a chosen shift
+/// * `obstacles` - entities that are still inside the visible world. With the broad phase enabled
+///                 only the ones whose swept volumes come close to a lane are checked against it
+/// * `world` - visible world holding player lanes
+/// * `time_travelled` - time at which the feature would be spawned
+/// * `config` - generator settings
+///
+pub fn blocks_all_player_lanes(
+    plan: &FeaturePlan,
+    obstacles: &ObstacleIndex,
+    world: &VisibleWorld,
+    time_travelled: f32,
+    config: &GeneratorConfig,
+) -> bool {
+    if world.player_lanes.is_empty() {
+        return false;
+    }
+    // Each path is paired with the interval in which it is checked. Motions are delayed so that
+    // they start at the beginning of that interval
+    let feature_paths: Vec<(&Prefab, BiArcCurveMotion, f32, f32)> = plan.prefabs
+        .iter()
+        .map(|prefab_plan| (
+            prefab_plan.prefab,
+            prefab_plan.motion.delayed(-prefab_plan.collision_start_time),
+            prefab_plan.collision_start_time,
+            prefab_plan.collision_end_time,
+        ))
+        .filter(|(_, _, start_time, end_time)| *end_time > *start_time)
+        .collect();
+    // Lanes are bounded until the last time at which any path is checked
+    let check_end_time = feature_paths
+        .iter()
+        .map(|(_, _, _, end_time)| *end_time)
+        .chain(obstacles.entities().iter().map(|obstacle| obstacle.collision_end_time - time_travelled))
+        .fold(0., f32::max);
+
+    let blocked_intervals_per_lane: Vec<Vec<(f32, f32)>> = world.player_lanes
+        .par_iter()
+        .map(|lane| {
+            let lane_shape = lane.shape.shape_handle();
+            let lane_obstacles: Vec<&CollidableEntity> = if config.broad_phase {
+                // Lanes are bounded in the time of obstacle motions
+                let lane_swept_bounds = SweptBounds::from_slab_bounds(
+                    time_travelled,
+                    time_travelled + check_end_time,
+                    |start_time, end_time| lane.bounds(start_time, end_time),
+                );
+                obstacles
+                    .candidates(&lane.bounds(time_travelled, time_travelled + check_end_time))
+                    .into_iter()
+                    .filter(|(obstacle, _)| lane.is_blocked_by(&obstacle.prefab))
+                    .filter(|&(obstacle, obstacle_swept_bounds)| {
+                        let obstacle_swept_bounds = obstacle_swept_bounds
+                            .map_or_else(|| Cow::Owned(SweptBounds::from_entity(obstacle)), Cow::Borrowed);
+                        lane_swept_bounds.intersects(&obstacle_swept_bounds, obstacle.prefab.clearance(config))
+                    })
+                    .map(|(obstacle, _)| obstacle)
+                    .collect()
+            } else {
+                obstacles.entities().iter().collect()
+            };
+            let obstacle_paths = lane_obstacles
+                .into_iter()
+                .map(|obstacle| {
+                    let start_time = (obstacle.collision_start_time - time_travelled).max(0.);
+                    (
+                        &obstacle.prefab,
+                        obstacle.motion.delayed(-time_travelled - start_time),
+                        start_time,
+                        obstacle.collision_end_time - time_travelled,
+                    )
+                })
+                .filter(|(_, _, start_time, end_time)| *end_time > *start_time);
+            let blocked_intervals = feature_paths
+                .iter()
+                .cloned()
+                .chain(obstacle_paths)
+                .filter(|(prefab, _, _, _)| lane.is_blocked_by(prefab))
+                .filter_map(|(prefab, motion, start_time, end_time)| {
+                    let duration = end_time - start_time;
+                    let lane_motion = lane.motion_since(time_travelled + start_time);
+                    let prefab_motion = PathMotion::from_bi_arc(&motion);
+                    let prefab_shape = prefab.collision_shape_handle();
+                    let clearance = prefab.clearance(config);
+                    let first_contact = prefab_motion
+                        .time_of_impact(prefab_shape.as_ref(), &lane_motion, lane_shape.as_ref(), duration, clearance)?
+                        .toi;
+                    let (mut reversed_prefab_motion, mut reversed_lane_motion) = (None, None);
+                    let last_contact = duration - prefab_motion
+                        .time_reversed(duration, &mut reversed_prefab_motion)
+                        .time_of_impact(
+                            prefab_shape.as_ref(),
+                            &lane_motion.time_reversed(duration, &mut reversed_lane_motion),
+                            lane_shape.as_ref(),
+                            duration,
+                            clearance,
+                        )
+                        .map_or(duration, |time_of_impact| time_of_impact.toi);
+                    Some((start_time + first_contact, start_time + last_contact.max(first_contact)))
+                })
+                .collect();
+            merge_intervals(blocked_intervals)
+        })
+        .collect();
+
+    let all_lanes_blocked_intervals = blocked_intervals_per_lane[1..]
+        .iter()
+        .fold(blocked_intervals_per_lane[0].clone(), |blocked_intervals, lane_blocked_intervals| {
+            intersect_intervals(&blocked_intervals, lane_blocked_intervals)
+        });
+    all_lanes_blocked_intervals
+        .iter()
+        .any(|(start, end)| end - start > ALL_LANES_BLOCKED_EPSILON)
+}
+
+/// Sorts intervals and merges the overlapping ones. Intervals with non-finite ends are dropped
+fn merge_intervals(mut intervals: Vec<(f32, f32)>) -> Vec<(f32, f32)> {
+    intervals.retain(|(start, end)| start.is_finite() && end.is_finite());
+    intervals.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Equal));
+    let mut merged: Vec<(f32, f32)> = Vec::with_capacity(intervals.len());
+    for (start, end) in intervals {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Intersects two sorted lists of disjoint intervals
+fn intersect_intervals(first: &[(f32, f32)], second: &[(f32, f32)]) -> Vec<(f32, f32)> {
+    let mut intersection = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < first.len() && j < second.len() {
+        let start = first[i].0.max(second[j].0);
+        let end = first[i].1.min(second[j].1);
+        if start <= end {
+            intersection.push((start, end));
+        }
+        if first[i].1 < second[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    intersection
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Point3, Vector2, Vector3, UnitQuaternion, Unit};
+    use ncollide3d::bounding_volume::AABB;
+    use crate::generator::types::{Movement, CollisionShape, PlayerLane, Feature, SpawnPeriodDistribution};
+    use crate::generator::spawn_feature::spawn_feature;
+
+    #[test]
+    fn test_merge_and_intersect_intervals() {
+        let merged = merge_intervals(vec![(3., 4.), (0., 1.), (0.5, 2.)]);
+        assert_eq!(merged, vec![(0., 2.), (3., 4.)]);
+        assert_eq!(intersect_intervals(&merged, &[(1., 3.5)]), vec![(1., 2.), (3., 3.5)]);
+        assert_eq!(intersect_intervals(&merged, &[(2.5, 2.8)]), vec![]);
+        assert_eq!(merge_intervals(vec![(f32::NAN, 1.), (0., 1.), (2., f32::INFINITY)]), vec![(0., 1.)]);
+    }
+
+    fn wall(x: f32) -> Prefab {
+        Prefab {
+            prefab_id: 0,
+            position: Vector3::new(x, 0., 0.),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(2., 5., 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+        }
+    }
+
+    fn feature(prefabs: Vec<Prefab>) -> Feature {
+        Feature {
+            translate_x: false,
+            translate_x_using_bounds: false,
+            translate_x_bounds: Vector2::new(0., 0.),
+            translate_y: false,
+            translate_y_using_bounds: false,
+            translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
+            prefabs,
+            spawn_count: 1,
+            spawn_period: 1.,
+            trigger_time: 0.,
+            priority: 0,
+            missed_spawns: 0,
+            is_spawn_period_strict: false,
+            last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
+        }
+    }
+
+    fn lane(x: f32) -> PlayerLane {
+        PlayerLane {
+            shape: CollisionShape::Cuboid { half_extents: Vector3::new(1., 1., 1.) },
+            position: Vector3::new(x, 0., -8.),
+            rotation: UnitQuaternion::identity(),
+            velocity: nalgebra::zero(),
+            collision_mask: u32::MAX,
+        }
+    }
+
+    fn world() -> VisibleWorld {
+        VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![lane(-5.), lane(5.)],
+        }
+    }
+
+    #[test]
+    fn test_blocks_all_lanes_with_a_wall_per_lane() {
+        let feature = feature(vec![wall(-5.), wall(5.)]);
+        let world = world();
+        let blocks = blocks_all_player_lanes(
+            &FeaturePlan::new(&feature, &world, &nalgebra::zero(), &GeneratorConfig::default()),
+            &ObstacleIndex::new(&world, &GeneratorConfig::default()),
+            &world,
+            0.,
+            &GeneratorConfig::default(),
+        );
+        assert_eq!(blocks, true);
+    }
+
+    #[test]
+    fn test_does_not_block_all_lanes_with_one_wall() {
+        let feature = feature(vec![wall(-5.)]);
+        let world = world();
+        let blocks = blocks_all_player_lanes(
+            &FeaturePlan::new(&feature, &world, &nalgebra::zero(), &GeneratorConfig::default()),
+            &ObstacleIndex::new(&world, &GeneratorConfig::default()),
+            &world,
+            0.,
+            &GeneratorConfig::default(),
+        );
+        assert_eq!(blocks, false);
+    }
+
+    #[test]
+    fn test_does_not_block_lanes_not_listening_to_layer() {
+        let mut transparent_wall = wall(5.);
+        transparent_wall.collision_layer = 0b10;
+        let mut world = world();
+        world.player_lanes.iter_mut().for_each(|lane| lane.collision_mask = 0b01);
+        let feature = feature(vec![wall(-5.), transparent_wall]);
+        let blocks = blocks_all_player_lanes(
+            &FeaturePlan::new(&feature, &world, &nalgebra::zero(), &GeneratorConfig::default()),
+            &ObstacleIndex::new(&world, &GeneratorConfig::default()),
+            &world,
+            0.,
+            &GeneratorConfig::default(),
+        );
+        assert_eq!(blocks, false);
+    }
+
+    #[test]
+    fn test_blocks_all_lanes_with_an_obstacle() {
+        let world = world();
+        let obstacle_feature = feature(vec![wall(5.)]);
+        let feature = feature(vec![wall(-5.)]);
+        for broad_phase in vec![true, false] {
+            let config = GeneratorConfig { broad_phase, ..GeneratorConfig::default() };
+            let mut obstacles = ObstacleIndex::new(&world, &config);
+            spawn_feature(
+                &FeaturePlan::new(&obstacle_feature, &world, &nalgebra::zero(), &config),
+                &mut obstacles,
+                &mut Vec::new(),
+                0.,
+            );
+            let blocks = blocks_all_player_lanes(
+                &FeaturePlan::new(&feature, &world, &nalgebra::zero(), &config),
+                &obstacles,
+                &world,
+                0.,
+                &config,
+            );
+            assert_eq!(blocks, true, "lanes not blocked with broad phase {}", broad_phase);
+        }
+    }
+
+    #[test]
+    fn test_ignores_contacts_before_entering_world() {
+        // The second wall trails the first one and crosses the lane upstream of the world before
+        // it enters it
+        let feature = feature(vec![wall(-5.), Prefab { position: Vector3::new(5., 0., 6.), ..wall(5.) }]);
+        let mut world = world();
+        world.player_lanes = vec![PlayerLane { position: Vector3::new(5., 0., 13.), ..lane(5.) }];
+        let blocks = blocks_all_player_lanes(
+            &FeaturePlan::new(&feature, &world, &nalgebra::zero(), &GeneratorConfig::default()),
+            &ObstacleIndex::new(&world, &GeneratorConfig::default()),
+            &world,
+            0.,
+            &GeneratorConfig::default(),
+        );
+        assert_eq!(blocks, false);
+    }
+}
@@ -1,84 +1,169 @@
 use rand::{RngCore, Rng};
 use crate::generator::types::{VisibleWorld, Feature};
-use nalgebra::{Vector3};
+use nalgebra::{Vector2, Vector3, Unit};
+use ncollide3d::bounding_volume::AABB;
 use crate::generator::calculate_prefabs_spawn_bounds::{calculate_prefabs_spawn_bounds};
 
+/// Number of shifts tried before giving up on avoiding static exclusion volumes and keeping
+/// prefabs inside of a non-box world shape
+const SHIFT_ATTEMPTS: usize = 8;
+
 /// Randomizes a shift with which all the entities belonging to this feature will be spawned. This
 /// function makes sure that after applying the shift no entity will be spawned outside visible
-/// world bounds
+/// world bounds. x and y translations are applied along the world lateral axes and z translation
+/// along the world flow direction. Shifts that would make any prefab
+/// cross a static exclusion volume or place it outside of a non-box world shape are resampled a
+/// few times
 ///
 /// * `rng` - random number generator
 /// * `world` - visible world
 /// * `feature` - feature that the possible shift is calculated for
 ///
 pub fn calculate_feature_shift(rng: &mut impl RngCore, world: &VisibleWorld, feature: &Feature) -> Vector3<f32> {
-    let mut shift = Vector3::new(0., 0., 0.);
-    if feature.translate_x_using_bounds || feature.translate_y_using_bounds {
-        let feature_spawn_bounds = calculate_prefabs_spawn_bounds(feature.prefabs.as_slice());
-        if feature.translate_x_using_bounds {
-            let mut min_x = feature.translate_x_bounds.x.min(feature.translate_x_bounds.y) - feature_spawn_bounds.center().x + feature_spawn_bounds.half_extents().x;
-            let mut max_x = feature.translate_x_bounds.x.max(feature.translate_x_bounds.y) - feature_spawn_bounds.center().x - feature_spawn_bounds.half_extents().x;
-            if min_x >= max_x {
-                let half_way = (min_x + max_x) / 2.;
-                min_x = half_way - 0.001;
-                max_x = half_way + 0.001;
-            }
-            shift.x = rng.gen_range(
-                min_x,
-                max_x,
-            );
-        }
-        if feature.translate_y_using_bounds {
-            let mut min_y = feature.translate_y_bounds.x.min(feature.translate_y_bounds.y) - feature_spawn_bounds.center().y + feature_spawn_bounds.half_extents().y;
-            let mut max_y = feature.translate_y_bounds.x.max(feature.translate_y_bounds.y) - feature_spawn_bounds.center().y - feature_spawn_bounds.half_extents().y;
-            if min_y >= max_y {
-                let half_way = (min_y + max_y) / 2.;
-                min_y = half_way - 0.001;
-                max_y = half_way + 0.001;
-            }
-            shift.y = rng.gen_range(
-                min_y,
-                max_y,
-            );
-        }
+    let (x_range, y_range) = shift_ranges(world, feature);
+    sample_shift_in_ranges(rng, world, feature, x_range, y_range)
+}
+
+/// Randomizes a shift within given ranges along the world lateral axes and within the depth range
+/// of a feature, resampling it a few times if any prefab would obviously be blocked or placed
+/// outside of the world
+///
+/// * `rng` - random number generator
+/// * `world` - visible world
+/// * `feature` - feature that the possible shift is calculated for
+/// * `x_range` - range of shifts along the world lateral x axis, none if it is not shifted along it
+/// * `y_range` - range of shifts along the world lateral y axis, none if it is not shifted along it
+///
+pub fn sample_shift_in_ranges(
+    rng: &mut impl RngCore,
+    world: &VisibleWorld,
+    feature: &Feature,
+    x_range: Option<(f32, f32)>,
+    y_range: Option<(f32, f32)>,
+) -> Vector3<f32> {
+    let z_range = depth_range(world, feature);
+    let mut shift = sample_feature_shift(rng, world, x_range, y_range, z_range);
+    if !(feature.translate_x || feature.translate_y || feature.translate_z) {
+        return shift;
     }
-    if (feature.translate_x && !feature.translate_x_using_bounds)
-        || (feature.translate_y && !feature.translate_y_using_bounds) {
-        let feature_spawn_bounds = calculate_prefabs_spawn_bounds(feature.prefabs.as_slice());
-        if feature.translate_x && !feature.translate_x_using_bounds {
-            let mut min_x = world.world_bounds.mins.x - feature_spawn_bounds.center().x + feature_spawn_bounds.half_extents().x;
-            let mut max_x = world.world_bounds.maxs.x - feature_spawn_bounds.center().x - feature_spawn_bounds.half_extents().x;
-            if min_x >= max_x {
-                let half_way = (min_x + max_x) / 2.;
-                min_x = half_way - 0.001;
-                max_x = half_way + 0.001;
-            }
-            shift.x = rng.gen_range(
-                min_x,
-                max_x,
-            );
-        }
-        if feature.translate_y && !feature.translate_y_using_bounds {
-            let mut min_y = world.world_bounds.mins.y - feature_spawn_bounds.center().y + feature_spawn_bounds.half_extents().y;
-            let mut max_y = world.world_bounds.maxs.y - feature_spawn_bounds.center().y - feature_spawn_bounds.half_extents().y;
-            if min_y >= max_y {
-                let half_way = (min_y + max_y) / 2.;
-                min_y = half_way - 0.001;
-                max_y = half_way + 0.001;
-            }
-            shift.y = rng.gen_range(
-                min_y,
-                max_y,
-            );
+    for _ in 1..SHIFT_ATTEMPTS {
+        if !is_shift_outside_world(world, feature, &shift) && !is_shift_blocked_by_static_volumes(world, feature, &shift) {
+            break;
         }
+        shift = sample_feature_shift(rng, world, x_range, y_range, z_range);
     }
     shift
 }
 
+/// Returns ranges of shifts along the world lateral axes that keep all prefabs inside of the
+/// translate bounds of a feature or inside of the world bounds. There is no range along an axis
+/// the feature is not shifted along
+pub fn shift_ranges(world: &VisibleWorld, feature: &Feature) -> (Option<(f32, f32)>, Option<(f32, f32)>) {
+    if !(feature.translate_x || feature.translate_y || feature.translate_x_using_bounds || feature.translate_y_using_bounds) {
+        return (None, None);
+    }
+    let (x_axis, y_axis) = world.lateral_axes();
+    let feature_spawn_bounds = calculate_prefabs_spawn_bounds(feature.prefabs.as_slice());
+    (
+        axis_shift_range(world, &feature_spawn_bounds, feature.translate_x, feature.translate_x_using_bounds, &feature.translate_x_bounds, &x_axis),
+        axis_shift_range(world, &feature_spawn_bounds, feature.translate_y, feature.translate_y_using_bounds, &feature.translate_y_bounds, &y_axis),
+    )
+}
+
+/// Returns the range of shifts along the world flow direction that keeps all prefabs inside of
+/// the z translate bounds of a feature or inside of the world bounds. There is no range if the
+/// feature is not shifted along the flow
+pub fn depth_range(world: &VisibleWorld, feature: &Feature) -> Option<(f32, f32)> {
+    if !(feature.translate_z || feature.translate_z_using_bounds) {
+        return None;
+    }
+    let feature_spawn_bounds = calculate_prefabs_spawn_bounds(feature.prefabs.as_slice());
+    axis_shift_range(world, &feature_spawn_bounds, feature.translate_z, feature.translate_z_using_bounds, &feature.translate_z_bounds, &world.flow_direction)
+}
+
+fn axis_shift_range(
+    world: &VisibleWorld,
+    feature_spawn_bounds: &AABB<f32>,
+    translate: bool,
+    using_bounds: bool,
+    bounds: &Vector2<f32>,
+    axis: &Unit<Vector3<f32>>,
+) -> Option<(f32, f32)> {
+    let (spawn_bounds_min, spawn_bounds_max) = project_aabb(feature_spawn_bounds, axis);
+    if using_bounds {
+        Some((bounds.x.min(bounds.y) - spawn_bounds_min, bounds.x.max(bounds.y) - spawn_bounds_max))
+    } else if translate {
+        let (world_min, world_max) = world.project(axis);
+        Some((world_min - spawn_bounds_min, world_max - spawn_bounds_max))
+    } else {
+        None
+    }
+}
+
+/// Checks if any prefab would be placed outside of a world shape. Positions within the shift range
+/// of a box world are always inside
+fn is_shift_outside_world(world: &VisibleWorld, feature: &Feature, shift: &Vector3<f32>) -> bool {
+    world.shape.is_some() && feature.prefabs
+        .iter()
+        .any(|prefab| !world.contains_point(&(prefab.position + shift).into()))
+}
+
+/// Checks if any prefab moving along its baseline line would pass through a static exclusion
+/// volume bounds. It only filters out shifts that are obviously blocked, the exact check is done
+/// when testing if a feature can be spawned
+fn is_shift_blocked_by_static_volumes(world: &VisibleWorld, feature: &Feature, shift: &Vector3<f32>) -> bool {
+    world.exclusion_volumes
+        .iter()
+        .filter(|volume| volume.is_static())
+        .any(|volume| {
+            feature.prefabs
+                .iter()
+                .any(|prefab| volume.bounds_intersect_line(
+                    &(prefab.position + shift),
+                    &prefab.movement.baseline_velocity,
+                    &prefab.bounding_box.half_extents(),
+                ))
+        })
+}
+
+fn sample_feature_shift(
+    rng: &mut impl RngCore,
+    world: &VisibleWorld,
+    x_range: Option<(f32, f32)>,
+    y_range: Option<(f32, f32)>,
+    z_range: Option<(f32, f32)>,
+) -> Vector3<f32> {
+    let (x_axis, y_axis) = world.lateral_axes();
+    let shift_x = x_range.map_or(0., |(min, max)| random_shift(rng, min, max));
+    let shift_y = y_range.map_or(0., |(min, max)| random_shift(rng, min, max));
+    let shift_z = z_range.map_or(0., |(min, max)| random_shift(rng, min, max));
+    x_axis.into_inner() * shift_x + y_axis.into_inner() * shift_y + world.flow_direction.into_inner() * shift_z
+}
+
+/// Projects a bounding box onto an axis and returns the resulting range
+pub fn project_aabb(aabb: &AABB<f32>, axis: &Unit<Vector3<f32>>) -> (f32, f32) {
+    let center = aabb.center().coords.dot(axis);
+    let half_extent = aabb.half_extents().abs().dot(&axis.abs());
+    (center - half_extent, center + half_extent)
+}
+
+/// Draws a shift from a range, or from a tiny range around its middle if it is empty
+pub fn random_shift(rng: &mut impl RngCore, mut min: f32, mut max: f32) -> f32 {
+    if min >= max {
+        let half_way = (min + max) / 2.;
+        min = half_way - 0.001;
+        max = half_way + 0.001;
+    }
+    rng.gen_range(
+        min,
+        max,
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use nalgebra::{Vector3, Point3, Vector2, UnitQuaternion, Unit};
-    use crate::{Prefab, Feature, VisibleWorld};
+    use crate::{Prefab, Feature, VisibleWorld, ExclusionVolume, CollisionShape, SpawnPeriodDistribution};
     use ncollide3d::bounding_volume::AABB;
     use crate::generator::calculate_feature_shift::calculate_feature_shift;
     use rand::thread_rng;
@@ -92,6 +177,10 @@ mod tests {
             position: Vector3::new(19.5, 0., 0.),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(48., 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -1.0),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -112,6 +201,14 @@ mod tests {
             translate_y: true,
             translate_y_using_bounds: false,
             translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             prefabs: vec![prefab0],
             spawn_count: 1,
             spawn_period: 1.,
@@ -120,9 +217,18 @@ mod tests {
             missed_spawns: 0,
             is_spawn_period_strict: false,
             last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
         let world = VisibleWorld {
             world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(30., 30., 30.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
         // let mut step_rng = StepRng::new(1000, 100);
         let feature_shift = calculate_feature_shift(
@@ -133,4 +239,230 @@ mod tests {
         dbg!(feature_shift);
         // TODO Actually test instead of just printing
     }
+
+    #[test]
+    fn test_feature_shift_perpendicular_to_flow() {
+        let prefab0 = Prefab {
+            prefab_id: 0,
+            position: Vector3::new(0., 0., 0.),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(-1., 0., 0.),
+                arcs_plane_normal: Unit::new_normalize(Vector3::new(0., 1., 0.)),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0
+            },
+        };
+        let feature = Feature {
+            translate_x: true,
+            translate_x_using_bounds: false,
+            translate_x_bounds: Vector2::new(0., 0.),
+            translate_y: true,
+            translate_y_using_bounds: false,
+            translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
+            prefabs: vec![prefab0],
+            spawn_count: 1,
+            spawn_period: 1.,
+            trigger_time: 10.,
+            priority: 0,
+            missed_spawns: 0,
+            is_spawn_period_strict: false,
+            last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
+        };
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 20., 30.)),
+            shape: None,
+            flow_direction: -Vector3::x_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
+        };
+        for _ in 0..100 {
+            let feature_shift = calculate_feature_shift(
+                &mut thread_rng(),
+                &world,
+                &feature,
+            );
+            assert_eq!(feature_shift.x, 0.);
+            assert!(feature_shift.y.abs() <= 19.5);
+            assert!(feature_shift.z.abs() <= 29.5);
+        }
+    }
+    #[test]
+    fn test_feature_shift_along_flow() {
+        let prefab0 = Prefab {
+            prefab_id: 0,
+            position: Vector3::new(0., 0., 0.),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(0., 0., -2.),
+                arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0
+            },
+        };
+        let feature = Feature {
+            translate_x: false,
+            translate_x_using_bounds: false,
+            translate_x_bounds: Vector2::new(0., 0.),
+            translate_y: false,
+            translate_y_using_bounds: false,
+            translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: true,
+            translate_z_using_bounds: true,
+            translate_z_bounds: Vector2::new(-3., 5.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
+            prefabs: vec![prefab0],
+            spawn_count: 1,
+            spawn_period: 1.,
+            trigger_time: 10.,
+            priority: 0,
+            missed_spawns: 0,
+            is_spawn_period_strict: false,
+            last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
+        };
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
+        };
+        let unshifted_approach_time = feature.max_approach_time(&world, &nalgebra::zero());
+        for _ in 0..100 {
+            let feature_shift = calculate_feature_shift(
+                &mut thread_rng(),
+                &world,
+                &feature,
+            );
+            let depth = -feature_shift.z;
+            assert_eq!((feature_shift.x, feature_shift.y), (0., 0.));
+            assert!((-2.5..=4.5).contains(&depth), "{} is out of bounds", depth);
+            // Prefabs moving at 2 units per second need longer to reach a position further along
+            // the flow
+            assert_relative_eq!(
+                feature.max_approach_time(&world, &feature_shift),
+                unshifted_approach_time + depth / 2.,
+                epsilon = 1.0e-4
+            );
+        }
+    }
+
+    #[test]
+    fn test_feature_shift_avoids_static_exclusion_volumes() {
+        let prefab0 = Prefab {
+            prefab_id: 0,
+            position: Vector3::new(0., 0., 0.),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0
+            },
+        };
+        let feature = Feature {
+            translate_x: true,
+            translate_x_using_bounds: false,
+            translate_x_bounds: Vector2::new(0., 0.),
+            translate_y: false,
+            translate_y_using_bounds: false,
+            translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
+            prefabs: vec![prefab0],
+            spawn_count: 1,
+            spawn_period: 1.,
+            trigger_time: 10.,
+            priority: 0,
+            missed_spawns: 0,
+            is_spawn_period_strict: false,
+            last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
+        };
+        // The volume blocks the left half of the world
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![ExclusionVolume {
+                shape: CollisionShape::Cuboid { half_extents: Vector3::new(5., 10., 1.) },
+                position: Vector3::new(-5.5, 0., 0.),
+                rotation: UnitQuaternion::identity(),
+                active_from: None,
+                active_until: None,
+            }],
+            player_lanes: vec![],
+        };
+        let unblocked_shifts = (0..100)
+            .map(|_| calculate_feature_shift(&mut thread_rng(), &world, &feature))
+            .filter(|feature_shift| feature_shift.x > 0.)
+            .count();
+        // Without resampling only about a half of the shifts would be unblocked
+        assert!(unblocked_shifts >= 90, "Only {} shifts avoid the volume", unblocked_shifts);
+    }
 }
@@ -0,0 +1,155 @@
+use rand::RngCore;
+use rand::seq::SliceRandom;
+use nalgebra::Vector3;
+use crate::generator::types::{VisibleWorld, Feature, GeneratorConfig, ShiftSampling, ObstacleIndex};
+use crate::generator::calculate_feature_shift::{calculate_feature_shift, sample_shift_in_ranges, shift_ranges};
+use crate::generator::calculate_free_feature_shifts::calculate_free_feature_shifts;
+
+/// Randomizes shifts tried at a single spawn attempt of a feature, in the order they should be
+/// tried. Stratified shifts split the allowed range into as many strata along each lateral axis as
+/// there are attempts, strata of both axes are paired randomly
+///
+/// * `rng` - random number generator
+/// * `world` - visible world
+/// * `feature` - feature that the shifts are calculated for
+/// * `obstacles` - entities that are still inside the visible world
+/// * `time_travelled` - time at which the feature would be spawned
+/// * `config` - generator settings providing the number of shifts and the sampling strategy
+///
+pub fn calculate_feature_shift_candidates(
+    rng: &mut impl RngCore,
+    world: &VisibleWorld,
+    feature: &Feature,
+    obstacles: &ObstacleIndex,
+    time_travelled: f32,
+    config: &GeneratorConfig,
+) -> Vec<Vector3<f32>> {
+    let attempts = config.shift_attempts.max(1);
+    match config.shift_sampling {
+        ShiftSampling::Random => (0..attempts)
+            .map(|_| calculate_feature_shift(rng, world, feature))
+            .collect(),
+        ShiftSampling::Stratified | ShiftSampling::BestFit => {
+            let (x_range, y_range) = shift_ranges(world, feature);
+            let mut y_strata: Vec<usize> = (0..attempts).collect();
+            y_strata.shuffle(rng);
+            let mut candidates: Vec<Vector3<f32>> = y_strata
+                .into_iter()
+                .enumerate()
+                .map(|(x_stratum, y_stratum)| sample_shift_in_ranges(
+                    rng,
+                    world,
+                    feature,
+                    stratum(x_range, x_stratum, attempts),
+                    stratum(y_range, y_stratum, attempts),
+                ))
+                .collect();
+            candidates.shuffle(rng);
+            candidates
+        }
+        ShiftSampling::FreeLanes => {
+            let free_shifts = calculate_free_feature_shifts(rng, world, feature, obstacles, time_travelled, attempts, config);
+            if free_shifts.is_empty() {
+                (0..attempts)
+                    .map(|_| calculate_feature_shift(rng, world, feature))
+                    .collect()
+            } else {
+                free_shifts
+            }
+        }
+    }
+}
+
+/// Returns one of equal parts a range is split into
+fn stratum(range: Option<(f32, f32)>, index: usize, count: usize) -> Option<(f32, f32)> {
+    range.map(|(min, max)| {
+        let width = (max - min) / count as f32;
+        (min + width * index as f32, min + width * (index + 1) as f32)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Point3, Vector2, UnitQuaternion};
+    use ncollide3d::bounding_volume::AABB;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use crate::generator::types::{Prefab, Movement, SpawnPeriodDistribution};
+
+    #[test]
+    fn test_stratified_candidates_cover_range() {
+        let prefab = Prefab {
+            prefab_id: 0,
+            position: nalgebra::zero(),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Vector3::x_axis(),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+        };
+        let feature = Feature {
+            translate_x: true,
+            translate_x_using_bounds: true,
+            translate_x_bounds: Vector2::new(-4.5, 4.5),
+            translate_y: false,
+            translate_y_using_bounds: false,
+            translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
+            prefabs: vec![prefab],
+            spawn_count: 1,
+            spawn_period: 1.,
+            trigger_time: 0.,
+            priority: 0,
+            missed_spawns: 0,
+            is_spawn_period_strict: false,
+            last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
+        };
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
+        };
+        let config = GeneratorConfig { shift_attempts: 4, shift_sampling: ShiftSampling::Stratified, ..GeneratorConfig::default() };
+        let obstacles = ObstacleIndex::new(&world, &config);
+        let candidates = calculate_feature_shift_candidates(&mut StdRng::seed_from_u64(3), &world, &feature, &obstacles, 0., &config);
+        assert_eq!(candidates.len(), 4);
+        // The prefab keeps within [-4.5, 4.5], so shifts span [-4, 4] split into strata 2 units wide
+        let mut strata: Vec<i32> = candidates
+            .iter()
+            .map(|shift| {
+                assert!(shift.x.abs() <= 4. && shift.y == 0. && shift.z == 0.);
+                ((shift.x + 4.) / 2.).floor().min(3.) as i32
+            })
+            .collect();
+        strata.sort();
+        assert_eq!(strata, vec![0, 1, 2, 3]);
+    }
+}
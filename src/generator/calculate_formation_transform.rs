@@ -0,0 +1,124 @@
+use rand::{RngCore, Rng};
+use crate::generator::types::{Feature, FormationTransform};
+
+/// Randomizes a transformation applied to prefabs of a feature at a single spawn. Options the
+/// feature doesn't enable are left at identity and don't draw any random numbers
+///
+/// * `rng` - random number generator
+/// * `feature` - feature that the transformation is calculated for
+///
+pub fn calculate_formation_transform(rng: &mut impl RngCore, feature: &Feature) -> FormationTransform {
+    FormationTransform {
+        rotation_angle: if feature.rotate { random_in_bounds(rng, feature.rotate_bounds.x, feature.rotate_bounds.y) } else { 0. },
+        scale: if feature.scale { random_in_bounds(rng, feature.scale_bounds.x, feature.scale_bounds.y) } else { 1. },
+        mirrored: feature.mirror && rng.gen_bool(0.5),
+    }
+}
+
+fn random_in_bounds(rng: &mut impl RngCore, min: f32, max: f32) -> f32 {
+    if min < max {
+        rng.gen_range(min, max)
+    } else {
+        min
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Point3, Vector2, Vector3, UnitQuaternion};
+    use ncollide3d::bounding_volume::AABB;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use crate::generator::types::{Prefab, Movement, VisibleWorld, SpawnPeriodDistribution};
+
+    #[test]
+    fn test_transformed_formation_keeps_its_layout() {
+        let prefab = |x: f32| Prefab {
+            prefab_id: 0,
+            position: Vector3::new(x, 0., 1.),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Vector3::x_axis(),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 2.0,
+                approach_arc_radius: 1.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+        };
+        let feature = Feature {
+            translate_x: false,
+            translate_x_using_bounds: false,
+            translate_x_bounds: Vector2::new(0., 0.),
+            translate_y: false,
+            translate_y_using_bounds: false,
+            translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: true,
+            rotate_bounds: Vector2::new(-1., 1.),
+            scale: true,
+            scale_bounds: Vector2::new(0.5, 2.),
+            mirror: true,
+            prefabs: vec![prefab(-1.), prefab(2.)],
+            spawn_count: 1,
+            spawn_period: 1.,
+            trigger_time: 0.,
+            priority: 0,
+            missed_spawns: 0,
+            is_spawn_period_strict: false,
+            last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
+        };
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
+        };
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut mirrored_count = 0;
+        for _ in 0..50 {
+            let transform = calculate_formation_transform(&mut rng, &feature);
+            assert!((-1.0..=1.).contains(&transform.rotation_angle));
+            assert!((0.5..=2.).contains(&transform.scale));
+            mirrored_count += transform.mirrored as usize;
+
+            let transformed = feature.transformed(&transform, &world);
+            let (first, second) = (&transformed.prefabs[0], &transformed.prefabs[1]);
+            // Rotation about the flow axis keeps distances along the flow, scaling stretches all
+            // of them
+            assert_relative_eq!(first.position.z, transform.scale, epsilon = 1.0e-5);
+            assert_relative_eq!((second.position - first.position).norm(), 3. * transform.scale, epsilon = 1.0e-5);
+            assert_relative_eq!(first.bounding_box.half_extents(), Vector3::new(0.5, 0.5, 0.5) * transform.scale, epsilon = 1.0e-5);
+            assert_relative_eq!(first.movement.approach_arc_center_distance, 2. * transform.scale, epsilon = 1.0e-5);
+            assert_relative_eq!(first.movement.baseline_velocity, Vector3::new(0., 0., -1.), epsilon = 1.0e-5);
+            // Mirroring swaps the sides of the formation before it is rotated
+            let expected_side = if transform.mirrored { -1. } else { 1. };
+            let rotated_x = Vector3::new(transform.rotation_angle.cos(), -transform.rotation_angle.sin(), 0.);
+            assert_relative_eq!(
+                (second.position - first.position).normalize().dot(&rotated_x).abs(),
+                1.,
+                epsilon = 1.0e-4
+            );
+            assert!((second.position - first.position).dot(&rotated_x) * expected_side > 0.);
+        }
+        assert!(mirrored_count > 0 && mirrored_count < 50);
+    }
+}
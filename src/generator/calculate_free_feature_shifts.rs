@@ -0,0 +1,249 @@
+use std::borrow::Cow;
+use std::cmp::Ordering::Equal;
+use rand::{RngCore, Rng};
+use nalgebra::{Vector3, Unit};
+use ncollide3d::bounding_volume::{AABB, BoundingVolume};
+use crate::generator::types::{VisibleWorld, Feature, FeaturePlan, GeneratorConfig, ObstacleIndex, SweptBounds, SWEPT_BOUNDS_TOLERANCE, bounding_radius};
+use crate::generator::calculate_feature_shift::{shift_ranges, depth_range, project_aabb, random_shift};
+
+/// Randomizes shifts of a feature only from the intervals in which the volume swept by the feature
+/// doesn't come close to the volume swept by any obstacle. The intervals are searched once along the
+/// world lateral x axis, or along the y axis if the feature is not shifted along x, and all shifts
+/// are drawn from them. The shifts along the other axis and along the flow are drawn randomly once
+/// and shared by all returned shifts. Swept volumes are bounded with the feature placed in the
+/// middle of its range and moved sideways, so the exact collision check is still needed. Returns
+/// no shifts if the feature is not shifted or no interval is free
+///
+/// * `rng` - random number generator
+/// * `world` - visible world
+/// * `feature` - feature that the shifts are calculated for
+/// * `obstacles` - entities that are still inside the visible world
+/// * `time_travelled` - time at which the feature would be spawned
+/// * `count` - number of shifts to draw
+/// * `config` - generator settings providing clearances
+///
+pub fn calculate_free_feature_shifts(
+    rng: &mut impl RngCore,
+    world: &VisibleWorld,
+    feature: &Feature,
+    obstacles: &ObstacleIndex,
+    time_travelled: f32,
+    count: usize,
+    config: &GeneratorConfig,
+) -> Vec<Vector3<f32>> {
+    let (x_axis, y_axis) = world.lateral_axes();
+    let (free_axis, (range_min, range_max), other_axis, other_range) = match shift_ranges(world, feature) {
+        (Some(x_range), y_range) => (x_axis, x_range, y_axis, y_range),
+        (None, Some(y_range)) => (y_axis, y_range, x_axis, None),
+        (None, None) => return vec![],
+    };
+    if range_min >= range_max {
+        return vec![];
+    }
+    let other_shift = other_range.map_or(0., |(min, max)| random_shift(rng, min, max));
+    let fixed_shift = other_axis.into_inner() * other_shift + world.flow_direction.into_inner()
+        * depth_range(world, feature).map_or(0., |(min, max)| random_shift(rng, min, max));
+    let middle = (range_min + range_max) / 2.;
+    let plan = FeaturePlan::new(
+        feature,
+        world,
+        &(free_axis.into_inner() * middle + fixed_shift),
+        config,
+    );
+
+    let mut blocked_intervals = Vec::new();
+    for prefab_plan in &plan.prefabs {
+        let prefab_clearance = prefab_plan.prefab.clearance(config);
+        // The path is bounded at both ends of the shift range, so that the candidates cover every
+        // shift the feature can be moved to
+        let path_bounds = prefab_plan.motion
+            .translation_bounds(prefab_plan.collision_start_time, prefab_plan.collision_end_time)
+            .loosened(bounding_radius(&prefab_plan.shape) + prefab_clearance + SWEPT_BOUNDS_TOLERANCE);
+        let range_bounds = translated_aabb(&path_bounds, &(free_axis.into_inner() * (range_min - middle)))
+            .merged(&translated_aabb(&path_bounds, &(free_axis.into_inner() * (range_max - middle))));
+        // Swept bounds of the prefab are computed in the time of obstacle motions
+        let prefab_swept_bounds = SweptBounds::new(
+            &prefab_plan.motion.delayed(time_travelled),
+            &prefab_plan.shape,
+            time_travelled + prefab_plan.collision_start_time,
+            time_travelled + prefab_plan.collision_end_time,
+        );
+        for (obstacle, obstacle_swept_bounds) in obstacles
+            .candidates(&range_bounds)
+            .into_iter()
+            .filter(|(obstacle, _)| prefab_plan.prefab.collides_with(&obstacle.prefab)) {
+            // Only the part of both paths during which both entities are inside the world is bounded
+            let margin = prefab_clearance.max(obstacle.prefab.clearance(config)) + SWEPT_BOUNDS_TOLERANCE;
+            let obstacle_swept_bounds = obstacle_swept_bounds
+                .map_or_else(|| Cow::Owned(SweptBounds::from_entity(obstacle)), Cow::Borrowed);
+            for (slab, obstacle_slab) in prefab_swept_bounds.common_slabs(&obstacle_swept_bounds) {
+                if !overlaps_along(slab, obstacle_slab, &world.flow_direction, margin)
+                    || !overlaps_along(slab, obstacle_slab, &other_axis, margin) {
+                    continue;
+                }
+                // Moving the feature by an offset moves its slab by the same offset
+                let (slab_min, slab_max) = project_aabb(slab, &free_axis);
+                let (obstacle_min, obstacle_max) = project_aabb(obstacle_slab, &free_axis);
+                blocked_intervals.push((
+                    middle + obstacle_min - slab_max - margin,
+                    middle + obstacle_max - slab_min + margin,
+                ));
+            }
+        }
+    }
+    let free_intervals = free_intervals(range_min, range_max, blocked_intervals);
+    if free_intervals.is_empty() {
+        return vec![];
+    }
+    (0..count)
+        .map(|_| free_axis.into_inner() * sample_free_interval(rng, &free_intervals) + fixed_shift)
+        .collect()
+}
+
+fn translated_aabb(aabb: &AABB<f32>, offset: &Vector3<f32>) -> AABB<f32> {
+    AABB {
+        mins: &aabb.mins + offset,
+        maxs: &aabb.maxs + offset,
+    }
+}
+
+fn overlaps_along(first: &AABB<f32>, second: &AABB<f32>, axis: &Unit<Vector3<f32>>, margin: f32) -> bool {
+    let (first_min, first_max) = project_aabb(first, axis);
+    let (second_min, second_max) = project_aabb(second, axis);
+    first_min <= second_max + margin && second_min <= first_max + margin
+}
+
+/// Returns the parts of a range that are not covered by any of the blocked intervals. Blocked
+/// intervals with a NaN end block the whole range
+fn free_intervals(min: f32, max: f32, mut blocked_intervals: Vec<(f32, f32)>) -> Vec<(f32, f32)> {
+    if blocked_intervals.iter().any(|(start, end)| start.is_nan() || end.is_nan()) {
+        return vec![];
+    }
+    blocked_intervals.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Equal));
+    let mut free_intervals = Vec::new();
+    let mut free_start = min;
+    for (blocked_start, blocked_end) in blocked_intervals {
+        if blocked_start > free_start {
+            free_intervals.push((free_start, blocked_start.min(max)));
+        }
+        free_start = free_start.max(blocked_end);
+        if free_start >= max {
+            break;
+        }
+    }
+    if free_start < max {
+        free_intervals.push((free_start, max));
+    }
+    free_intervals.retain(|(start, end)| end > start);
+    free_intervals
+}
+
+/// Draws a value uniformly from non-empty free intervals
+fn sample_free_interval(rng: &mut impl RngCore, free_intervals: &[(f32, f32)]) -> f32 {
+    let free_length: f32 = free_intervals.iter().map(|(start, end)| end - start).sum();
+    let mut offset = rng.gen_range(0., free_length);
+    for (start, end) in free_intervals {
+        if offset < end - start {
+            return start + offset;
+        }
+        offset -= end - start;
+    }
+    free_intervals.last().map_or(0., |(_, end)| *end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Point3, Vector2, UnitQuaternion};
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use crate::generator::types::{Prefab, Movement, SpawnPeriodDistribution};
+    use crate::generator::spawn_feature::spawn_feature;
+
+    #[test]
+    fn test_sample_free_interval() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let intervals = free_intervals(-4., 4., vec![(1., 5.), (-5., -3.), (-1., 0.)]);
+        assert_eq!(intervals, vec![(-3., -1.), (0., 1.)]);
+        for _ in 0..100 {
+            let value = sample_free_interval(&mut rng, &intervals);
+            assert!((-3.0_f32..=-1.).contains(&value) || (0.0_f32..=1.).contains(&value), "{} is blocked", value);
+        }
+        assert!(free_intervals(-4., 4., vec![(-5., 0.), (-0.5, 4.5)]).is_empty());
+        assert!(free_intervals(-4., 4., vec![(f32::NAN, 0.), (1., 2.)]).is_empty());
+    }
+
+    #[test]
+    fn test_free_shift_avoids_obstacle_lane() {
+        let prefab = Prefab {
+            prefab_id: 0,
+            position: nalgebra::zero(),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Vector3::x_axis(),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+        };
+        let fixed_feature = Feature {
+            translate_x: false,
+            translate_x_using_bounds: false,
+            translate_x_bounds: Vector2::new(0., 0.),
+            translate_y: false,
+            translate_y_using_bounds: false,
+            translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
+            prefabs: vec![prefab],
+            spawn_count: 1,
+            spawn_period: 1.,
+            trigger_time: 0.,
+            priority: 0,
+            missed_spawns: 0,
+            is_spawn_period_strict: false,
+            last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
+        };
+        let shifted_feature = Feature { translate_x: true, ..fixed_feature.clone() };
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(5., 5., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
+        };
+        let config = GeneratorConfig::default();
+        let mut obstacles = ObstacleIndex::new(&world, &config);
+        spawn_feature(&FeaturePlan::new(&fixed_feature, &world, &nalgebra::zero(), &config), &mut obstacles, &mut vec![], 0.);
+
+        let shifts = calculate_free_feature_shifts(&mut StdRng::seed_from_u64(9), &world, &shifted_feature, &obstacles, 0.5, 100, &config);
+        assert_eq!(shifts.len(), 100);
+        for shift in shifts {
+            // Boxes one unit wide placed side by side are at least one unit apart
+            assert!(shift.x.abs() >= 1., "{} is in the obstacle lane", shift.x);
+            assert!(shift.x.abs() <= 4.5);
+        }
+    }
+}
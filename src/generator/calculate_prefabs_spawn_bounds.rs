@@ -32,6 +32,10 @@ mod tests {
             position: Vector3::new(-10., 0., -10.),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(1., 1., 1.)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -1.0),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -1,100 +1,149 @@
 use super::types::{CollidableEntity, VisibleWorld};
-use std::collections::VecDeque;
-use ncollide3d::shape::Cuboid;
-use ncollide3d::query;
-use nalgebra::{Isometry3, Vector3, Translation3};
+use std::borrow::Cow;
 use rayon::prelude::*;
-use crate::generator::types::Feature;
-use crate::generator::bi_arc_motion::{BiArcCurveMotion};
+use ncollide3d::bounding_volume::BoundingVolume;
+use crate::generator::types::{FeaturePlan, GeneratorConfig, SpawnRejection, SweptBounds, ObstacleIndex, SWEPT_BOUNDS_TOLERANCE, bounding_radius};
+use crate::generator::bi_arc_motion::PathMotion;
 
-/// Checks if a feature can be safely spawn so that it won't collide with any existing entities in
-/// a visible world
+/// Checks if a feature can be safely spawn so that it won't collide with any existing entities or
+/// exclusion volumes in a visible world. Returns the first collision found otherwise, prefabs are
+/// checked in order and for each of them exclusion volumes are checked before obstacles. Entities
+/// are only checked while they are inside the world grown by the safety margin, so their paths may
+/// cross outside of it
+///
+/// * `plan` - feature prepared to be spawned with a chosen shift
+/// * `obstacles` - entities that are still inside the visible world. With the broad phase enabled
+///                 only the ones whose paths come close to the feature sideways are checked
+/// * `world` - visible world
+/// * `time_travelled` - time at which the feature would be spawned
+/// * `config` - generator settings
 ///
 pub fn can_spawn_feature(
-    feature: &Feature,
-    obstacles: &VecDeque<CollidableEntity>,
+    plan: &FeaturePlan,
+    obstacles: &ObstacleIndex,
     world: &VisibleWorld,
     time_travelled: f32,
-    feature_shift: &Vector3<f32>,
-) -> bool {
-    let max_time_to_travel = feature.max_approach_time(&world, &feature_shift);
-    let any_prefab_in_feature_collides_with_any_obstacle = feature.prefabs
+    config: &GeneratorConfig,
+) -> Result<(), SpawnRejection> {
+    let rejection = plan.prefabs
         .par_iter()
-        .any(|prefab| {
-            let any_obstacle_collides_with_prefab = obstacles
-                .into_par_iter()
-                .any(|obstacle| {
-                    let prefab_motion = BiArcCurveMotion::new(
-                        max_time_to_travel + feature.priority as f32,
-                        Isometry3::from_parts(Translation3::from(prefab.position + Vector3::new(feature_shift.x, feature_shift.y, 0.)), prefab.rotation),
-                        prefab.movement.baseline_velocity.clone(),
-                        prefab.movement.arcs_plane_normal.clone(),
-                        prefab.movement.approach_arc_angle,
-                        prefab.movement.approach_arc_center_distance,
-                        prefab.movement.approach_arc_radius,
-                        prefab.movement.approach_rotation_strength,
-                        prefab.movement.departure_arc_angle,
-                        prefab.movement.departure_arc_center_distance,
-                        prefab.movement.departure_arc_radius,
-                        prefab.movement.departure_rotation_strength,
-                    );
-
-                    let obstacle_motion = BiArcCurveMotion::new(
-                        obstacle.spawn_time - time_travelled - obstacle.movement_start_parameter,
-                        Isometry3::from_parts(Translation3::from(obstacle.prefab.position + obstacle.spawn_feature_shift), obstacle.prefab.rotation),
-                        obstacle.prefab.movement.baseline_velocity.clone(),
-                        obstacle.prefab.movement.arcs_plane_normal.clone(),
-                        obstacle.prefab.movement.approach_arc_angle,
-                        obstacle.prefab.movement.approach_arc_center_distance,
-                        obstacle.prefab.movement.approach_arc_radius,
-                        obstacle.prefab.movement.approach_rotation_strength,
-                        obstacle.prefab.movement.departure_arc_angle,
-                        obstacle.prefab.movement.departure_arc_center_distance,
-                        obstacle.prefab.movement.departure_arc_radius,
-                        obstacle.prefab.movement.departure_rotation_strength,
-                    );
-
-                    let prefab_bounding_box = Cuboid::new(prefab.bounding_box.half_extents());
-                    let obstacle_bounding_box = Cuboid::new(obstacle.prefab.bounding_box.half_extents());
-                    let time_of_impact = query::nonlinear_time_of_impact(
-                        &query::DefaultTOIDispatcher,
-                        &prefab_motion,
-                        &prefab_bounding_box,
-                        &obstacle_motion,
-                        &obstacle_bounding_box,
-                        max_time_to_travel + feature.priority as f32 + prefab.find_departure_time_in_world(&world,  &feature_shift),
-                        0.0,
-                    );
-                    match time_of_impact {
-                        Ok(time_of_impact_option) => {
-                            return match time_of_impact_option {
-                                None => {
-                                    false
-                                }
-                                Some(_toi) => {
-                                    // dbg!(_toi);
-                                    true
-                                }
-                            };
-                        }
-                        Err(error) => {
-                            panic!(error)
-                        }
+        .enumerate()
+        .find_map_first(|(prefab_index, prefab_plan)| {
+            let prefab = prefab_plan.prefab;
+            let collision_end_time = prefab_plan.collision_end_time;
+            let exclusion_volume_rejection = world.exclusion_volumes
+                .par_iter()
+                .enumerate()
+                .find_map_first(|(volume_index, volume)| {
+                    // Only the part of the prefab path during which the volume exists is checked
+                    let (active_from, active_until) = volume.active_interval_since(time_travelled);
+                    let check_start = active_from.max(prefab_plan.collision_start_time);
+                    let check_end = active_until.min(collision_end_time);
+                    if check_start > check_end {
+                        return None;
                     }
+                    let prefab_motion = prefab_plan.motion.delayed(-check_start);
+                    let prefab_path_motion = PathMotion::from_bi_arc(&prefab_motion);
+                    let volume_shape = volume.shape.shape_handle();
+                    prefab_path_motion
+                        .time_of_impact(
+                            prefab_plan.shape.as_ref(),
+                            &PathMotion::fixed(volume.isometry()),
+                            volume_shape.as_ref(),
+                            check_end - check_start,
+                            prefab.clearance(config),
+                        )
+                        .map(|time_of_impact| SpawnRejection::ExclusionVolumeCollision {
+                            prefab_index,
+                            volume_index,
+                            time_of_impact: check_start + time_of_impact.toi,
+                            contact_point: prefab_path_motion.position_at_time(time_of_impact.toi) * time_of_impact.witness1,
+                        })
                 });
-            any_obstacle_collides_with_prefab
+            if exclusion_volume_rejection.is_some() {
+                return exclusion_volume_rejection;
+            }
+            // Both paths are bounded over the whole time range checked by the exact query. Swept
+            // bounds of the prefab are computed in the time of obstacle motions
+            let (prefab_swept_bounds, candidates) = if config.broad_phase {
+                let prefab_bounds = prefab_plan.motion
+                    .translation_bounds(0., collision_end_time)
+                    .loosened(bounding_radius(&prefab_plan.shape) + prefab.clearance(config) + SWEPT_BOUNDS_TOLERANCE);
+                (
+                    Some(SweptBounds::new(
+                        &prefab_plan.motion.delayed(time_travelled),
+                        &prefab_plan.shape,
+                        time_travelled + prefab_plan.collision_start_time,
+                        time_travelled + collision_end_time,
+                    )),
+                    obstacles.candidates(&prefab_bounds),
+                )
+            } else {
+                (
+                    None,
+                    obstacles.entities()
+                        .iter()
+                        .map(|obstacle| (obstacle, None))
+                        .collect::<Vec<(&CollidableEntity, Option<&SweptBounds>)>>(),
+                )
+            };
+            candidates
+                .par_iter()
+                .filter(|(obstacle, _)| prefab.collides_with(&obstacle.prefab))
+                .find_map_first(|&(obstacle, obstacle_swept_bounds)| {
+                    // Only the part of both paths during which both entities are inside the world
+                    // is checked
+                    let check_start = prefab_plan.collision_start_time.max(obstacle.collision_start_time - time_travelled);
+                    let check_end = collision_end_time.min(obstacle.collision_end_time - time_travelled);
+                    if check_start > check_end {
+                        return None;
+                    }
+                    let obstacle_motion = obstacle.motion.delayed(-time_travelled);
+                    let clearance = prefab.clearance(config).max(obstacle.prefab.clearance(config));
+                    let obstacle_shape = obstacle.prefab.collision_shape_handle();
+                    if let Some(prefab_swept_bounds) = &prefab_swept_bounds {
+                        let obstacle_swept_bounds = obstacle_swept_bounds
+                            .map_or_else(|| Cow::Owned(SweptBounds::from_entity(obstacle)), Cow::Borrowed);
+                        if !prefab_swept_bounds.intersects(&obstacle_swept_bounds, clearance) {
+                            return None;
+                        }
+                    }
+                    let prefab_motion = prefab_plan.motion.delayed(-check_start);
+                    let prefab_path_motion = PathMotion::from_bi_arc(&prefab_motion);
+                    prefab_path_motion
+                        .time_of_impact(
+                            prefab_plan.shape.as_ref(),
+                            &PathMotion::from_bi_arc(&obstacle_motion.delayed(-check_start)),
+                            obstacle_shape.as_ref(),
+                            check_end - check_start,
+                            clearance,
+                        )
+                        .map(|time_of_impact| SpawnRejection::ObstacleCollision {
+                            prefab_index,
+                            obstacle_entity_id: obstacle.entity_id,
+                            time_of_impact: check_start + time_of_impact.toi,
+                            contact_point: prefab_path_motion.position_at_time(time_of_impact.toi) * time_of_impact.witness1,
+                        })
+                })
         });
-    !any_prefab_in_feature_collides_with_any_obstacle
+    match rejection {
+        Some(rejection) => Err(rejection),
+        None => Ok(()),
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::generator::types::{Prefab, Feature, VisibleWorld, CollidableEntity};
+    use crate::generator::types::{Prefab, Feature, VisibleWorld, CollidableEntity, GeneratorConfig, FeaturePlan, ObstacleIndex, SpawnPeriodDistribution};
     use ncollide3d::bounding_volume::AABB;
     use nalgebra::{Vector3, Point3, Vector2};
     use crate::generator::can_spawn_feature::can_spawn_feature;
-    use std::collections::VecDeque;
-    use std::iter::FromIterator;
+
+    fn obstacle_index(world: &VisibleWorld, config: &GeneratorConfig, obstacles: Vec<CollidableEntity>) -> ObstacleIndex {
+        let mut index = ObstacleIndex::new(world, config);
+        obstacles.into_iter().for_each(|obstacle| index.insert(obstacle));
+        index
+    }
 
     mod zero_travel_time {
         use super::*;
@@ -108,6 +157,10 @@ mod tests {
                 position: Vector3::new(0., 0., 0.),
                 rotation: UnitQuaternion::identity(),
                 bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+                collision_shape: None,
+                clearance: None,
+                collision_layer: 1,
+                collision_mask: u32::MAX,
                 movement: Movement {
                     baseline_velocity: Vector3::new(0., 0., -1.),
                     arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -128,6 +181,14 @@ mod tests {
                 translate_y: false,
                 translate_y_using_bounds: false,
                 translate_y_bounds: Vector2::new(0., 0.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
                 prefabs: vec![prefab],
                 spawn_count: 1,
                 spawn_period: 1.,
@@ -136,19 +197,23 @@ mod tests {
                 missed_spawns: 0,
                 is_spawn_period_strict: false,
                 last_spawn_attempt: 0.0,
+                spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+                spawn_period_curve: None,
+                speed_multiplier_curve: None,
+                next_spawn_time: 0.0,
+                variants: vec![],
             };
-            let obstacle = CollidableEntity {
-                movement_start_parameter: -10.5,
-                movement_end_parameter: 0.0,
-                spawn_position: Vector3::new(0., 5., 10.),
-                spawn_rotation: UnitQuaternion::identity(),
-                spawn_feature_shift: nalgebra::zero(),
-                spawn_time: 0.0,
-                prefab: Prefab {
+            let obstacle = CollidableEntity::new(
+                0,
+                Prefab {
                     prefab_id: 0,
                     position: nalgebra::zero(),
                     rotation: UnitQuaternion::identity(),
                     bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+                    collision_shape: None,
+                    clearance: None,
+                    collision_layer: 1,
+                    collision_mask: u32::MAX,
                     movement: Movement {
                         baseline_velocity: Vector3::new(0., -1., -1.),
                         arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -162,18 +227,26 @@ mod tests {
                         departure_rotation_strength: 0.0,
                     },
                 },
-                priority: 0,
-            };
+                nalgebra::zero(),
+                0.0,
+                -10.5,
+                0.0,
+                0,
+            );
             let world = VisibleWorld {
                 world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+                shape: None,
+                flow_direction: -Vector3::z_axis(),
+                exclusion_volumes: vec![],
+                player_lanes: vec![],
             };
             let can_spawn = can_spawn_feature(
-                &feature,
-                &VecDeque::from_iter([obstacle].iter().cloned()),
+                &FeaturePlan::new(&feature, &world, &Vector3::new(0., 0., 0.), &GeneratorConfig::default()),
+                &obstacle_index(&world, &GeneratorConfig::default(), vec![obstacle]),
                 &world,
                 0.,
-                &Vector3::new(0., 0., 0.),
-            );
+                &GeneratorConfig::default(),
+            ).is_ok();
             assert_eq!(can_spawn, false);
         }
 
@@ -184,6 +257,10 @@ mod tests {
                 position: Vector3::new(0., 0., 0.),
                 rotation: UnitQuaternion::identity(),
                 bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+                collision_shape: None,
+                clearance: None,
+                collision_layer: 1,
+                collision_mask: u32::MAX,
                 movement: Movement {
                     baseline_velocity: Vector3::new(0., 0., -1.),
                     arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -204,6 +281,14 @@ mod tests {
                 translate_y: false,
                 translate_y_using_bounds: false,
                 translate_y_bounds: Vector2::new(0., 0.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
                 prefabs: vec![prefab],
                 spawn_count: 1,
                 spawn_period: 1.,
@@ -212,17 +297,23 @@ mod tests {
                 missed_spawns: 0,
                 is_spawn_period_strict: false,
                 last_spawn_attempt: 0.0,
+                spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+                spawn_period_curve: None,
+                speed_multiplier_curve: None,
+                next_spawn_time: 0.0,
+                variants: vec![],
             };
-            let obstacle = CollidableEntity {
-                movement_start_parameter: -10.5,
-                spawn_position: Vector3::new(0., 0., -1.25),
-                spawn_rotation: UnitQuaternion::identity(),
-                spawn_feature_shift: nalgebra::zero(),
-                prefab: Prefab {
+            let obstacle = CollidableEntity::new(
+                0,
+                Prefab {
                     prefab_id: 0,
                     position: Default::default(),
                     rotation: Default::default(),
                     bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+                    collision_shape: None,
+                    clearance: None,
+                    collision_layer: 1,
+                    collision_mask: u32::MAX,
                     movement: Movement {
                         baseline_velocity: Vector3::new(0., 0., -0.5),
                         arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -236,20 +327,26 @@ mod tests {
                         departure_rotation_strength: 0.0,
                     },
                 },
-                spawn_time: 0.0,
-                priority: 0,
-                movement_end_parameter: 0.0,
-            };
+                nalgebra::zero(),
+                0.0,
+                -10.5,
+                0.0,
+                0,
+            );
             let world = VisibleWorld {
                 world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+                shape: None,
+                flow_direction: -Vector3::z_axis(),
+                exclusion_volumes: vec![],
+                player_lanes: vec![],
             };
             let can_spawn = can_spawn_feature(
-                &feature,
-                &VecDeque::from_iter([obstacle].iter().cloned()),
+                &FeaturePlan::new(&feature, &world, &Vector3::new(0., 0., 0.), &GeneratorConfig::default()),
+                &obstacle_index(&world, &GeneratorConfig::default(), vec![obstacle]),
                 &world,
                 0.,
-                &Vector3::new(0., 0., 0.),
-            );
+                &GeneratorConfig::default(),
+            ).is_ok();
             assert_eq!(can_spawn, false);
         }
 
@@ -260,6 +357,10 @@ mod tests {
                 position: Vector3::new(0., 0., 0.),
                 rotation: UnitQuaternion::identity(),
                 bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+                collision_shape: None,
+                clearance: None,
+                collision_layer: 1,
+                collision_mask: u32::MAX,
                 movement: Movement {
                     baseline_velocity: Vector3::new(0., 0., -1.),
                     arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -280,6 +381,14 @@ mod tests {
                 translate_y: false,
                 translate_y_using_bounds: false,
                 translate_y_bounds: Vector2::new(0., 0.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
                 prefabs: vec![prefab],
                 spawn_count: 1,
                 spawn_period: 1.,
@@ -288,20 +397,23 @@ mod tests {
                 missed_spawns: 0,
                 is_spawn_period_strict: false,
                 last_spawn_attempt: 0.0,
+                spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+                spawn_period_curve: None,
+                speed_multiplier_curve: None,
+                next_spawn_time: 0.0,
+                variants: vec![],
             };
-            let obstacle = CollidableEntity {
-                movement_start_parameter: -24.,
-                movement_end_parameter: 0.0,
-                spawn_position: Vector3::new(0., 0., -2.5),
-                spawn_rotation: UnitQuaternion::identity(),
-                spawn_feature_shift: nalgebra::zero(),
-                spawn_time: 0.0,
-                priority: 0,
-                prefab: Prefab {
+            let obstacle = CollidableEntity::new(
+                0,
+                Prefab {
                     prefab_id: 0,
                     position: nalgebra::zero(),
                     rotation: Default::default(),
                     bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+                    collision_shape: None,
+                    clearance: None,
+                    collision_layer: 1,
+                    collision_mask: u32::MAX,
                     movement: Movement {
                         baseline_velocity: Vector3::new(0., 0., -0.5),
                         arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -315,17 +427,26 @@ mod tests {
                         departure_rotation_strength: 0.0,
                     },
                 },
-            };
+                nalgebra::zero(),
+                0.0,
+                -24.,
+                0.0,
+                0,
+            );
             let world = VisibleWorld {
                 world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+                shape: None,
+                flow_direction: -Vector3::z_axis(),
+                exclusion_volumes: vec![],
+                player_lanes: vec![],
             };
             let can_spawn = can_spawn_feature(
-                &feature,
-                &VecDeque::from_iter([obstacle].iter().cloned()),
+                &FeaturePlan::new(&feature, &world, &Vector3::new(0., 0., 0.), &GeneratorConfig::default()),
+                &obstacle_index(&world, &GeneratorConfig::default(), vec![obstacle]),
                 &world,
                 0.,
-                &Vector3::new(0., 0., 0.),
-            );
+                &GeneratorConfig::default(),
+            ).is_ok();
             assert_eq!(can_spawn, true);
         }
 
@@ -336,6 +457,10 @@ mod tests {
                 position: Vector3::new(0., 0., 0.),
                 rotation: UnitQuaternion::identity(),
                 bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+                collision_shape: None,
+                clearance: None,
+                collision_layer: 1,
+                collision_mask: u32::MAX,
                 movement: Movement {
                     baseline_velocity: Vector3::new(0., 0., -1.),
                     arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -356,6 +481,14 @@ mod tests {
                 translate_y: false,
                 translate_y_using_bounds: false,
                 translate_y_bounds: Vector2::new(0., 0.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
                 prefabs: vec![prefab],
                 spawn_count: 1,
                 spawn_period: 1.,
@@ -364,20 +497,23 @@ mod tests {
                 missed_spawns: 0,
                 is_spawn_period_strict: false,
                 last_spawn_attempt: 0.0,
+                spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+                spawn_period_curve: None,
+                speed_multiplier_curve: None,
+                next_spawn_time: 0.0,
+                variants: vec![],
             };
-            let obstacle = CollidableEntity {
-                movement_start_parameter: -10.5,
-                movement_end_parameter: 0.0,
-                spawn_position: Vector3::new(0., 0., 10.0),
-                spawn_rotation: UnitQuaternion::identity(),
-                spawn_feature_shift: nalgebra::zero(),
-                spawn_time: 0.0,
-                priority: 0,
-                prefab: Prefab {
+            let obstacle = CollidableEntity::new(
+                0,
+                Prefab {
                     prefab_id: 0,
                     position: nalgebra::zero(),
                     rotation: Default::default(),
                     bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+                    collision_shape: None,
+                    clearance: None,
+                    collision_layer: 1,
+                    collision_mask: u32::MAX,
                     movement: Movement {
                         baseline_velocity: Vector3::new(0., 0., -1.),
                         arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -392,20 +528,118 @@ mod tests {
                     },
 
                 },
-            };
+                nalgebra::zero(),
+                0.0,
+                -10.5,
+                0.0,
+                0,
+            );
             let world = VisibleWorld {
                 world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+                shape: None,
+                flow_direction: -Vector3::z_axis(),
+                exclusion_volumes: vec![],
+                player_lanes: vec![],
             };
             let can_spawn = can_spawn_feature(
-                &feature,
-                &VecDeque::from_iter([obstacle].iter().cloned()),
+                &FeaturePlan::new(&feature, &world, &Vector3::new(0., 0., 0.), &GeneratorConfig::default()),
+                &obstacle_index(&world, &GeneratorConfig::default(), vec![obstacle]),
                 &world,
                 0.,
-                &Vector3::new(0., 0., 0.),
-            );
+                &GeneratorConfig::default(),
+            ).is_ok();
             assert_eq!(can_spawn, false);
         }
 
+        #[test]
+        fn test_can_spawn_feature_collision_outside_world() {
+            let movement = Movement {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            };
+            let prefab = Prefab {
+                prefab_id: 0,
+                position: Vector3::new(0., 0., 0.),
+                rotation: UnitQuaternion::identity(),
+                bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+                collision_shape: None,
+                clearance: None,
+                collision_layer: 1,
+                collision_mask: u32::MAX,
+                movement: movement.clone(),
+            };
+            let feature = Feature {
+                translate_x: false,
+                translate_x_using_bounds: false,
+                translate_x_bounds: Vector2::new(0., 0.),
+                translate_y: false,
+                translate_y_using_bounds: false,
+                translate_y_bounds: Vector2::new(0., 0.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
+                prefabs: vec![prefab.clone()],
+                spawn_count: 1,
+                spawn_period: 1.,
+                trigger_time: 10.,
+                priority: 0,
+                missed_spawns: 0,
+                is_spawn_period_strict: false,
+                last_spawn_attempt: 0.0,
+                spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+                spawn_period_curve: None,
+                speed_multiplier_curve: None,
+                next_spawn_time: 0.0,
+                variants: vec![],
+            };
+            // Crosses the feature path behind the world, when the feature is at z = -12
+            let obstacle = CollidableEntity::new(
+                0,
+                Prefab {
+                    position: Vector3::new(0., 0., -12.),
+                    movement: Movement { baseline_velocity: Vector3::new(1., 0., 0.), ..movement },
+                    ..prefab
+                },
+                nalgebra::zero(),
+                22.5,
+                0.0,
+                0.0,
+                0,
+            );
+            let world = VisibleWorld {
+                world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+                shape: None,
+                flow_direction: -Vector3::z_axis(),
+                exclusion_volumes: vec![],
+                player_lanes: vec![],
+            };
+            let can_spawn_with_margin = |world_safety_margin: f32| {
+                let config = GeneratorConfig { world_safety_margin, ..GeneratorConfig::default() };
+                can_spawn_feature(
+                    &FeaturePlan::new(&feature, &world, &Vector3::new(0., 0., 0.), &config),
+                    &obstacle_index(&world, &config, vec![obstacle.clone()]),
+                    &world,
+                    0.,
+                    &config,
+                ).is_ok()
+            };
+            assert_eq!(can_spawn_with_margin(0.), true);
+            assert_eq!(can_spawn_with_margin(2.), false);
+        }
+
         #[ignore]
         #[test]
         fn test_can_spawn_rotated_collides_in_spawn_position() {
@@ -414,6 +648,10 @@ mod tests {
                 position: Vector3::new(0., 0., 0.),
                 rotation: UnitQuaternion::from_euler_angles(std::f32::consts::FRAC_PI_2, 0., 0.),
                 bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 4., 0.5)),
+                collision_shape: None,
+                clearance: None,
+                collision_layer: 1,
+                collision_mask: u32::MAX,
                 movement: Movement {
                     baseline_velocity: Vector3::new(0., 0., -1.),
                     arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -434,6 +672,14 @@ mod tests {
                 translate_y: false,
                 translate_y_using_bounds: false,
                 translate_y_bounds: Vector2::new(0., 0.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
                 prefabs: vec![prefab0],
                 spawn_count: 10,
                 spawn_period: 1.0,
@@ -442,24 +688,31 @@ mod tests {
                 missed_spawns: 0,
                 is_spawn_period_strict: false,
                 last_spawn_attempt: 0.0,
+                spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+                spawn_period_curve: None,
+                speed_multiplier_curve: None,
+                next_spawn_time: 0.0,
+                variants: vec![],
             };
 
             let world = VisibleWorld {
                 world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+                shape: None,
+                flow_direction: -Vector3::z_axis(),
+                exclusion_volumes: vec![],
+                player_lanes: vec![],
             };
-            let obstacle = CollidableEntity {
-                movement_start_parameter: 0.0,
-                movement_end_parameter: 0.0,
-                spawn_position: Vector3::new(0., 0., 2.1),
-                spawn_rotation: UnitQuaternion::identity(),
-                spawn_feature_shift: nalgebra::zero(),
-                spawn_time: 0.0,
-                priority: 0,
-                prefab: Prefab {
+            let obstacle = CollidableEntity::new(
+                0,
+                Prefab {
                     position: nalgebra::zero(),
                     rotation: UnitQuaternion::from_euler_angles(std::f32::consts::FRAC_PI_2, 0., 0.),
                     prefab_id: 0,
                     bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 4., 0.5)),
+                    collision_shape: None,
+                    clearance: None,
+                    collision_layer: 1,
+                    collision_mask: u32::MAX,
                     movement: Movement {
                         baseline_velocity: Vector3::new(0., 0., -1.),
                         arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -473,14 +726,19 @@ mod tests {
                         departure_rotation_strength: 0.0,
                     },
                 },
-            };
+                nalgebra::zero(),
+                0.0,
+                0.0,
+                0.0,
+                0,
+            );
             let can_spawn = can_spawn_feature(
-                &feature0,
-                &VecDeque::from_iter([obstacle].iter().cloned()),
+                &FeaturePlan::new(&feature0, &world, &Vector3::new(0., 0., 0.), &GeneratorConfig::default()),
+                &obstacle_index(&world, &GeneratorConfig::default(), vec![obstacle]),
                 &world,
                 0.,
-                &Vector3::new(0., 0., 0.),
-            );
+                &GeneratorConfig::default(),
+            ).is_ok();
             assert_eq!(can_spawn, false);
         }
 
@@ -491,6 +749,10 @@ mod tests {
                 position: Vector3::new(0., 0., 0.),
                 rotation: UnitQuaternion::from_euler_angles(std::f32::consts::FRAC_PI_2, 0., 0.),
                 bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 4., 0.5)),
+                collision_shape: None,
+                clearance: None,
+                collision_layer: 1,
+                collision_mask: u32::MAX,
                 movement: Movement {
                     baseline_velocity: Vector3::new(0., 0., -1.),
                     arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -511,6 +773,14 @@ mod tests {
                 translate_y: false,
                 translate_y_using_bounds: false,
                 translate_y_bounds: Vector2::new(0., 0.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
                 prefabs: vec![prefab0],
                 spawn_count: 10,
                 spawn_period: 1.0,
@@ -519,24 +789,31 @@ mod tests {
                 missed_spawns: 0,
                 is_spawn_period_strict: false,
                 last_spawn_attempt: 0.0,
+                spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+                spawn_period_curve: None,
+                speed_multiplier_curve: None,
+                next_spawn_time: 0.0,
+                variants: vec![],
             };
 
             let world = VisibleWorld {
                 world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+                shape: None,
+                flow_direction: -Vector3::z_axis(),
+                exclusion_volumes: vec![],
+                player_lanes: vec![],
             };
-            let obstacle = CollidableEntity {
-                movement_start_parameter: 0.0,
-                movement_end_parameter: 0.0,
-                spawn_position: Vector3::new(0., 0., 1.9),
-                spawn_rotation: UnitQuaternion::identity(),
-                spawn_feature_shift: nalgebra::zero(),
-                spawn_time: 0.0,
-                priority: 0,
-                prefab: Prefab {
+            let obstacle = CollidableEntity::new(
+                0,
+                Prefab {
                     prefab_id: 0,
                     position: nalgebra::zero(),
                     rotation: UnitQuaternion::from_euler_angles(std::f32::consts::FRAC_PI_2, 0., 0.),
                     bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 4., 0.5)),
+                    collision_shape: None,
+                    clearance: None,
+                    collision_layer: 1,
+                    collision_mask: u32::MAX,
                     movement: Movement {
                         baseline_velocity: Vector3::new(0., 0., -1.),
                         arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -551,14 +828,19 @@ mod tests {
                     },
 
                 },
-            };
+                nalgebra::zero(),
+                0.0,
+                0.0,
+                0.0,
+                0,
+            );
             let can_spawn = can_spawn_feature(
-                &feature0,
-                &VecDeque::from_iter([obstacle].iter().cloned()),
+                &FeaturePlan::new(&feature0, &world, &Vector3::new(0., 0., 0.), &GeneratorConfig::default()),
+                &obstacle_index(&world, &GeneratorConfig::default(), vec![obstacle]),
                 &world,
                 0.,
-                &Vector3::new(0., 0., 0.),
-            );
+                &GeneratorConfig::default(),
+            ).is_ok();
             assert_eq!(can_spawn, true);
         }
 
@@ -569,6 +851,10 @@ mod tests {
                 position: Vector3::new(0., 0., 0.),
                 rotation: UnitQuaternion::from_euler_angles(std::f32::consts::FRAC_PI_2, 0., 0.),
                 bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 4., 0.5)),
+                collision_shape: None,
+                clearance: None,
+                collision_layer: 1,
+                collision_mask: u32::MAX,
                 movement: Movement {
                     baseline_velocity: Vector3::new(0., 0., -2.),
                     arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -589,6 +875,14 @@ mod tests {
                 translate_y: false,
                 translate_y_using_bounds: false,
                 translate_y_bounds: Vector2::new(0., 0.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
                 prefabs: vec![prefab0],
                 spawn_count: 10,
                 spawn_period: 1.0,
@@ -597,24 +891,31 @@ mod tests {
                 missed_spawns: 0,
                 is_spawn_period_strict: false,
                 last_spawn_attempt: 0.0,
+                spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+                spawn_period_curve: None,
+                speed_multiplier_curve: None,
+                next_spawn_time: 0.0,
+                variants: vec![],
             };
 
             let world = VisibleWorld {
                 world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+                shape: None,
+                flow_direction: -Vector3::z_axis(),
+                exclusion_volumes: vec![],
+                player_lanes: vec![],
             };
-            let obstacle = CollidableEntity {
-                movement_start_parameter: 0.0,
-                movement_end_parameter: 0.0,
-                spawn_position: Vector3::new(0., 0., -5.),
-                spawn_rotation: UnitQuaternion::identity(),
-                spawn_feature_shift: nalgebra::zero(),
-                spawn_time: 0.0,
-                priority: 0,
-                prefab: Prefab {
+            let obstacle = CollidableEntity::new(
+                0,
+                Prefab {
                     prefab_id: 0,
                     position: nalgebra::zero(),
                     rotation: UnitQuaternion::from_euler_angles(std::f32::consts::FRAC_PI_2, 0., 0.),
                     bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 4., 0.5)),
+                    collision_shape: None,
+                    clearance: None,
+                    collision_layer: 1,
+                    collision_mask: u32::MAX,
                     movement: Movement {
                         baseline_velocity: Vector3::new(0., 0., -1.),
                         arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -629,14 +930,19 @@ mod tests {
                     },
 
                 },
-            };
+                nalgebra::zero(),
+                0.0,
+                0.0,
+                0.0,
+                0,
+            );
             let can_spawn = can_spawn_feature(
-                &feature0,
-                &VecDeque::from_iter([obstacle].iter().cloned()),
+                &FeaturePlan::new(&feature0, &world, &Vector3::new(0., 0., 0.), &GeneratorConfig::default()),
+                &obstacle_index(&world, &GeneratorConfig::default(), vec![obstacle]),
                 &world,
                 0.,
-                &Vector3::new(0., 0., 0.),
-            );
+                &GeneratorConfig::default(),
+            ).is_ok();
             assert_eq!(can_spawn, false);
         }
     }
@@ -653,6 +959,10 @@ mod tests {
                 position: Vector3::new(0., 0., 0.),
                 rotation: UnitQuaternion::identity(),
                 bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+                collision_shape: None,
+                clearance: None,
+                collision_layer: 1,
+                collision_mask: u32::MAX,
                 movement: Movement {
                     baseline_velocity: Vector3::new(0., 0., -1.),
                     arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -673,6 +983,14 @@ mod tests {
                 translate_y: false,
                 translate_y_using_bounds: false,
                 translate_y_bounds: Vector2::new(0., 0.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
                 prefabs: vec![prefab],
                 spawn_count: 1,
                 spawn_period: 1.,
@@ -681,20 +999,23 @@ mod tests {
                 missed_spawns: 0,
                 is_spawn_period_strict: false,
                 last_spawn_attempt: 0.0,
+                spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+                spawn_period_curve: None,
+                speed_multiplier_curve: None,
+                next_spawn_time: 0.0,
+                variants: vec![],
             };
-            let obstacle = CollidableEntity {
-                movement_start_parameter: -15.5,
-                movement_end_parameter: 0.0,
-                spawn_position: nalgebra::zero(),
-                spawn_rotation: UnitQuaternion::identity(),
-                spawn_feature_shift: nalgebra::zero(),
-                spawn_time: 5.0,
-                priority: 0,
-                prefab: Prefab {
+            let obstacle = CollidableEntity::new(
+                0,
+                Prefab {
                     prefab_id: 0,
                     position: nalgebra::zero(),
                     rotation: UnitQuaternion::identity(),
                     bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+                    collision_shape: None,
+                    clearance: None,
+                    collision_layer: 1,
+                    collision_mask: u32::MAX,
                     movement: Movement {
                         baseline_velocity: Vector3::new(0., -0.5, -0.5),
                         arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -709,17 +1030,26 @@ mod tests {
                     },
 
                 },
-            };
+                nalgebra::zero(),
+                5.0,
+                -15.5,
+                0.0,
+                0,
+            );
             let world = VisibleWorld {
                 world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+                shape: None,
+                flow_direction: -Vector3::z_axis(),
+                exclusion_volumes: vec![],
+                player_lanes: vec![],
             };
             let can_spawn = can_spawn_feature(
-                &feature,
-                &VecDeque::from_iter([obstacle].iter().cloned()),
+                &FeaturePlan::new(&feature, &world, &Vector3::new(0., 0., 0.), &GeneratorConfig::default()),
+                &obstacle_index(&world, &GeneratorConfig::default(), vec![obstacle]),
                 &world,
                 5.,
-                &Vector3::new(0., 0., 0.),
-            );
+                &GeneratorConfig::default(),
+            ).is_ok();
             assert_eq!(can_spawn, false);
         }
     }
@@ -736,6 +1066,10 @@ mod tests {
                 position: Vector3::new(0., 0., 0.),
                 rotation: UnitQuaternion::identity(),
                 bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+                collision_shape: None,
+                clearance: None,
+                collision_layer: 1,
+                collision_mask: u32::MAX,
                 movement: Movement {
                     baseline_velocity: Vector3::new(0., 0., -1.),
                     arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -756,6 +1090,14 @@ mod tests {
                 translate_y: false,
                 translate_y_using_bounds: false,
                 translate_y_bounds: Vector2::new(0., 0.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
                 prefabs: vec![prefab],
                 spawn_count: 1,
                 spawn_period: 1.,
@@ -764,20 +1106,23 @@ mod tests {
                 missed_spawns: 0,
                 is_spawn_period_strict: false,
                 last_spawn_attempt: 0.0,
+                spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+                spawn_period_curve: None,
+                speed_multiplier_curve: None,
+                next_spawn_time: 0.0,
+                variants: vec![],
             };
-            let obstacle = CollidableEntity {
-                movement_start_parameter: -100.25,
-                movement_end_parameter: 0.0,
-                spawn_position: Vector3::new(0., 100., 100.),
-                spawn_rotation: UnitQuaternion::identity(),
-                spawn_feature_shift: nalgebra::zero(),
-                spawn_time: 5.0,
-                priority: 0,
-                prefab: Prefab {
+            let obstacle = CollidableEntity::new(
+                0,
+                Prefab {
                     prefab_id: 0,
                     position: nalgebra::zero(),
                     rotation: UnitQuaternion::identity(),
                     bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+                    collision_shape: None,
+                    clearance: None,
+                    collision_layer: 1,
+                    collision_mask: u32::MAX,
                     movement: Movement {
                         baseline_velocity: Vector3::new(0., -1., -1.),
                         arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -792,17 +1137,26 @@ mod tests {
                     },
 
                 },
-            };
+                nalgebra::zero(),
+                5.0,
+                -100.25,
+                0.0,
+                0,
+            );
             let world = VisibleWorld {
                 world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(100., 100., 100.)),
+                shape: None,
+                flow_direction: -Vector3::z_axis(),
+                exclusion_volumes: vec![],
+                player_lanes: vec![],
             };
             let can_spawn = can_spawn_feature(
-                &feature,
-                &VecDeque::from_iter([obstacle].iter().cloned()),
+                &FeaturePlan::new(&feature, &world, &Vector3::new(0., 0., 0.), &GeneratorConfig::default()),
+                &obstacle_index(&world, &GeneratorConfig::default(), vec![obstacle]),
                 &world,
                 5.,
-                &Vector3::new(0., 0., 0.),
-            );
+                &GeneratorConfig::default(),
+            ).is_ok();
             assert_eq!(can_spawn, false);
         }
     }
@@ -819,6 +1173,10 @@ mod tests {
                 position: Vector3::new(0., 0., 0.),
                 rotation: UnitQuaternion::identity(),
                 bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+                collision_shape: None,
+                clearance: None,
+                collision_layer: 1,
+                collision_mask: u32::MAX,
                 movement: Movement {
                     baseline_velocity: Vector3::new(0., 0., -1.),
                     arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -839,6 +1197,14 @@ mod tests {
                 translate_y: false,
                 translate_y_using_bounds: false,
                 translate_y_bounds: Vector2::new(0., 0.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
                 prefabs: vec![prefab],
                 spawn_count: 1,
                 spawn_period: 1.,
@@ -847,20 +1213,23 @@ mod tests {
                 missed_spawns: 0,
                 is_spawn_period_strict: false,
                 last_spawn_attempt: 0.0,
+                spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+                spawn_period_curve: None,
+                speed_multiplier_curve: None,
+                next_spawn_time: 0.0,
+                variants: vec![],
             };
-            let obstacle = CollidableEntity {
-                movement_start_parameter: -100.5,
-                movement_end_parameter: 0.0,
-                spawn_position: nalgebra::zero(),
-                spawn_rotation: UnitQuaternion::identity(),
-                spawn_feature_shift: nalgebra::zero(),
-                spawn_time: 5.0,
-                priority: 0,
-                prefab: Prefab {
+            let obstacle = CollidableEntity::new(
+                0,
+                Prefab {
                     prefab_id: 0,
                     position: nalgebra::zero(),
                     rotation: UnitQuaternion::identity(),
                     bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+                    collision_shape: None,
+                    clearance: None,
+                    collision_layer: 1,
+                    collision_mask: u32::MAX,
                     movement: Movement {
                         baseline_velocity: Vector3::new(0., 0., -1.),
                         arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -874,17 +1243,26 @@ mod tests {
                         departure_rotation_strength: 0.0
                     },
                 },
-            };
+                nalgebra::zero(),
+                5.0,
+                -100.5,
+                0.0,
+                0,
+            );
             let world = VisibleWorld {
                 world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(100., 100., 100.)),
+                shape: None,
+                flow_direction: -Vector3::z_axis(),
+                exclusion_volumes: vec![],
+                player_lanes: vec![],
             };
             let can_spawn = can_spawn_feature(
-                &feature,
-                &VecDeque::from_iter([obstacle].iter().cloned()),
+                &FeaturePlan::new(&feature, &world, &Vector3::new(0., 0., 0.), &GeneratorConfig::default()),
+                &obstacle_index(&world, &GeneratorConfig::default(), vec![obstacle]),
                 &world,
                 5.,
-                &Vector3::new(0., 0., 0.),
-            );
+                &GeneratorConfig::default(),
+            ).is_ok();
             assert_eq!(can_spawn, false);
         }
 
@@ -895,6 +1273,10 @@ mod tests {
                 position: Vector3::new(0., 0., 0.),
                 rotation: UnitQuaternion::identity(),
                 bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+                collision_shape: None,
+                clearance: None,
+                collision_layer: 1,
+                collision_mask: u32::MAX,
                 movement: Movement {
                     baseline_velocity: Vector3::new(0., 0., -1.),
                     arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -915,6 +1297,14 @@ mod tests {
                 translate_y: false,
                 translate_y_using_bounds: false,
                 translate_y_bounds: Vector2::new(0., 0.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
                 prefabs: vec![prefab],
                 spawn_count: 1,
                 spawn_period: 1.,
@@ -923,20 +1313,23 @@ mod tests {
                 missed_spawns: 0,
                 is_spawn_period_strict: false,
                 last_spawn_attempt: 0.0,
+                spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+                spawn_period_curve: None,
+                speed_multiplier_curve: None,
+                next_spawn_time: 0.0,
+                variants: vec![],
             };
-            let obstacle = CollidableEntity {
-                movement_start_parameter: -100.5,
-                movement_end_parameter: 0.0,
-                spawn_position: Vector3::new(0., 0., 100.),
-                spawn_rotation: UnitQuaternion::identity(),
-                spawn_feature_shift: nalgebra::zero(),
-                spawn_time: 5.0,
-                priority: 0,
-                prefab: Prefab {
+            let obstacle = CollidableEntity::new(
+                0,
+                Prefab {
                     prefab_id: 0,
                     position: nalgebra::zero(),
                     rotation: UnitQuaternion::identity(),
                     bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+                    collision_shape: None,
+                    clearance: None,
+                    collision_layer: 1,
+                    collision_mask: u32::MAX,
                     movement: Movement {
                         baseline_velocity: Vector3::new(0., 0., -1.),
                         arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -951,17 +1344,26 @@ mod tests {
                     },
 
                 },
-            };
+                nalgebra::zero(),
+                5.0,
+                -100.5,
+                0.0,
+                0,
+            );
             let world = VisibleWorld {
                 world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(100., 100., 100.)),
+                shape: None,
+                flow_direction: -Vector3::z_axis(),
+                exclusion_volumes: vec![],
+                player_lanes: vec![],
             };
             let can_spawn = can_spawn_feature(
-                &feature,
-                &VecDeque::from_iter([obstacle].iter().cloned()),
+                &FeaturePlan::new(&feature, &world, &Vector3::new(0., 0., 0.), &GeneratorConfig::default()),
+                &obstacle_index(&world, &GeneratorConfig::default(), vec![obstacle]),
                 &world,
                 5.,
-                &Vector3::new(0., 0., 0.),
-            );
+                &GeneratorConfig::default(),
+            ).is_ok();
             assert_eq!(can_spawn, false);
         }
 
@@ -972,6 +1374,10 @@ mod tests {
                 position: Vector3::new(0., 0., 0.),
                 rotation: UnitQuaternion::identity(),
                 bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+                collision_shape: None,
+                clearance: None,
+                collision_layer: 1,
+                collision_mask: u32::MAX,
                 movement: Movement {
                     baseline_velocity: Vector3::new(0., 0., -1.),
                     arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -992,6 +1398,14 @@ mod tests {
                 translate_y: false,
                 translate_y_using_bounds: false,
                 translate_y_bounds: Vector2::new(0., 0.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
                 prefabs: vec![prefab],
                 spawn_count: 1,
                 spawn_period: 1.,
@@ -1000,20 +1414,23 @@ mod tests {
                 missed_spawns: 0,
                 is_spawn_period_strict: false,
                 last_spawn_attempt: 0.0,
+                spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+                spawn_period_curve: None,
+                speed_multiplier_curve: None,
+                next_spawn_time: 0.0,
+                variants: vec![],
             };
-            let obstacle = CollidableEntity {
-                movement_start_parameter: -100.25,
-                movement_end_parameter: 0.0,
-                spawn_position: Vector3::new(0., 100., 100.),
-                spawn_rotation: UnitQuaternion::identity(),
-                spawn_feature_shift: nalgebra::zero(),
-                spawn_time: 5.0,
-                priority: 0,
-                prefab: Prefab {
+            let obstacle = CollidableEntity::new(
+                0,
+                Prefab {
                     prefab_id: 0,
                     position: nalgebra::zero(),
                     rotation: UnitQuaternion::identity(),
                     bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+                    collision_shape: None,
+                    clearance: None,
+                    collision_layer: 1,
+                    collision_mask: u32::MAX,
                     movement: Movement {
                         baseline_velocity: Vector3::new(0., 0., -1.),
                         arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -1028,17 +1445,26 @@ mod tests {
                     },
 
                 },
-            };
+                nalgebra::zero(),
+                5.0,
+                -100.25,
+                0.0,
+                0,
+            );
             let world = VisibleWorld {
                 world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(100., 100., 100.)),
+                shape: None,
+                flow_direction: -Vector3::z_axis(),
+                exclusion_volumes: vec![],
+                player_lanes: vec![],
             };
             let can_spawn = can_spawn_feature(
-                &feature,
-                &VecDeque::from_iter([obstacle].iter().cloned()),
+                &FeaturePlan::new(&feature, &world, &Vector3::new(0., 0., 0.), &GeneratorConfig::default()),
+                &obstacle_index(&world, &GeneratorConfig::default(), vec![obstacle]),
                 &world,
                 5.,
-                &Vector3::new(0., 0., 0.),
-            );
+                &GeneratorConfig::default(),
+            ).is_ok();
             assert_eq!(can_spawn, false);
         }
 
@@ -1105,4 +1531,270 @@ mod tests {
             // assert_eq!(can_spawn, true);
         }
     }
-}
\ No newline at end of file
+    mod collision_shapes {
+        use super::*;
+        use nalgebra::{UnitQuaternion, Unit};
+        use crate::generator::Movement;
+        use crate::generator::types::{CollisionShape, SpawnRejection};
+
+        fn prefab(collision_shape: Option<CollisionShape>, velocity: Vector3<f32>) -> Prefab {
+            Prefab {
+                prefab_id: 0,
+                position: Vector3::new(0., 0., 0.),
+                rotation: UnitQuaternion::identity(),
+                bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+                collision_shape,
+                clearance: None,
+                collision_layer: 1,
+                collision_mask: u32::MAX,
+                movement: Movement {
+                    baseline_velocity: velocity,
+                    arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                    approach_arc_angle: 0.0,
+                    approach_arc_center_distance: 0.0,
+                    approach_arc_radius: 0.0,
+                    approach_rotation_strength: 0.,
+                    departure_arc_angle: 0.0,
+                    departure_arc_center_distance: 0.0,
+                    departure_arc_radius: 0.0,
+                    departure_rotation_strength: 0.0,
+                },
+            }
+        }
+
+        fn can_spawn_catching_up_diagonal_obstacle(collision_shape: Option<CollisionShape>, config: &GeneratorConfig) -> bool {
+            can_spawn_catching_up_diagonal_obstacle_prefab(
+                prefab(collision_shape.clone(), Vector3::new(0., 0., -1.)),
+                prefab(collision_shape, Vector3::new(0., 0., -0.5)),
+                config,
+            )
+        }
+
+        fn can_spawn_catching_up_diagonal_obstacle_prefab(feature_prefab: Prefab, obstacle_prefab: Prefab, config: &GeneratorConfig) -> bool {
+            spawn_catching_up_diagonal_obstacle_prefab(feature_prefab, obstacle_prefab, config).is_ok()
+        }
+
+        fn spawn_catching_up_diagonal_obstacle_prefab(feature_prefab: Prefab, obstacle_prefab: Prefab, config: &GeneratorConfig) -> Result<(), SpawnRejection> {
+            let feature = Feature {
+                translate_x: false,
+                translate_x_using_bounds: false,
+                translate_x_bounds: Vector2::new(0., 0.),
+                translate_y: false,
+                translate_y_using_bounds: false,
+                translate_y_bounds: Vector2::new(0., 0.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
+                prefabs: vec![feature_prefab],
+                spawn_count: 1,
+                spawn_period: 1.,
+                trigger_time: 10.,
+                priority: 0,
+                missed_spawns: 0,
+                is_spawn_period_strict: false,
+                last_spawn_attempt: 0.0,
+                spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+                spawn_period_curve: None,
+                speed_multiplier_curve: None,
+                next_spawn_time: 0.0,
+                variants: vec![],
+            };
+            let obstacle = CollidableEntity::new(
+                3,
+                obstacle_prefab,
+                Vector3::new(0.8, 0.8, 0.),
+                0.0,
+                -10.5,
+                0.0,
+                0,
+            );
+            let world = VisibleWorld {
+                world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+                shape: None,
+                flow_direction: -Vector3::z_axis(),
+                exclusion_volumes: vec![],
+                player_lanes: vec![],
+            };
+            can_spawn_feature(
+                &FeaturePlan::new(&feature, &world, &Vector3::new(0., 0., 0.), config),
+                &obstacle_index(&world, config, vec![obstacle]),
+                &world,
+                0.,
+                config,
+            )
+        }
+
+        #[test]
+        fn test_cannot_spawn_if_bounding_boxes_collide() {
+            assert_eq!(can_spawn_catching_up_diagonal_obstacle(None, &GeneratorConfig::default()), false);
+        }
+
+        #[test]
+        fn test_rejection_names_colliding_obstacle() {
+            let rejection = spawn_catching_up_diagonal_obstacle_prefab(
+                prefab(None, Vector3::new(0., 0., -1.)),
+                prefab(None, Vector3::new(0., 0., -0.5)),
+                &GeneratorConfig::default(),
+            );
+            match rejection {
+                Err(SpawnRejection::ObstacleCollision { prefab_index, obstacle_entity_id, time_of_impact, .. }) => {
+                    assert_eq!(prefab_index, 0);
+                    assert_eq!(obstacle_entity_id, 3);
+                    assert!(time_of_impact > 0.);
+                }
+                rejection => panic!("Unexpected result {:?}", rejection),
+            }
+        }
+
+        #[test]
+        fn test_can_spawn_if_balls_pass_diagonally() {
+            assert_eq!(can_spawn_catching_up_diagonal_obstacle(Some(CollisionShape::Ball { radius: 0.5 }), &GeneratorConfig::default()), true);
+        }
+
+        #[test]
+        fn test_cannot_spawn_if_capsules_collide() {
+            let capsule = CollisionShape::Capsule { half_height: 0.5, radius: 0.5 };
+            assert_eq!(can_spawn_catching_up_diagonal_obstacle(Some(capsule), &GeneratorConfig::default()), false);
+        }
+
+        #[test]
+        fn test_cannot_spawn_if_balls_pass_within_clearance() {
+            let ball = CollisionShape::Ball { radius: 0.5 };
+            let config = GeneratorConfig { clearance: 0.2, ..GeneratorConfig::default() };
+            assert_eq!(can_spawn_catching_up_diagonal_obstacle(Some(ball), &config), false);
+        }
+        #[test]
+        fn test_can_spawn_if_layers_do_not_interact() {
+            let mut feature_prefab = prefab(None, Vector3::new(0., 0., -1.));
+            feature_prefab.collision_layer = 0b10;
+            let mut obstacle_prefab = prefab(None, Vector3::new(0., 0., -0.5));
+            obstacle_prefab.collision_mask = 0b01;
+            assert_eq!(can_spawn_catching_up_diagonal_obstacle_prefab(feature_prefab.clone(), obstacle_prefab.clone(), &GeneratorConfig::default()), true);
+            obstacle_prefab.collision_mask = 0b11;
+            assert_eq!(can_spawn_catching_up_diagonal_obstacle_prefab(feature_prefab, obstacle_prefab, &GeneratorConfig::default()), false);
+        }
+    }
+    mod exclusion_volumes {
+        use super::*;
+        use nalgebra::{UnitQuaternion, Unit};
+        use crate::generator::Movement;
+        use crate::generator::types::{CollisionShape, ExclusionVolume, SpawnRejection};
+
+        fn can_spawn_with_exclusion_volume(position: Vector3<f32>, active_from: Option<f32>, active_until: Option<f32>) -> bool {
+            spawn_with_exclusion_volume(position, active_from, active_until).is_ok()
+        }
+
+        fn spawn_with_exclusion_volume(position: Vector3<f32>, active_from: Option<f32>, active_until: Option<f32>) -> Result<(), SpawnRejection> {
+            let prefab = Prefab {
+                prefab_id: 0,
+                position: Vector3::new(0., 0., 0.),
+                rotation: UnitQuaternion::identity(),
+                bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+                collision_shape: None,
+                clearance: None,
+                collision_layer: 1,
+                collision_mask: u32::MAX,
+                movement: Movement {
+                    baseline_velocity: Vector3::new(0., 0., -1.),
+                    arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                    approach_arc_angle: 0.0,
+                    approach_arc_center_distance: 0.0,
+                    approach_arc_radius: 0.0,
+                    approach_rotation_strength: 0.,
+                    departure_arc_angle: 0.0,
+                    departure_arc_center_distance: 0.0,
+                    departure_arc_radius: 0.0,
+                    departure_rotation_strength: 0.0,
+                },
+            };
+            let feature = Feature {
+                translate_x: false,
+                translate_x_using_bounds: false,
+                translate_x_bounds: Vector2::new(0., 0.),
+                translate_y: false,
+                translate_y_using_bounds: false,
+                translate_y_bounds: Vector2::new(0., 0.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
+                prefabs: vec![prefab],
+                spawn_count: 1,
+                spawn_period: 1.,
+                trigger_time: 0.,
+                priority: 0,
+                missed_spawns: 0,
+                is_spawn_period_strict: false,
+                last_spawn_attempt: 0.0,
+                spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+                spawn_period_curve: None,
+                speed_multiplier_curve: None,
+                next_spawn_time: 0.0,
+                variants: vec![],
+            };
+            let world = VisibleWorld {
+                world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+                shape: None,
+                flow_direction: -Vector3::z_axis(),
+                exclusion_volumes: vec![ExclusionVolume {
+                    shape: CollisionShape::Ball { radius: 1. },
+                    position,
+                    rotation: UnitQuaternion::identity(),
+                    active_from,
+                    active_until,
+                }],
+                player_lanes: vec![],
+            };
+            can_spawn_feature(
+                &FeaturePlan::new(&feature, &world, &Vector3::new(0., 0., 0.), &GeneratorConfig::default()),
+                &ObstacleIndex::new(&world, &GeneratorConfig::default()),
+                &world,
+                0.,
+                &GeneratorConfig::default(),
+            )
+        }
+
+        #[test]
+        fn test_cannot_spawn_through_static_volume() {
+            assert_eq!(can_spawn_with_exclusion_volume(Vector3::new(0., 0., -5.), None, None), false);
+        }
+
+        #[test]
+        fn test_rejection_describes_volume_collision() {
+            match spawn_with_exclusion_volume(Vector3::new(0., 0., -5.), None, None) {
+                Err(SpawnRejection::ExclusionVolumeCollision { prefab_index, volume_index, time_of_impact, contact_point }) => {
+                    assert_eq!(prefab_index, 0);
+                    assert_eq!(volume_index, 0);
+                    // The prefab enters the world at z = 10.5 and its front touches the ball at z = -4
+                    assert_relative_eq!(time_of_impact, 14., epsilon = 1.0e-2);
+                    assert_relative_eq!(contact_point, Point3::new(0., 0., -4.), epsilon = 1.0e-2);
+                }
+                rejection => panic!("Unexpected result {:?}", rejection),
+            }
+        }
+
+        #[test]
+        fn test_can_spawn_next_to_static_volume() {
+            assert_eq!(can_spawn_with_exclusion_volume(Vector3::new(3., 0., -5.), None, None), true);
+        }
+
+        #[test]
+        fn test_can_spawn_through_volume_that_disappears_before_passing() {
+            assert_eq!(can_spawn_with_exclusion_volume(Vector3::new(0., 0., -5.), None, Some(5.)), true);
+        }
+
+        #[test]
+        fn test_cannot_spawn_through_volume_that_appears_before_passing() {
+            assert_eq!(can_spawn_with_exclusion_volume(Vector3::new(0., 0., -5.), Some(14.), None), false);
+        }
+    }
+}
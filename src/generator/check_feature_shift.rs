@@ -0,0 +1,34 @@
+use crate::generator::types::{FeaturePlan, ObstacleIndex, VisibleWorld, GeneratorConfig, SpawnConstraint, SpawnRejection};
+use crate::generator::can_spawn_feature::can_spawn_feature;
+use crate::generator::blocks_all_player_lanes::blocks_all_player_lanes;
+
+/// Runs all checks deciding if a feature can be spawned with a chosen shift. Spawn constraints are
+/// checked first, then collisions and finally player lanes. Returns the reason of the first failed
+/// check
+///
+/// * `plan` - feature prepared to be spawned with a chosen shift
+/// * `obstacles` - entities that are still inside the visible world
+/// * `world` - visible world
+/// * `spawn_time` - time at which the feature would be spawned
+/// * `constraints` - user constraints that have to accept the spawn
+/// * `config` - generator settings
+///
+pub fn check_feature_shift(
+    plan: &FeaturePlan,
+    obstacles: &ObstacleIndex,
+    world: &VisibleWorld,
+    spawn_time: f32,
+    constraints: &[&dyn SpawnConstraint],
+    config: &GeneratorConfig,
+) -> Result<(), SpawnRejection> {
+    if let Some(constraint_index) = constraints
+        .iter()
+        .position(|constraint| !constraint.can_spawn(plan.feature, &plan.feature_shift, spawn_time, obstacles.entities())) {
+        return Err(SpawnRejection::ConstraintFailed { constraint_index });
+    }
+    can_spawn_feature(plan, obstacles, world, spawn_time, config)?;
+    if blocks_all_player_lanes(plan, obstacles, world, spawn_time, config) {
+        return Err(SpawnRejection::AllPlayerLanesBlocked);
+    }
+    Ok(())
+}
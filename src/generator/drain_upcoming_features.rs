@@ -5,20 +5,20 @@ use nalgebra::Vector3;
 /// Activated not yet active features that should start spawning at a given travelled distance
 /// and adds them to active entities queue
 ///
-/// * `upcoming_features` - features that didn't yet start to spawn
+/// * `upcoming_features` - features that didn't yet start to spawn, each paired with the time at
+///                         which it becomes active. The time is computed once by the caller as
+///                         it takes ray casts against the world
 /// * `active_features` - features that are actively spawning
-/// * `distance_travelled` - distance travelled in a given world
-pub fn drain_upcoming_features(upcoming_features: &mut Vec<Feature>,
+/// * `time_travelled` - time travelled in a given world
+pub fn drain_upcoming_features(upcoming_features: &mut Vec<(f32, Feature)>,
                                active_features: &mut Vec<Feature>,
-                               world: &VisibleWorld,
                                time_travelled: f32,
 ) {
     if upcoming_features.is_empty() {
         return;
     }
-    upcoming_features.retain(|feature| {
-        let trigger_adjusted = feature.trigger_time - feature.max_approach_time(&world, &Vector3::new(0., 0., 0.)) - feature.priority as f32;
-        if time_travelled >= trigger_adjusted {
+    upcoming_features.retain(|(activation_time, feature)| {
+        if time_travelled >= *activation_time {
             active_features.push(feature.clone());
             false
         } else {
@@ -27,10 +27,19 @@ pub fn drain_upcoming_features(upcoming_features: &mut Vec<Feature>,
     })
 }
 
+/// Returns the time at which a feature has to become active so that its entities reach the world
+/// at its trigger time even at the lowest speed the feature reaches
+///
+/// * `feature` - feature to activate
+/// * `world` - visible world
+pub fn activation_time(feature: &Feature, world: &VisibleWorld) -> f32 {
+    feature.trigger_time - feature.slowest_approach_time(world, &Vector3::new(0., 0., 0.)) - feature.priority as f32
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::generator::types::{Feature, Prefab, Movement};
-    use super::drain_upcoming_features;
+    use crate::generator::types::{Feature, Prefab, Movement, SpawnPeriodDistribution};
+    use super::{drain_upcoming_features, activation_time};
 
     use nalgebra::{Vector3, Point3, Vector2, UnitQuaternion, Unit};
     use ncollide3d::bounding_volume::AABB;
@@ -40,12 +49,20 @@ mod tests {
     fn test_drain_upcoming_features() {
         let world = VisibleWorld {
             world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(20., 20., 20.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
         let prefab0 = Prefab {
             prefab_id: 0,
             position: Vector3::new(0.0, 0.0, 0.0),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -1.0),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -66,6 +83,14 @@ mod tests {
             translate_y: false,
             translate_y_using_bounds: false,
             translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             prefabs: vec![prefab0],
             spawn_count: 1,
             spawn_period: 1.0,
@@ -74,6 +99,11 @@ mod tests {
             missed_spawns: 0,
             is_spawn_period_strict: false,
             last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
         let feature1 = Feature {
             trigger_time: 120.0,
@@ -88,7 +118,10 @@ mod tests {
             ..feature0.clone()
         };
 
-        let mut upcoming_features: Vec<Feature> = vec![feature0.clone(), feature1.clone(), feature2.clone(), feature3.clone()];
+        let mut upcoming_features: Vec<(f32, Feature)> = [feature0.clone(), feature1.clone(), feature2.clone(), feature3.clone()]
+            .iter()
+            .map(|feature| (activation_time(feature, &world), feature.clone()))
+            .collect();
         let mut active_features: Vec<Feature> = Vec::new();
         let mut distance_travelled = 0.0_f32;
 
@@ -96,7 +129,6 @@ mod tests {
         drain_upcoming_features(
             &mut upcoming_features,
             &mut active_features,
-            &world,
             distance_travelled,
         );
 
@@ -110,7 +142,6 @@ mod tests {
         drain_upcoming_features(
             &mut upcoming_features,
             &mut active_features,
-            &world,
             distance_travelled,
         );
 
@@ -122,7 +153,6 @@ mod tests {
         drain_upcoming_features(
             &mut upcoming_features,
             &mut active_features,
-            &world,
             distance_travelled,
         );
 
@@ -134,7 +164,6 @@ mod tests {
         drain_upcoming_features(
             &mut upcoming_features,
             &mut active_features,
-            &world,
             distance_travelled,
         );
 
@@ -146,7 +175,6 @@ mod tests {
         drain_upcoming_features(
             &mut upcoming_features,
             &mut active_features,
-            &world,
             distance_travelled,
         );
 
@@ -0,0 +1,78 @@
+use crate::generator::types::CollidableEntity;
+use nalgebra::Isometry3;
+use rayon::prelude::*;
+
+/// Calculates transforms of all entities that are inside a visible world at a given time. An entity
+/// is considered to be inside from its `spawn_time` until it leaves the visible world
+///
+/// * `entities` - entities returned from #generate
+/// * `time` - time at which transforms are evaluated
+///
+/// Returns pairs of an index into `entities` and a transform of an entity at that index
+pub fn entities_at_time(entities: &[CollidableEntity], time: f32) -> Vec<(usize, Isometry3<f32>)> {
+    entities
+        .par_iter()
+        .enumerate()
+        .filter(|(_, entity)| entity.is_alive_at(time))
+        .map(|(index, entity)| (index, entity.transform_at_time(time)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::types::{Prefab, Movement};
+    use nalgebra::{Vector3, Point3, UnitQuaternion, Unit};
+    use ncollide3d::bounding_volume::AABB;
+
+    #[test]
+    fn test_entities_at_time() {
+        let entity = CollidableEntity::new(
+            0,
+            Prefab {
+                prefab_id: 0,
+                position: nalgebra::zero(),
+                rotation: UnitQuaternion::identity(),
+                bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+                collision_shape: None,
+                clearance: None,
+                collision_layer: 1,
+                collision_mask: u32::MAX,
+                movement: Movement {
+                    baseline_velocity: Vector3::new(0., 0., -1.),
+                    arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                    approach_arc_angle: 0.0,
+                    approach_arc_center_distance: 0.0,
+                    approach_arc_radius: 0.0,
+                    approach_rotation_strength: 0.,
+                    departure_arc_angle: 0.0,
+                    departure_arc_center_distance: 0.0,
+                    departure_arc_radius: 0.0,
+                    departure_rotation_strength: 0.0,
+                },
+            },
+            nalgebra::zero(),
+            0.0,
+            -10.,
+            10.,
+            0,
+        );
+        let late_entity = CollidableEntity {
+            entity_id: 1,
+            ..entity.delayed(15.)
+        };
+
+        let transforms = entities_at_time(&[entity.clone(), late_entity.clone()], 5.);
+        assert_eq!(transforms.len(), 1);
+        assert_eq!(transforms[0].0, 0);
+        assert_relative_eq!(transforms[0].1.translation.vector, Vector3::new(0., 0., 5.));
+
+        let transforms = entities_at_time(&[entity.clone(), late_entity.clone()], 22.);
+        assert_eq!(transforms.len(), 1);
+        assert_eq!(transforms[0].0, 1);
+        assert_relative_eq!(transforms[0].1.translation.vector, Vector3::new(0., 0., 3.));
+
+        let transforms = entities_at_time(&[entity.clone(), late_entity.clone()], 40.);
+        assert!(transforms.is_empty());
+    }
+}
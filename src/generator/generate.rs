@@ -1,14 +1,18 @@
-use std::collections::VecDeque;
-use crate::{Feature, CollidableEntity, VisibleWorld};
+use crate::{Feature, CollidableEntity, VisibleWorld, GeneratorConfig, SpawnConstraint, SpawnRejection, GenerationReport, RejectedSpawn, ShiftSampling, SpawnPeriodDistribution};
+use nalgebra::Vector3;
 use rand::{RngCore, Rng};
 use rand::seq::SliceRandom;
-use crate::generator::drain_upcoming_features::drain_upcoming_features;
+use crate::generator::drain_upcoming_features::{drain_upcoming_features, activation_time};
+use crate::generator::types::{FeaturePlan, ObstacleIndex, FormationTransform};
 use crate::generator::trim_active_features::trim_active_features;
 use crate::generator::trim_obstacles::trim_obstacles;
-use crate::generator::calculate_feature_shift::calculate_feature_shift;
-use crate::generator::can_spawn_feature::can_spawn_feature;
+use crate::generator::calculate_feature_shift_candidates::calculate_feature_shift_candidates;
+use crate::generator::check_feature_shift::check_feature_shift;
 use crate::generator::spawn_feature::spawn_feature;
+use crate::generator::calculate_formation_transform::calculate_formation_transform;
+use crate::generator::validate::validate;
 use itertools::Itertools;
+use std::borrow::Cow;
 use std::cmp::Ordering::Equal;
 
 const STEP: f32 = 0.025;
@@ -19,37 +23,89 @@ const STEP: f32 = 0.025;
 ///             is no guarantee that entities won't intersect
 /// * `features` - a list of possible level features that can be spawned. It is guaranteed that
 ///             all of them will eventually be spawned before algorithm stops
+/// * `config` - settings controlling how features are placed
 /// * `rng` - random number generator used during generation
+///
+/// Returns a list of human-readable problems instead if the world or any of the features is
+/// invalid and generating would produce meaningless results
 pub fn generate(
     world: &VisibleWorld,
     features: &[Feature],
+    config: &GeneratorConfig,
+    rng: &mut impl RngCore,
+) -> Result<Vec<CollidableEntity>, Vec<String>> {
+    generate_with_constraints(world, features, config, &[], rng)
+}
+
+/// Randomly generates non-intersecting entities that satisfy custom spawn constraints. A feature
+/// is only spawned when all of the constraints accept it
+///
+/// * `world` - a bounded volume in which entities paths are non-intersecting. Outside of it there
+///             is no guarantee that entities won't intersect
+/// * `features` - a list of possible level features that can be spawned. A feature rejected by a
+///             constraint is retried later, so every feature must eventually be accepted
+/// * `config` - settings controlling how features are placed
+/// * `constraints` - game-specific rules checked before spawning each feature
+/// * `rng` - random number generator used during generation
+///
+/// Returns a list of human-readable problems instead if the world or any of the features is
+/// invalid and generating would produce meaningless results
+pub fn generate_with_constraints(
+    world: &VisibleWorld,
+    features: &[Feature],
+    config: &GeneratorConfig,
+    constraints: &[&dyn SpawnConstraint],
     rng: &mut impl RngCore,
-) -> Vec<CollidableEntity> {
-    let world_json = serde_json::to_string(&world).unwrap();
-    let features_json = serde_json::to_string(&features).unwrap();
-    println!("WORLD DESCRIPTION:");
-    println!("{}", &world_json);
-    println!("FEATURES DESCRIPTION:");
-    println!("{}", &features_json);
+) -> Result<Vec<CollidableEntity>, Vec<String>> {
+    generate_report(world, features, config, constraints, rng).map(|report| report.entities)
+}
+
+/// Randomly generates non-intersecting entities like `generate_with_constraints` and reports why
+/// spawn attempts were rejected along the way
+///
+/// * `world` - a bounded volume in which entities paths are non-intersecting
+/// * `features` - a list of possible level features that can be spawned
+/// * `config` - settings controlling how features are placed
+/// * `constraints` - game-specific rules checked before spawning each feature
+/// * `rng` - random number generator used during generation
+///
+/// Returns a list of human-readable problems instead if the world or any of the features is
+/// invalid and generating would produce meaningless results
+pub fn generate_report(
+    world: &VisibleWorld,
+    features: &[Feature],
+    config: &GeneratorConfig,
+    constraints: &[&dyn SpawnConstraint],
+    rng: &mut impl RngCore,
+) -> Result<GenerationReport, Vec<String>> {
+    let problems = validate(world, features, config);
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+    if features.is_empty() {
+        return Ok(GenerationReport { entities: Vec::new(), rejected_spawns: Vec::new() });
+    }
     let mut upcoming_features: Vec<Feature> = Vec::from(features);
     let mut active_features: Vec<Feature> = Vec::new();
 
     let mut generated_entities: Vec<CollidableEntity> = Vec::new();
-    let mut obstacles: VecDeque<CollidableEntity> = VecDeque::new();
+    let mut rejected_spawns: Vec<RejectedSpawn> = Vec::new();
+    // Features slowed down by their speed curves approach longer, so the delay and the priorities
+    // are computed for the slowest spawns
     let highest_time_to_travel = features
         .iter()
-        .map(|item| item.max_approach_time(&world, &nalgebra::zero()))
+        .map(|item| item.slowest_approach_time(&world, &nalgebra::zero()))
         .sorted_by(|a, b| a.partial_cmp(b).unwrap_or(Equal))
         .last()
         .unwrap();
     upcoming_features
         .iter_mut()
         .for_each(|item| {
-            item.priority += (highest_time_to_travel - item.max_approach_time(&world, &nalgebra::zero())) as i32;
+            item.priority += (highest_time_to_travel - item.slowest_approach_time(&world, &nalgebra::zero())) as i32;
         });
     let highest_spawn_delay = features
         .iter()
-        .map(|item| item.max_approach_time(&world, &nalgebra::zero()) + item.priority as f32)
+        .map(|item| item.slowest_approach_time(&world, &nalgebra::zero()) + item.priority as f32)
         .sorted_by(|a, b| a.partial_cmp(b).unwrap_or(Equal))
         .last()
         .unwrap();
@@ -58,6 +114,14 @@ pub fn generate(
         .for_each(|item| {
             item.trigger_time += highest_spawn_delay;
         });
+    // Exclusion volumes and player lanes are timed in level time, which lags behind travelled time
+    // by the delay
+    let world = &world.delayed(highest_spawn_delay);
+    let mut upcoming_features: Vec<(f32, Feature)> = upcoming_features
+        .into_iter()
+        .map(|feature| (activation_time(&feature, world), feature))
+        .collect();
+    let mut obstacles = ObstacleIndex::new(world, config);
 
     let mut time_travelled = 0.;
 
@@ -67,46 +131,89 @@ pub fn generate(
             break 'main_loop;
         }
         time_travelled += STEP;
-        drain_upcoming_features(&mut upcoming_features, &mut active_features, &world, time_travelled);
+        drain_upcoming_features(&mut upcoming_features, &mut active_features, time_travelled);
         trim_active_features(&mut active_features);
         trim_obstacles(&mut obstacles, time_travelled);
         active_features.shuffle(rng);
 
         'features_loop: for feature in &mut active_features {
+            // Curves are given in level time
+            let level_time = time_travelled - highest_spawn_delay;
+            let spawn_period = feature.spawn_period_at(level_time);
             if feature.last_spawn_attempt == f32::MIN {
-                feature.last_spawn_attempt = time_travelled - feature.spawn_period;
+                feature.last_spawn_attempt = time_travelled - spawn_period;
             }
-            let should_try_spawning = if feature.is_spawn_period_strict {
-                time_travelled >= feature.last_spawn_attempt + feature.spawn_period
+            let is_adaptive = feature.spawn_period_distribution == SpawnPeriodDistribution::Adaptive;
+            let should_try_spawning = if !is_adaptive {
+                time_travelled >= feature.next_spawn_time
+            } else if feature.is_spawn_period_strict {
+                time_travelled >= feature.last_spawn_attempt + spawn_period
             } else {
-                let chance_to_spawn = ((STEP * (1 + feature.missed_spawns) as f32 / feature.spawn_period) as f64).min(1.0);
+                let chance_to_spawn = ((STEP * (1 + feature.missed_spawns) as f32 / spawn_period) as f64).min(1.0);
                 rng.gen_bool(chance_to_spawn)
             };
             if !should_try_spawning {
                 continue 'features_loop;
             }
-            let spawn_time = if feature.is_spawn_period_strict { feature.last_spawn_attempt + feature.spawn_period } else { time_travelled };
-            let feature_shift = calculate_feature_shift(rng, &world, feature);
-            let can_spawn = can_spawn_feature(
-                &feature,
-                &obstacles,
-                &world,
-                spawn_time,
-                &feature_shift,
-            );
-            if can_spawn {
-                spawn_feature(
-                    &feature,
-                    &mut obstacles,
-                    &mut generated_entities,
-                    spawn_time,
-                    &world,
-                    &feature_shift,
-                );
+            let spawn_time = if is_adaptive && feature.is_spawn_period_strict { feature.last_spawn_attempt + spawn_period } else { time_travelled };
+            let prefabs = match feature.pick_variant(rng, level_time) {
+                Some(prefabs) => prefabs,
+                None => {
+                    rejected_spawns.push(RejectedSpawn {
+                        spawn_time: spawn_time - highest_spawn_delay,
+                        feature_shift: nalgebra::zero(),
+                        prefab_ids: Vec::new(),
+                        rejection: SpawnRejection::NoVariantAvailable,
+                    });
+                    feature.missed_spawns += 1;
+                    feature.last_spawn_attempt = spawn_time;
+                    continue 'features_loop;
+                }
+            };
+            let speed_multiplier = feature.speed_multiplier_at(level_time);
+            let transform = calculate_formation_transform(rng, feature);
+            // Prefabs are copied only if the spawn differs from the feature as it is
+            let variant = if feature.variants.is_empty() && speed_multiplier == 1. && transform == FormationTransform::default() {
+                Cow::Borrowed(&*feature)
+            } else {
+                Cow::Owned(feature.spawned_variant(prefabs, speed_multiplier, &transform, &world))
+            };
+            // Free shifts are spawned as soon as they are found unless the best fitting one is wanted
+            let mut accepted: Option<(f32, FeaturePlan)> = None;
+            let mut first_rejection: Option<(Vector3<f32>, SpawnRejection)> = None;
+            for feature_shift in calculate_feature_shift_candidates(rng, &world, &variant, &obstacles, spawn_time, config) {
+                let plan = FeaturePlan { transform, ..FeaturePlan::new(&variant, &world, &feature_shift, config) };
+                match check_feature_shift(&plan, &obstacles, &world, spawn_time, constraints, config) {
+                    Ok(()) if config.shift_sampling == ShiftSampling::BestFit => {
+                        let gap = plan.lateral_gap(obstacles.entities(), &world, spawn_time);
+                        if accepted.as_ref().map_or(true, |(best_gap, _)| gap < *best_gap) {
+                            accepted = Some((gap, plan));
+                        }
+                    }
+                    Ok(()) => {
+                        accepted = Some((0., plan));
+                        break;
+                    }
+                    Err(rejection) => {
+                        if first_rejection.is_none() {
+                            first_rejection = Some((feature_shift, rejection));
+                        }
+                    }
+                }
+            }
+            if let Some((_, plan)) = accepted {
+                spawn_feature(&plan, &mut obstacles, &mut generated_entities, spawn_time);
                 feature.spawn_count -= 1;
                 feature.missed_spawns = 0;
                 feature.last_spawn_attempt = spawn_time;
-            } else {
+                feature.next_spawn_time = spawn_time + feature.spawn_period_distribution.sample_interval(rng, spawn_period);
+            } else if let Some((feature_shift, rejection)) = first_rejection {
+                rejected_spawns.push(RejectedSpawn {
+                    spawn_time: spawn_time - highest_spawn_delay,
+                    feature_shift,
+                    prefab_ids: variant.prefabs.iter().map(|prefab| prefab.prefab_id).collect(),
+                    rejection,
+                });
                 feature.missed_spawns += 1;
                 feature.last_spawn_attempt = spawn_time;
             }
@@ -115,7 +222,7 @@ pub fn generate(
     generated_entities
         .iter_mut()
         .for_each(|item| {
-            item.spawn_time -= highest_spawn_delay;
+            *item = item.delayed(-highest_spawn_delay);
         });
-    generated_entities
+    Ok(GenerationReport { entities: generated_entities, rejected_spawns })
 }
@@ -3,12 +3,22 @@ mod drain_upcoming_features;
 mod trim_active_features;
 mod trim_obstacles;
 mod calculate_feature_shift;
+mod calculate_feature_shift_candidates;
+mod calculate_free_feature_shifts;
 mod can_spawn_feature;
 mod spawn_feature;
 mod generate;
 mod calculate_prefabs_spawn_bounds;
 mod bi_arc_motion;
+mod entities_at_time;
+mod validate;
+mod blocks_all_player_lanes;
+mod check_feature_shift;
+mod calculate_formation_transform;
 
-pub use self::types::{VisibleWorld, Feature, Prefab, CollidableEntity, Movement};
+pub use self::types::{VisibleWorld, Feature, FeatureVariant, FormationTransform, SpawnPeriodDistribution, Curve, CurveKeyframe, CurveInterpolation, Prefab, CollidableEntity, Movement, CollisionShape, CompoundShapePart, GeneratorConfig, ExclusionVolume, PlayerLane, WorldShape, WorldShapeKind, SpawnConstraint, SpawnRejection, GenerationReport, RejectedSpawn, ShiftSampling};
+pub(crate) use self::types::{default_flow_direction, is_entity_alive_at};
 pub use self::bi_arc_motion::{BiArcCurveMotion};
-pub use self::generate::generate;
+pub use self::generate::{generate, generate_with_constraints, generate_report};
+pub use self::entities_at_time::entities_at_time;
+pub use self::validate::validate;
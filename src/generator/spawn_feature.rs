@@ -1,65 +1,48 @@
-use std::collections::VecDeque;
-use crate::generator::types::{Feature, CollidableEntity, VisibleWorld};
-use nalgebra::{Vector3, Isometry3, Translation3, };
-use crate::generator::bi_arc_motion::{BiArcCurveMotion};
-use ncollide3d::interpolation::RigidMotion;
+use crate::generator::types::{CollidableEntity, FeaturePlan, ObstacleIndex};
 
-/// Spawns entities belonging to a feature at a given time of travel in a given world
-/// * `feature` - feature to spawn
+/// Spawns entities belonging to a feature at a given time of travel in a given world. Prefabs pass
+/// their positions at `time` plus the plan pass time, which includes the feature priority, so the
+/// entities are reported as spawned at `time` delayed by the priority at their world entry points
+/// * `plan` - feature prepared to be spawned with a chosen shift
 /// * `obstacles` - a list of entities that all s entities spawned from a feature will be added to.
 ///                 They will now have to be check against collisions until they get trimmed from
 ///                 the list after leaving the visible world
 /// * `generated_entities` -  a list of all spawned entities. Entities spawned from this feature
 ///                           will be added to it
 /// * `time` - current time travel
-/// * `world` - visible world
-/// * `feature_shift` - a randomized positional shift of this feature. All feature entities will
-///                     be spawned shifted by this value
 ///
-pub fn spawn_feature(feature: &Feature,
-                     obstacles: &mut VecDeque<CollidableEntity>,
+pub fn spawn_feature(plan: &FeaturePlan,
+                     obstacles: &mut ObstacleIndex,
                      generated_entities: &mut Vec<CollidableEntity>,
                      time: f32,
-                     world: &VisibleWorld,
-                     feature_shift: &Vector3<f32>,
 ) {
-    let max_time_to_travel = feature.max_approach_time(&world, &feature_shift);
-    for prefab in &feature.prefabs {
-        let prefab_motion = BiArcCurveMotion::new(
-            max_time_to_travel,
-            Isometry3::from_parts(Translation3::from(prefab.position + Vector3::new(feature_shift.x, feature_shift.y, 0.)), prefab.rotation),
-            prefab.movement.baseline_velocity.clone(),
-            prefab.movement.arcs_plane_normal.clone(),
-            prefab.movement.approach_arc_angle,
-            prefab.movement.approach_arc_center_distance,
-            prefab.movement.approach_arc_radius,
-            prefab.movement.approach_rotation_strength,
-            prefab.movement.departure_arc_angle,
-            prefab.movement.departure_arc_center_distance,
-            prefab.movement.departure_arc_radius,
-            prefab.movement.departure_rotation_strength,
-        );
+    for prefab_plan in &plan.prefabs {
         let entity = CollidableEntity {
-            movement_start_parameter: -max_time_to_travel,
-            movement_end_parameter: prefab.find_departure_time_in_world(&world, feature_shift),
-            spawn_position: prefab_motion.position_at_time(0.).translation.vector,
-            spawn_rotation: prefab_motion.rotation_at_time(0.),
-            spawn_feature_shift: feature_shift.clone(),
-            spawn_time: time + feature.priority as f32,
-            prefab: prefab.clone(),
-            priority: feature.priority,
+            collision_start_time: time + prefab_plan.collision_start_time,
+            collision_end_time: time + prefab_plan.collision_end_time,
+            spawn_scale: plan.transform.scale,
+            spawn_mirrored: plan.transform.mirrored,
+            ..CollidableEntity::new(
+                generated_entities.len(),
+                prefab_plan.prefab.clone(),
+                plan.feature_shift,
+                time + plan.feature.priority as f32,
+                -plan.max_approach_time,
+                prefab_plan.departure_time,
+                plan.feature.priority,
+            )
         };
-        obstacles.push_back(entity.clone());
+        obstacles.insert(entity.clone());
         generated_entities.push(entity.clone());
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{Feature, Prefab, Movement, VisibleWorld};
-    use nalgebra::{Vector3, Point3, UnitQuaternion, Unit};
+    use crate::{Feature, Prefab, Movement, VisibleWorld, SpawnPeriodDistribution};
+    use nalgebra::{Vector2, Vector3, Point3, UnitQuaternion, Unit};
     use crate::generator::spawn_feature::{spawn_feature};
-    use std::collections::VecDeque;
+    use crate::generator::types::{FeaturePlan, ObstacleIndex, GeneratorConfig};
     use ncollide3d::bounding_volume::AABB;
 
     #[test]
@@ -69,6 +52,10 @@ mod tests {
             position: nalgebra::zero(),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -8.0),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -95,20 +82,35 @@ mod tests {
             translate_y: false,
             translate_y_using_bounds: false,
             translate_y_bounds: nalgebra::zero(),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             missed_spawns: 0,
             last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
         let world = VisibleWorld {
             world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(100., 100., 100.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
         let generated_entities = &mut vec![];
         spawn_feature(
-            &feature,
-            &mut VecDeque::new(),
+            &FeaturePlan::new(&feature, &world, &nalgebra::zero(), &GeneratorConfig::default()),
+            &mut ObstacleIndex::new(&world, &GeneratorConfig::default()),
             generated_entities,
             0.,
-            &world,
-            &nalgebra::zero(),
         );
 
         for (index, entity) in generated_entities.iter().enumerate() {
@@ -116,6 +118,85 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_spawn_feature_delays_spawn_by_priority() {
+        let prefab = Prefab {
+            prefab_id: 1,
+            position: Vector3::new(2., 0., 0.),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(0., 0., -8.0),
+                arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+        };
+        let feature = Feature {
+            prefabs: vec![prefab.clone()],
+            spawn_period: 0.0,
+            is_spawn_period_strict: false,
+            spawn_count: 1,
+            trigger_time: 0.0,
+            priority: 3,
+            translate_x: false,
+            translate_x_using_bounds: false,
+            translate_x_bounds: nalgebra::zero(),
+            translate_y: false,
+            translate_y_using_bounds: false,
+            translate_y_bounds: nalgebra::zero(),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
+            missed_spawns: 0,
+            last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
+        };
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
+        };
+        let plan = FeaturePlan::new(&feature, &world, &nalgebra::zero(), &GeneratorConfig::default());
+        let generated_entities = &mut vec![];
+        spawn_feature(
+            &plan,
+            &mut ObstacleIndex::new(&world, &GeneratorConfig::default()),
+            generated_entities,
+            2.,
+        );
+
+        let entity = &generated_entities[0];
+        assert_relative_eq!(entity.spawn_time, 5.);
+        // The entity is spawned at its world entry point and passes its position at the same time
+        // as the plan checked for collisions
+        let entry_position = prefab.position - prefab.movement.baseline_velocity * plan.max_approach_time;
+        assert_relative_eq!(entity.spawn_position, entry_position, epsilon = 1.0e-4);
+        assert_relative_eq!(entity.transform_at_time(entity.spawn_time).translation.vector, entry_position, epsilon = 1.0e-4);
+        assert_relative_eq!(entity.transform_at_time(2. + plan.prefabs[0].pass_time).translation.vector, prefab.position, epsilon = 1.0e-4);
+    }
+
     #[test]
     fn test_spawn_feature_tiled() {
         let prefab = Prefab {
@@ -123,6 +204,10 @@ mod tests {
             position: nalgebra::zero(),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -8.0),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -149,20 +234,35 @@ mod tests {
             translate_y: false,
             translate_y_using_bounds: false,
             translate_y_bounds: nalgebra::zero(),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             missed_spawns: 0,
             last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
         let world = VisibleWorld {
             world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(100., 100., 100.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
         let generated_entities = &mut vec![];
         spawn_feature(
-            &feature,
-            &mut VecDeque::new(),
+            &FeaturePlan::new(&feature, &world, &nalgebra::zero(), &GeneratorConfig::default()),
+            &mut ObstacleIndex::new(&world, &GeneratorConfig::default()),
             generated_entities,
             0.,
-            &world,
-            &nalgebra::zero(),
         );
         for (index, entity) in generated_entities.iter().enumerate() {
             println!("Generated entitity {}: {:?}", index, entity)
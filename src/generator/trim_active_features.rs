@@ -12,7 +12,7 @@ pub fn trim_active_features(
 
 #[cfg(test)]
 mod tests {
-    use crate::generator::types::{Feature, Prefab, Movement};
+    use crate::generator::types::{Feature, Prefab, Movement, SpawnPeriodDistribution};
     use super::trim_active_features;
 
     use nalgebra::{Vector3, Point3, Vector2, UnitQuaternion, Unit};
@@ -25,6 +25,10 @@ mod tests {
             position: Vector3::new(0.0, 0.0, 0.0),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -1.0),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -45,6 +49,14 @@ mod tests {
             translate_y: false,
             translate_y_using_bounds: false,
             translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             prefabs: vec![prefab0.clone()],
             spawn_count: 1,
             spawn_period: 1.0,
@@ -53,6 +65,11 @@ mod tests {
             missed_spawns: 0,
             is_spawn_period_strict: false,
             last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
         let feature1 = Feature {
             spawn_count: 0,
@@ -1,16 +1,16 @@
-use std::collections::VecDeque;
-use super::types::{CollidableEntity};
+use super::types::ObstacleIndex;
 
-/// Removes obstacles after they leave bounded volume of a visible world
+/// Removes obstacles after they leave bounded volume of a visible world grown by the safety margin,
+/// when no entity has to be kept apart from them anymore
 /// * `obstacles` - entity list to trim
 /// * `time_travelled` - current time passed in a generated world
 ///
 pub fn trim_obstacles(
-    obstacles: &mut VecDeque<CollidableEntity>,
+    obstacles: &mut ObstacleIndex,
     time_travelled: f32,
 ) {
     obstacles.retain(|entity| {
-        if time_travelled < entity.spawn_time - entity.movement_start_parameter + entity.movement_end_parameter {
+        if time_travelled < entity.collision_end_time {
             return true;
         };
         false
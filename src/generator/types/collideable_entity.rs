@@ -1,21 +1,121 @@
-use nalgebra::{Vector3, UnitQuaternion};
+use nalgebra::{Vector3, UnitQuaternion, Isometry3};
 use crate::Prefab;
+use crate::generator::bi_arc_motion::BiArcCurveMotion;
+use ncollide3d::interpolation::RigidMotion;
 
 // Represents a spawned entity
 #[derive(Clone, PartialEq, Debug)]
 pub struct CollidableEntity {
+    /// Index of this entity in the list of generated entities
+    pub entity_id: usize,
     pub movement_start_parameter: f32,
     pub movement_end_parameter: f32,
     pub spawn_position: Vector3<f32>,
     pub spawn_rotation: UnitQuaternion<f32>,
     pub spawn_feature_shift: Vector3<f32>,
+    /// Uniform scale the formation of this entity was spawned with. The prefab is already scaled
+    pub spawn_scale: f32,
+    /// Whether the formation of this entity was spawned as a mirror image. The prefab is already
+    /// mirrored, so only its visual representation has to be reflected
+    pub spawn_mirrored: bool,
     pub spawn_time: f32,
     pub prefab: Prefab,
     pub priority: i32,
+    /// Motion this entity follows, parametrized with the same time as `spawn_time`. It is computed
+    /// once when the entity is created and reused by every collision check against it
+    pub motion: BiArcCurveMotion,
+    /// Time from which this entity is kept apart from other entities, usually when it enters the
+    /// visible world grown by the safety margin. Entities created with `new` are checked along
+    /// their whole path
+    pub collision_start_time: f32,
+    /// Time until which this entity is kept apart from other entities, usually when it leaves the
+    /// visible world grown by the safety margin
+    pub collision_end_time: f32,
 }
 
 impl CollidableEntity {
+    /// Creates an entity of a prefab that passes its shifted position at `spawn_time` minus
+    /// `movement_start_parameter`
+    ///
+    /// * `entity_id` - index of the entity in the list of generated entities
+    /// * `prefab` - spawned prefab
+    /// * `spawn_feature_shift` - shift of the feature the prefab belongs to
+    /// * `spawn_time` - time at which the entity enters the visible world
+    /// * `movement_start_parameter` - negated time the entity needs to reach its shifted position
+    ///                                from the world entry
+    /// * `movement_end_parameter` - time the entity needs to leave the world from its shifted
+    ///                              position
+    /// * `priority` - priority of the feature the prefab belongs to
+    ///
+    pub fn new(
+        entity_id: usize,
+        prefab: Prefab,
+        spawn_feature_shift: Vector3<f32>,
+        spawn_time: f32,
+        movement_start_parameter: f32,
+        movement_end_parameter: f32,
+        priority: i32,
+    ) -> CollidableEntity {
+        let motion = prefab.motion(spawn_time - movement_start_parameter, &spawn_feature_shift);
+        CollidableEntity {
+            entity_id,
+            movement_start_parameter,
+            movement_end_parameter,
+            spawn_position: motion.position_at_time(spawn_time).translation.vector,
+            spawn_rotation: motion.rotation_at_time(spawn_time),
+            spawn_feature_shift,
+            spawn_scale: 1.,
+            spawn_mirrored: false,
+            spawn_time,
+            prefab,
+            priority,
+            motion,
+            collision_start_time: f32::NEG_INFINITY,
+            collision_end_time: f32::INFINITY,
+        }
+    }
+
+    /// Returns a copy of this entity that is spawned later by a given delay
+    pub fn delayed(&self, delay: f32) -> CollidableEntity {
+        CollidableEntity {
+            spawn_time: self.spawn_time + delay,
+            motion: self.motion.delayed(delay),
+            collision_start_time: self.collision_start_time + delay,
+            collision_end_time: self.collision_end_time + delay,
+            ..self.clone()
+        }
+    }
+
     pub fn position(&self, time: f32) -> Vector3<f32> {
         self.spawn_position + self.prefab.movement.baseline_velocity * (time - self.spawn_time)
     }
+
+    /// Time at which this entity leaves the visible world and no longer has to be checked against
+    /// collisions
+    pub fn world_exit_time(&self) -> f32 {
+        entity_world_exit_time(self.spawn_time, self.movement_start_parameter, self.movement_end_parameter)
+    }
+
+    /// Checks if this entity is already spawned and didn't yet leave the visible world
+    pub fn is_alive_at(&self, time: f32) -> bool {
+        is_entity_alive_at(self.spawn_time, self.movement_start_parameter, self.movement_end_parameter, time)
+    }
+
+    /// Calculates position and rotation of this entity at a given time. The result is computed
+    /// with the same motion that was used to check this entity for collisions
+    pub fn transform_at_time(&self, time: f32) -> Isometry3<f32> {
+        Isometry3::from_parts(self.motion.position_at_time(time).translation, self.motion.rotation_at_time(time))
+    }
+}
+
+/// Time at which an entity spawned at `spawn_time` with given movement parameters leaves the
+/// visible world
+pub(crate) fn entity_world_exit_time(spawn_time: f32, movement_start_parameter: f32, movement_end_parameter: f32) -> f32 {
+    spawn_time - movement_start_parameter + movement_end_parameter
+}
+
+/// Checks if an entity with given spawn time and movement parameters is inside the visible world
+/// at a given time
+pub(crate) fn is_entity_alive_at(spawn_time: f32, movement_start_parameter: f32, movement_end_parameter: f32, time: f32) -> bool {
+    time >= spawn_time && time < entity_world_exit_time(spawn_time, movement_start_parameter, movement_end_parameter)
 }
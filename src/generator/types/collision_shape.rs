@@ -0,0 +1,295 @@
+use nalgebra::{Vector3, UnitQuaternion, Point3, Isometry3, Translation3, Unit};
+use ncollide3d::shape::{self, ShapeHandle};
+use serde::{Serialize, Deserialize};
+use crate::generator::types::{mirror_vector, mirror_rotation};
+
+/// Number of sides of the prism used to approximate a collision cylinder
+const CYLINDER_PRISM_SIDES: usize = 16;
+
+/// Shape used to check a prefab against collisions with other prefabs. Shapes are expressed in the
+/// prefab local space so they follow the prefab rotation along its movement
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum CollisionShape {
+    /// Box centered at the prefab position
+    Cuboid {
+        half_extents: Vector3<f32>,
+    },
+    /// Sphere centered at the prefab position
+    Ball {
+        radius: f32,
+    },
+    /// Capsule centered at the prefab position with its segment along the local Y axis
+    Capsule {
+        half_height: f32,
+        radius: f32,
+    },
+    /// Cylinder centered at the prefab position with its axis along the local Y axis. It is
+    /// approximated with a circumscribed prism so it never reserves less space than the cylinder
+    Cylinder {
+        half_height: f32,
+        radius: f32,
+    },
+    /// Box moved and rotated relatively to the prefab position
+    OrientedCuboid {
+        half_extents: Vector3<f32>,
+        position: Vector3<f32>,
+        rotation: UnitQuaternion<f32>,
+    },
+    /// Convex hull of a cloud of points
+    ConvexHull {
+        points: Vec<Point3<f32>>,
+    },
+    /// Union of shapes, each moved and rotated relatively to the prefab position
+    Compound {
+        parts: Vec<CompoundShapePart>,
+    },
+}
+
+/// Single part of a compound collision shape
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CompoundShapePart {
+    pub position: Vector3<f32>,
+    pub rotation: UnitQuaternion<f32>,
+    pub shape: CollisionShape,
+}
+
+impl CollisionShape {
+    /// Checks if this shape can be used in collision queries. Returns a list of human-readable
+    /// problems that is empty if the shape is valid
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        match self {
+            CollisionShape::Cuboid { half_extents } => {
+                validate_half_extents(half_extents, &mut problems);
+            }
+            CollisionShape::Ball { radius } => {
+                validate_non_negative("radius", *radius, &mut problems);
+            }
+            CollisionShape::Capsule { half_height, radius } => {
+                validate_non_negative("half_height", *half_height, &mut problems);
+                validate_non_negative("radius", *radius, &mut problems);
+            }
+            CollisionShape::Cylinder { half_height, radius } => {
+                if !(half_height.is_finite() && *half_height > 0. && radius.is_finite() && *radius > 0.) {
+                    problems.push(format!("cylinder dimensions must be finite and positive, got half_height {} and radius {}", half_height, radius));
+                }
+            }
+            CollisionShape::OrientedCuboid { half_extents, position, .. } => {
+                validate_half_extents(half_extents, &mut problems);
+                if !position.iter().all(|value| value.is_finite()) {
+                    problems.push(format!("position must be finite, got {:?}", position.as_slice()));
+                }
+            }
+            CollisionShape::ConvexHull { points } => {
+                if !points.iter().all(|point| point.coords.iter().all(|value| value.is_finite())) {
+                    problems.push("convex hull points must be finite".to_string());
+                } else if shape::ConvexHull::try_from_points(points).is_none() {
+                    problems.push(format!("convex hull of {} points is degenerate, points must not be coplanar", points.len()));
+                }
+            }
+            CollisionShape::Compound { parts } => {
+                if parts.is_empty() {
+                    problems.push("compound must have at least one part".to_string());
+                }
+                for (index, part) in parts.iter().enumerate() {
+                    if !part.position.iter().all(|value| value.is_finite()) {
+                        problems.push(format!("part {}: position must be finite, got {:?}", index, part.position.as_slice()));
+                    }
+                    problems.extend(part.shape.validate().into_iter().map(|problem| format!("part {}: {}", index, problem)));
+                }
+            }
+        }
+        problems
+    }
+
+    /// Creates a shape that can be used in ncollide collision queries. Nested compounds are
+    /// flattened into a single compound shape
+    pub fn shape_handle(&self) -> ShapeHandle<f32> {
+        match self {
+            CollisionShape::Cuboid { half_extents } => ShapeHandle::new(shape::Cuboid::new(*half_extents)),
+            CollisionShape::Ball { radius } => ShapeHandle::new(shape::Ball::new(*radius)),
+            CollisionShape::Capsule { half_height, radius } => ShapeHandle::new(shape::Capsule::new(*half_height, *radius)),
+            CollisionShape::Cylinder { half_height, radius } => {
+                let points = cylinder_prism_points(*half_height, *radius);
+                ShapeHandle::new(shape::ConvexHull::try_from_points(&points).expect("Cylinder must have positive dimensions"))
+            }
+            CollisionShape::ConvexHull { points } => {
+                ShapeHandle::new(shape::ConvexHull::try_from_points(points).expect("Convex hull must not be degenerate"))
+            }
+            CollisionShape::OrientedCuboid { .. } | CollisionShape::Compound { .. } => {
+                let mut parts = Vec::new();
+                self.collect_parts(&Isometry3::identity(), &mut parts);
+                ShapeHandle::new(shape::Compound::new(parts))
+            }
+        }
+    }
+
+    /// Returns this shape uniformly scaled about the prefab position
+    ///
+    /// * `scale` - positive scale factor
+    ///
+    pub fn scaled(&self, scale: f32) -> CollisionShape {
+        match self {
+            CollisionShape::Cuboid { half_extents } => CollisionShape::Cuboid { half_extents: half_extents * scale },
+            CollisionShape::Ball { radius } => CollisionShape::Ball { radius: radius * scale },
+            CollisionShape::Capsule { half_height, radius } => CollisionShape::Capsule { half_height: half_height * scale, radius: radius * scale },
+            CollisionShape::Cylinder { half_height, radius } => CollisionShape::Cylinder { half_height: half_height * scale, radius: radius * scale },
+            CollisionShape::OrientedCuboid { half_extents, position, rotation } => CollisionShape::OrientedCuboid {
+                half_extents: half_extents * scale,
+                position: position * scale,
+                rotation: *rotation,
+            },
+            CollisionShape::ConvexHull { points } => CollisionShape::ConvexHull {
+                points: points.iter().map(|point| Point3::from(point.coords * scale)).collect(),
+            },
+            CollisionShape::Compound { parts } => CollisionShape::Compound {
+                parts: parts
+                    .iter()
+                    .map(|part| CompoundShapePart {
+                        position: part.position * scale,
+                        rotation: part.rotation,
+                        shape: part.shape.scaled(scale),
+                    })
+                    .collect(),
+            },
+        }
+    }
+
+    /// Returns this shape reflected across a plane going through the prefab position, given in
+    /// the prefab local space. Like with mirrored prefabs, rotated parts are mirrored by
+    /// conjugating their rotations with the reflection, so boxes, capsules and cylinders stay exact
+    /// only for planes perpendicular to their local axes
+    ///
+    /// * `plane_normal` - normal of the mirror plane
+    ///
+    pub fn mirrored(&self, plane_normal: &Unit<Vector3<f32>>) -> CollisionShape {
+        match self {
+            CollisionShape::OrientedCuboid { half_extents, position, rotation } => CollisionShape::OrientedCuboid {
+                half_extents: *half_extents,
+                position: mirror_vector(position, plane_normal),
+                rotation: mirror_rotation(rotation, plane_normal),
+            },
+            CollisionShape::ConvexHull { points } => CollisionShape::ConvexHull {
+                points: points.iter().map(|point| Point3::from(mirror_vector(&point.coords, plane_normal))).collect(),
+            },
+            CollisionShape::Compound { parts } => CollisionShape::Compound {
+                parts: parts
+                    .iter()
+                    .map(|part| CompoundShapePart {
+                        position: mirror_vector(&part.position, plane_normal),
+                        rotation: mirror_rotation(&part.rotation, plane_normal),
+                        shape: part.shape.mirrored(plane_normal),
+                    })
+                    .collect(),
+            },
+            shape => shape.clone(),
+        }
+    }
+
+    fn collect_parts(&self, isometry: &Isometry3<f32>, parts: &mut Vec<(Isometry3<f32>, ShapeHandle<f32>)>) {
+        match self {
+            CollisionShape::OrientedCuboid { half_extents, position, rotation } => {
+                let offset = Isometry3::from_parts(Translation3::from(*position), *rotation);
+                parts.push((isometry * offset, ShapeHandle::new(shape::Cuboid::new(*half_extents))));
+            }
+            CollisionShape::Compound { parts: compound_parts } => {
+                for part in compound_parts {
+                    let offset = Isometry3::from_parts(Translation3::from(part.position), part.rotation);
+                    part.shape.collect_parts(&(isometry * offset), parts);
+                }
+            }
+            _ => parts.push((*isometry, self.shape_handle())),
+        }
+    }
+}
+
+pub(crate) fn cylinder_prism_points(half_height: f32, radius: f32) -> Vec<Point3<f32>> {
+    let sector_angle = 2. * std::f32::consts::PI / CYLINDER_PRISM_SIDES as f32;
+    let circumscribed_radius = radius / (sector_angle / 2.).cos();
+    (0..CYLINDER_PRISM_SIDES)
+        .flat_map(|side| {
+            let angle = sector_angle * side as f32;
+            let x = circumscribed_radius * angle.cos();
+            let z = circumscribed_radius * angle.sin();
+            vec![Point3::new(x, -half_height, z), Point3::new(x, half_height, z)]
+        })
+        .collect()
+}
+
+fn validate_half_extents(half_extents: &Vector3<f32>, problems: &mut Vec<String>) {
+    if !half_extents.iter().all(|value| value.is_finite() && *value >= 0.) {
+        problems.push(format!("half_extents must be finite and non-negative, got {:?}", half_extents.as_slice()));
+    }
+}
+
+fn validate_non_negative(name: &str, value: f32, problems: &mut Vec<String>) {
+    if !(value.is_finite() && value >= 0.) {
+        problems.push(format!("{} must be finite and non-negative, got {}", name, value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ncollide3d::query;
+
+    fn distance(first: &CollisionShape, second: &CollisionShape, offset: Vector3<f32>) -> f32 {
+        query::distance(
+            &Isometry3::identity(),
+            first.shape_handle().as_ref(),
+            &Isometry3::new(offset, nalgebra::zero()),
+            second.shape_handle().as_ref(),
+        )
+    }
+
+    #[test]
+    fn test_ball_reserves_less_space_than_bounding_cuboid() {
+        let ball = CollisionShape::Ball { radius: 1. };
+        let cuboid = CollisionShape::Cuboid { half_extents: Vector3::new(1., 1., 1.) };
+        let diagonal_offset = Vector3::new(1.6, 1.6, 0.);
+        assert!(distance(&ball, &ball, diagonal_offset) > 0.);
+        assert_eq!(distance(&cuboid, &cuboid, diagonal_offset), 0.);
+    }
+
+    #[test]
+    fn test_compound_with_nested_parts() {
+        let compound = CollisionShape::Compound {
+            parts: vec![
+                CompoundShapePart {
+                    position: Vector3::new(2., 0., 0.),
+                    rotation: UnitQuaternion::identity(),
+                    shape: CollisionShape::Compound {
+                        parts: vec![CompoundShapePart {
+                            position: Vector3::new(1., 0., 0.),
+                            rotation: UnitQuaternion::identity(),
+                            shape: CollisionShape::Ball { radius: 0.5 },
+                        }],
+                    },
+                },
+            ],
+        };
+        let ball = CollisionShape::Ball { radius: 0.5 };
+        assert!(compound.validate().is_empty());
+        assert!(distance(&compound, &ball, Vector3::new(3., 0., 0.)) <= 0.);
+        assert!(distance(&compound, &ball, Vector3::new(0., 0., 0.)) > 1.);
+    }
+
+    #[test]
+    fn test_cylinder_contains_inscribed_cylinder() {
+        let cylinder = CollisionShape::Cylinder { half_height: 1., radius: 1. };
+        let ball = CollisionShape::Ball { radius: 0.001 };
+        let angle = std::f32::consts::PI / CYLINDER_PRISM_SIDES as f32;
+        let on_circle = Vector3::new(angle.cos(), 0., angle.sin()) * 0.999;
+        assert_eq!(distance(&cylinder, &ball, on_circle), 0.);
+    }
+
+    #[test]
+    fn test_validate_degenerate_shapes() {
+        let flat_hull = CollisionShape::ConvexHull {
+            points: vec![Point3::new(0., 0., 0.), Point3::new(1., 0., 0.), Point3::new(0., 1., 0.), Point3::new(1., 1., 0.)],
+        };
+        assert_eq!(flat_hull.validate().len(), 1);
+        assert_eq!(CollisionShape::Ball { radius: -1. }.validate().len(), 1);
+        assert_eq!(CollisionShape::Compound { parts: vec![] }.validate().len(), 1);
+    }
+}
@@ -0,0 +1,170 @@
+use serde::{Serialize, Deserialize};
+
+/// Interpolation used between consecutive keyframes of a curve
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum CurveInterpolation {
+    Linear,
+    /// Monotone cubic Hermite interpolation. The curve is smooth and never overshoots the values
+    /// of neighbouring keyframes
+    Cubic,
+}
+
+impl Default for CurveInterpolation {
+    fn default() -> Self {
+        CurveInterpolation::Linear
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CurveKeyframe {
+    pub time: f32,
+    pub value: f32,
+}
+
+/// Keyframed curve over the level time, the same time `Feature::trigger_time` is measured in.
+/// Before the first keyframe and after the last one the curve keeps their values
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Curve {
+    pub keyframes: Vec<CurveKeyframe>,
+    #[serde(default)]
+    pub interpolation: CurveInterpolation,
+}
+
+impl Curve {
+    /// Checks if this curve can be evaluated. Returns a list of human-readable problems that is
+    /// empty if the curve is valid
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.keyframes.is_empty() {
+            problems.push("keyframes must not be empty".to_string());
+        }
+        for (index, keyframe) in self.keyframes.iter().enumerate() {
+            if !keyframe.time.is_finite() || !keyframe.value.is_finite() {
+                problems.push(format!("keyframe {} must have a finite time and value, got ({}, {})", index, keyframe.time, keyframe.value));
+            }
+        }
+        if self.keyframes.windows(2).any(|pair| pair[0].time >= pair[1].time) {
+            problems.push("keyframe times must be strictly increasing".to_string());
+        }
+        problems
+    }
+
+    /// Returns the lowest and the highest keyframe values. As neither interpolation overshoots
+    /// the keyframes, the whole curve stays within this range
+    pub fn value_range(&self) -> (f32, f32) {
+        self.keyframes
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), keyframe| (min.min(keyframe.value), max.max(keyframe.value)))
+    }
+
+    /// Returns the value of this curve at a given time
+    ///
+    /// * `time` - level time at which the curve is evaluated
+    ///
+    pub fn evaluate(&self, time: f32) -> f32 {
+        let keyframes = &self.keyframes;
+        let (first, last) = match (keyframes.first(), keyframes.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return 0.,
+        };
+        if time <= first.time {
+            return first.value;
+        }
+        if time >= last.time {
+            return last.value;
+        }
+        let index = keyframes.iter().rposition(|keyframe| keyframe.time <= time).unwrap_or(0);
+        let (start, end) = (&keyframes[index], &keyframes[index + 1]);
+        let duration = end.time - start.time;
+        let t = (time - start.time) / duration;
+        match self.interpolation {
+            CurveInterpolation::Linear => start.value + (end.value - start.value) * t,
+            CurveInterpolation::Cubic => {
+                let start_tangent = self.tangent(index);
+                let end_tangent = self.tangent(index + 1);
+                let (t2, t3) = (t * t, t * t * t);
+                (2. * t3 - 3. * t2 + 1.) * start.value
+                    + (t3 - 2. * t2 + t) * duration * start_tangent
+                    + (-2. * t3 + 3. * t2) * end.value
+                    + (t3 - t2) * duration * end_tangent
+            }
+        }
+    }
+
+    /// Fritsch-Carlson tangent at a keyframe. It is zero at extrema and limited so that the
+    /// interpolated segments stay monotone
+    fn tangent(&self, index: usize) -> f32 {
+        let slope = |index: usize| {
+            let (start, end) = (&self.keyframes[index], &self.keyframes[index + 1]);
+            (end.value - start.value) / (end.time - start.time)
+        };
+        let before = if index > 0 { Some(slope(index - 1)) } else { None };
+        let after = if index + 1 < self.keyframes.len() { Some(slope(index)) } else { None };
+        match (before, after) {
+            (Some(before), Some(after)) => {
+                if before * after <= 0. {
+                    0.
+                } else {
+                    // Harmonic mean never exceeds twice the smaller slope, which keeps the segments monotone
+                    2. * before * after / (before + after)
+                }
+            }
+            (Some(slope), None) | (None, Some(slope)) => slope,
+            (None, None) => 0.,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve(interpolation: CurveInterpolation) -> Curve {
+        Curve {
+            keyframes: vec![
+                CurveKeyframe { time: 0., value: 4. },
+                CurveKeyframe { time: 10., value: 2. },
+                CurveKeyframe { time: 20., value: 1. },
+                CurveKeyframe { time: 30., value: 1. },
+            ],
+            interpolation,
+        }
+    }
+
+    #[test]
+    fn test_evaluate_linear_curve() {
+        let curve = curve(CurveInterpolation::Linear);
+        assert_eq!(curve.evaluate(-5.), 4.);
+        assert_relative_eq!(curve.evaluate(5.), 3.);
+        assert_relative_eq!(curve.evaluate(15.), 1.5);
+        assert_eq!(curve.evaluate(40.), 1.);
+        assert!(curve.validate().is_empty());
+    }
+
+    #[test]
+    fn test_evaluate_cubic_curve_without_overshooting() {
+        let curve = curve(CurveInterpolation::Cubic);
+        assert_relative_eq!(curve.evaluate(10.), 2.);
+        let mut previous = curve.evaluate(0.);
+        let mut time = 0.;
+        while time <= 30. {
+            let value = curve.evaluate(time);
+            assert!(value <= previous + 1.0e-5, "curve rises at {}", time);
+            assert!(value >= 1. - 1.0e-5, "curve overshoots at {}", time);
+            previous = value;
+            time += 0.1;
+        }
+        // Smooth at the middle keyframe, unlike the linear curve
+        assert_relative_eq!(curve.evaluate(9.9) - 2., 2. - curve.evaluate(10.1), epsilon = 2.0e-3);
+    }
+
+    #[test]
+    fn test_validate_curve() {
+        let unordered = Curve {
+            keyframes: vec![CurveKeyframe { time: 1., value: 0. }, CurveKeyframe { time: 1., value: 1. }],
+            interpolation: CurveInterpolation::Linear,
+        };
+        assert_eq!(unordered.validate().len(), 1);
+        assert_eq!(Curve { keyframes: vec![], interpolation: CurveInterpolation::Cubic }.validate().len(), 1);
+    }
+}
@@ -0,0 +1,123 @@
+use nalgebra::{Vector3, UnitQuaternion, Isometry3, Translation3};
+use ncollide3d::bounding_volume::AABB;
+use ncollide3d::query::{Ray, RayCast};
+use serde::{Serialize, Deserialize};
+use crate::generator::types::CollisionShape;
+
+/// A region of a visible world that no entity may pass through, e.g. real-world furniture, a
+/// player safe zone or a UI panel. A volume is static unless it has an activity time window
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ExclusionVolume {
+    pub shape: CollisionShape,
+    pub position: Vector3<f32>,
+    #[serde(default = "UnitQuaternion::identity")]
+    pub rotation: UnitQuaternion<f32>,
+    /// Level time at which this volume appears. A volume without it exists from the beginning
+    #[serde(default)]
+    pub active_from: Option<f32>,
+    /// Level time at which this volume disappears. A volume without it exists until the end
+    #[serde(default)]
+    pub active_until: Option<f32>,
+}
+
+impl ExclusionVolume {
+    /// Checks if this volume can be used by the generator. Returns a list of human-readable
+    /// problems that is empty if the volume is valid
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems: Vec<String> = self.shape.validate()
+            .into_iter()
+            .map(|problem| format!("shape: {}", problem))
+            .collect();
+        if !self.position.iter().all(|value| value.is_finite()) {
+            problems.push(format!("position must be finite, got {:?}", self.position.as_slice()));
+        }
+        if self.active_from.map_or(false, |time| !time.is_finite()) || self.active_until.map_or(false, |time| !time.is_finite()) {
+            problems.push("activity times must be finite".to_string());
+        }
+        if let (Some(active_from), Some(active_until)) = (self.active_from, self.active_until) {
+            if active_from > active_until {
+                problems.push(format!("active_from {} must not be later than active_until {}", active_from, active_until));
+            }
+        }
+        problems
+    }
+
+    /// Returns true if this volume exists for the whole level
+    pub fn is_static(&self) -> bool {
+        self.active_from.is_none() && self.active_until.is_none()
+    }
+
+    pub fn isometry(&self) -> Isometry3<f32> {
+        Isometry3::from_parts(Translation3::from(self.position), self.rotation)
+    }
+
+    /// Returns the time interval in which this volume exists, measured relatively to a given time
+    ///
+    /// * `time` - time the interval is measured from
+    ///
+    pub fn active_interval_since(&self, time: f32) -> (f32, f32) {
+        (
+            self.active_from.map_or(f32::NEG_INFINITY, |active_from| active_from - time),
+            self.active_until.map_or(f32::INFINITY, |active_until| active_until - time),
+        )
+    }
+
+    /// Returns a copy of this volume that appears and disappears later by a given delay
+    pub fn delayed(&self, delay: f32) -> ExclusionVolume {
+        ExclusionVolume {
+            active_from: self.active_from.map(|active_from| active_from + delay),
+            active_until: self.active_until.map(|active_until| active_until + delay),
+            ..self.clone()
+        }
+    }
+
+    /// Checks if a box moving along an infinite line would pass through the bounding box of this
+    /// volume. It is a cheap conservative test that ignores time windows
+    ///
+    /// * `point` - any point on the line
+    /// * `direction` - direction of the line
+    /// * `half_extents` - half extents of the moving box
+    ///
+    pub fn bounds_intersect_line(&self, point: &Vector3<f32>, direction: &Vector3<f32>, half_extents: &Vector3<f32>) -> bool {
+        let bounds = self.shape.shape_handle().aabb(&self.isometry());
+        let loosened_bounds = AABB::new(bounds.mins - half_extents, bounds.maxs + half_extents);
+        let identity = Isometry3::identity();
+        loosened_bounds.intersects_ray(&identity, &Ray::new((*point).into(), *direction), f32::MAX)
+            || loosened_bounds.intersects_ray(&identity, &Ray::new((*point).into(), -*direction), f32::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounds_intersect_line() {
+        let volume = ExclusionVolume {
+            shape: CollisionShape::Ball { radius: 1. },
+            position: Vector3::new(0., 0., -5.),
+            rotation: UnitQuaternion::identity(),
+            active_from: None,
+            active_until: None,
+        };
+        let direction = Vector3::new(0., 0., -1.);
+        let half_extents = Vector3::new(0.5, 0.5, 0.5);
+        assert!(volume.bounds_intersect_line(&Vector3::new(1.4, 0., 0.), &direction, &half_extents));
+        assert!(volume.bounds_intersect_line(&Vector3::new(0., 0., -10.), &direction, &half_extents));
+        assert!(!volume.bounds_intersect_line(&Vector3::new(1.6, 0., 0.), &direction, &half_extents));
+    }
+
+    #[test]
+    fn test_delayed_active_interval() {
+        let volume = ExclusionVolume {
+            shape: CollisionShape::Cuboid { half_extents: Vector3::new(1., 1., 1.) },
+            position: nalgebra::zero(),
+            rotation: UnitQuaternion::identity(),
+            active_from: Some(2.),
+            active_until: None,
+        }.delayed(3.);
+        assert!(!volume.is_static());
+        assert_eq!(volume.active_interval_since(1.), (4., f32::INFINITY));
+        assert_eq!(volume.validate().len(), 0);
+    }
+}
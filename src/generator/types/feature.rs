@@ -1,8 +1,11 @@
 use crate::generator::types::prefab::Prefab;
-use nalgebra::{Vector2, Vector3};
+use crate::generator::types::{FeatureVariant, FormationTransform, SpawnPeriodDistribution, Curve};
+use nalgebra::{Vector2, Vector3, Unit};
 use crate::VisibleWorld;
 use itertools::Itertools;
 use std::cmp::Ordering::Equal;
+use rand::RngCore;
+use rand::seq::SliceRandom;
 use serde::{Serialize, Deserialize};
 
 /// Represents a description of a feature that can be spawned in a generated world. Single feature
@@ -11,8 +14,24 @@ use serde::{Serialize, Deserialize};
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Feature {
     pub prefabs: Vec<Prefab>,
+    /// Alternative formations of this feature. When any are listed, each spawn picks one of them
+    /// by weight and `prefabs` are not spawned, so they may be left empty
+    #[serde(default)]
+    pub variants: Vec<FeatureVariant>,
     pub spawn_period: f32,
     pub is_spawn_period_strict: bool,
+    /// Distribution of intervals between consecutive spawns. The adaptive one follows
+    /// `is_spawn_period_strict`, the others ignore it
+    #[serde(default)]
+    pub spawn_period_distribution: SpawnPeriodDistribution,
+    /// Spawn period over the level time. Replaces `spawn_period` when present. It can't be used
+    /// with the uniform distribution, which ignores the spawn period
+    #[serde(default)]
+    pub spawn_period_curve: Option<Curve>,
+    /// Multiplier of the prefabs speed over the level time. Paths are kept, so faster spawns pass
+    /// their positions sooner after entering the world
+    #[serde(default)]
+    pub speed_multiplier_curve: Option<Curve>,
     pub spawn_count: i32,
     pub trigger_time: f32,
     pub priority: i32,
@@ -22,14 +41,245 @@ pub struct Feature {
     pub translate_y: bool,
     pub translate_y_using_bounds: bool,
     pub translate_y_bounds: Vector2<f32>,
+    /// Shifts the formation along the world flow axis. Prefabs still enter the world at the spawn
+    /// time, but they pass their shifted positions and arcs further downstream, which lengthens
+    /// their approach times accordingly. Bounds are measured along the flow direction
+    #[serde(default)]
+    pub translate_z: bool,
+    #[serde(default)]
+    pub translate_z_using_bounds: bool,
+    #[serde(default)]
+    pub translate_z_bounds: Vector2<f32>,
+    /// Rotates the formation about the world flow axis at each spawn by an angle drawn from
+    /// `rotate_bounds`, given in radians
+    #[serde(default)]
+    pub rotate: bool,
+    #[serde(default)]
+    pub rotate_bounds: Vector2<f32>,
+    /// Scales the formation uniformly at each spawn by a factor drawn from `scale_bounds`
+    #[serde(default)]
+    pub scale: bool,
+    #[serde(default)]
+    pub scale_bounds: Vector2<f32>,
+    /// Mirrors the formation across the plane spanned by the world flow axis and the second
+    /// lateral axis at every other spawn on average
+    #[serde(default)]
+    pub mirror: bool,
     pub missed_spawns: i32,
     pub last_spawn_attempt: f32,
+    /// Time from which the next spawn is attempted when intervals are drawn from a distribution
+    #[serde(default)]
+    pub next_spawn_time: f32,
 }
 
 impl Feature {
+    /// Checks if this feature can be used by the generator. Returns a list of human-readable
+    /// problems that is empty if the feature is valid
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.prefabs.is_empty() && self.variants.is_empty() {
+            problems.push("prefabs must not be empty".to_string());
+        }
+        if !self.variants.is_empty() && !self.variants.iter().any(|variant| variant.can_be_picked()) {
+            problems.push("at least one variant must have a positive weight".to_string());
+        }
+        if !self.spawn_period.is_finite() || self.spawn_period < 0. {
+            problems.push(format!("spawn_period must be a finite non-negative number, got {}", self.spawn_period));
+        }
+        if let Some(curve) = &self.spawn_period_curve {
+            problems.extend(curve.validate().into_iter().map(|problem| format!("spawn_period_curve: {}", problem)));
+            if curve.value_range().0 < 0. {
+                problems.push("spawn_period_curve must not go below zero".to_string());
+            }
+        }
+        if let Some(curve) = &self.speed_multiplier_curve {
+            problems.extend(curve.validate().into_iter().map(|problem| format!("speed_multiplier_curve: {}", problem)));
+            if curve.value_range().0 <= 0. {
+                problems.push("speed_multiplier_curve must stay positive".to_string());
+            }
+        }
+        problems.extend(self.spawn_period_distribution.validate().into_iter().map(|problem| format!("spawn_period_distribution: {}", problem)));
+        if self.spawn_period_curve.is_some() {
+            if let SpawnPeriodDistribution::Uniform { .. } = self.spawn_period_distribution {
+                problems.push("uniform spawn_period_distribution ignores the spawn period, so it can't be used with spawn_period_curve".to_string());
+            }
+        }
+        if !self.trigger_time.is_finite() {
+            problems.push(format!("trigger_time must be finite, got {}", self.trigger_time));
+        }
+        if self.translate_x_using_bounds && !self.translate_x_bounds.iter().all(|value| value.is_finite()) {
+            problems.push(format!("translate_x_bounds must be finite, got {:?}", self.translate_x_bounds.as_slice()));
+        }
+        if self.translate_y_using_bounds && !self.translate_y_bounds.iter().all(|value| value.is_finite()) {
+            problems.push(format!("translate_y_bounds must be finite, got {:?}", self.translate_y_bounds.as_slice()));
+        }
+        if self.translate_z_using_bounds && !self.translate_z_bounds.iter().all(|value| value.is_finite()) {
+            problems.push(format!("translate_z_bounds must be finite, got {:?}", self.translate_z_bounds.as_slice()));
+        }
+        if self.rotate && !(self.rotate_bounds.iter().all(|value| value.is_finite()) && self.rotate_bounds.x <= self.rotate_bounds.y) {
+            problems.push(format!("rotate_bounds must be a finite range, got {:?}", self.rotate_bounds.as_slice()));
+        }
+        if self.scale && !(self.scale_bounds.iter().all(|value| value.is_finite() && *value > 0.) && self.scale_bounds.x <= self.scale_bounds.y) {
+            problems.push(format!("scale_bounds must be a finite range of positive numbers, got {:?}", self.scale_bounds.as_slice()));
+        }
+        for (index, prefab) in self.prefabs.iter().enumerate() {
+            problems.extend(prefab.validate().into_iter().map(|problem| format!("prefab {}: {}", index, problem)));
+        }
+        for (index, variant) in self.variants.iter().enumerate() {
+            problems.extend(variant.validate().into_iter().map(|problem| format!("variant {}: {}", index, problem)));
+        }
+        problems
+    }
+
+    /// Returns the spawn period at a given level time
+    pub fn spawn_period_at(&self, time: f32) -> f32 {
+        self.spawn_period_curve.as_ref().map_or(self.spawn_period, |curve| curve.evaluate(time))
+    }
+
+    /// Returns the multiplier of the prefabs speed at a given level time
+    pub fn speed_multiplier_at(&self, time: f32) -> f32 {
+        self.speed_multiplier_curve.as_ref().map_or(1., |curve| curve.evaluate(time))
+    }
+
+    /// Returns prefabs of a single spawn of this feature. If the feature lists variants, one of them
+    /// is picked by its weight at a given time and its prefabs are returned. Returns None if no
+    /// variant can be picked at that time. Prefabs are borrowed, so nothing is copied until the
+    /// spawn is prepared with #spawned_variant
+    ///
+    /// * `rng` - random number generator used to pick a variant
+    /// * `time` - level time at which the variant weights are evaluated
+    ///
+    pub fn pick_variant(&self, rng: &mut impl RngCore, time: f32) -> Option<&[Prefab]> {
+        if self.variants.is_empty() {
+            return Some(&self.prefabs);
+        }
+        self.variants
+            .choose_weighted(rng, |variant| variant.weight_at(time).max(0.))
+            .ok()
+            .map(|variant| variant.prefabs.as_slice())
+    }
+
+    /// Returns a feature without variants that spawns given prefabs sped up and transformed about
+    /// the feature origin. Only the given prefabs are copied, everything else is taken from this
+    /// feature
+    ///
+    /// * `prefabs` - prefabs of a single spawn, usually picked with #pick_variant
+    /// * `speed_multiplier` - positive speed multiplier
+    /// * `transform` - transformation applied to the formation
+    /// * `world` - visible world providing the flow axis and the mirror plane
+    ///
+    pub fn spawned_variant(&self, prefabs: &[Prefab], speed_multiplier: f32, transform: &FormationTransform, world: &VisibleWorld) -> Feature {
+        let transform_prefab = prefab_transformation(transform, world);
+        self.with_prefabs(
+            prefabs
+                .iter()
+                .map(|prefab| transform_prefab(&prefab.sped_up(speed_multiplier)))
+                .collect(),
+            Vec::new(),
+        )
+    }
+
+    /// Returns a feature whose prefabs and variants fly along the same paths as this feature ones
+    /// at a multiplied speed
+    ///
+    /// * `multiplier` - positive speed multiplier
+    ///
+    pub fn sped_up(&self, multiplier: f32) -> Feature {
+        self.map_prefabs(|prefab| prefab.sped_up(multiplier))
+    }
+
+    /// Returns a feature whose prefabs and variants are transformed about the feature origin.
+    /// Translation options are left untouched
+    ///
+    /// * `transform` - transformation applied to the formation
+    /// * `world` - visible world providing the flow axis and the mirror plane
+    ///
+    pub fn transformed(&self, transform: &FormationTransform, world: &VisibleWorld) -> Feature {
+        self.map_prefabs(prefab_transformation(transform, world))
+    }
+
+    /// Returns a feature whose prefabs are a mirror image of this feature prefabs across a plane
+    /// going through the feature origin. Translation options are left untouched
+    ///
+    /// * `plane_normal` - normal of the mirror plane
+    ///
+    pub fn mirrored(&self, plane_normal: &Unit<Vector3<f32>>) -> Feature {
+        self.map_prefabs(|prefab| prefab.mirrored(plane_normal))
+    }
+
+    /// Returns a feature whose prefabs fly along the same paths as this feature prefabs but in the
+    /// opposite direction
+    pub fn reversed(&self) -> Feature {
+        self.map_prefabs(Prefab::reversed)
+    }
+
+    /// Returns the longest time any of the prefabs of this feature or of its variants needs to
+    /// reach its shifted position after entering the world at the lowest speed multiplier the
+    /// feature reaches during the level. No spawn of the feature takes longer to approach
+    pub fn slowest_approach_time(&self, world: &VisibleWorld, shift: &Vector3<f32>) -> f32 {
+        let slowest_multiplier = self.speed_multiplier_curve.as_ref().map_or(1., |curve| curve.value_range().0);
+        self.max_approach_time(world, shift) / slowest_multiplier
+    }
+
+    /// Returns a feature whose prefabs and variant prefabs are replaced by the results of a
+    /// function applied to each of them. Everything else is left untouched
+    ///
+    /// * `f` - function mapping a prefab to its replacement
+    ///
+    fn map_prefabs(&self, f: impl Fn(&Prefab) -> Prefab) -> Feature {
+        self.with_prefabs(
+            self.prefabs.iter().map(&f).collect(),
+            self.variants
+                .iter()
+                .map(|variant| FeatureVariant {
+                    prefabs: variant.prefabs.iter().map(&f).collect(),
+                    weight: variant.weight,
+                    weight_curve: variant.weight_curve.clone(),
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns a copy of this feature with given prefabs and variants. Prefabs and variants of this
+    /// feature are not copied
+    fn with_prefabs(&self, prefabs: Vec<Prefab>, variants: Vec<FeatureVariant>) -> Feature {
+        Feature {
+            prefabs,
+            variants,
+            spawn_period: self.spawn_period,
+            is_spawn_period_strict: self.is_spawn_period_strict,
+            spawn_period_distribution: self.spawn_period_distribution,
+            spawn_period_curve: self.spawn_period_curve.clone(),
+            speed_multiplier_curve: self.speed_multiplier_curve.clone(),
+            spawn_count: self.spawn_count,
+            trigger_time: self.trigger_time,
+            priority: self.priority,
+            translate_x: self.translate_x,
+            translate_x_using_bounds: self.translate_x_using_bounds,
+            translate_x_bounds: self.translate_x_bounds,
+            translate_y: self.translate_y,
+            translate_y_using_bounds: self.translate_y_using_bounds,
+            translate_y_bounds: self.translate_y_bounds,
+            translate_z: self.translate_z,
+            translate_z_using_bounds: self.translate_z_using_bounds,
+            translate_z_bounds: self.translate_z_bounds,
+            rotate: self.rotate,
+            rotate_bounds: self.rotate_bounds,
+            scale: self.scale,
+            scale_bounds: self.scale_bounds,
+            mirror: self.mirror,
+            missed_spawns: self.missed_spawns,
+            last_spawn_attempt: self.last_spawn_attempt,
+            next_spawn_time: self.next_spawn_time,
+        }
+    }
+
+    /// Returns the longest time any of the prefabs of this feature or of its variants needs to
+    /// reach its shifted position after entering the world
     pub fn max_approach_time(&self, world: &VisibleWorld, shift: &Vector3<f32>) -> f32 {
         self.prefabs
             .iter()
+            .chain(self.variants.iter().flat_map(|variant| variant.prefabs.iter()))
             .map(|prefab| prefab.find_approach_time_in_world(&world, &shift))
             .sorted_by(|a, b| { a.partial_cmp(b).unwrap_or(Equal) })
             .last()
@@ -37,23 +287,45 @@ impl Feature {
     }
 }
 
+/// Returns a function that mirrors, scales and rotates a prefab about the feature origin
+fn prefab_transformation(transform: &FormationTransform, world: &VisibleWorld) -> impl Fn(&Prefab) -> Prefab {
+    let rotation = transform.rotation(world);
+    let mirror_plane_normal = world.lateral_axes().0;
+    let (mirrored, scale) = (transform.mirrored, transform.scale);
+    move |prefab: &Prefab| {
+        let prefab = if mirrored { prefab.mirrored(&mirror_plane_normal) } else { prefab.clone() };
+        prefab.scaled(scale).rotated(&rotation)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use nalgebra::{UnitQuaternion, Point3, Vector3, Unit};
     use ncollide3d::bounding_volume::AABB;
     use crate::Movement;
+    use crate::generator::types::{CurveKeyframe, CurveInterpolation};
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
 
     #[test]
     pub fn test_max_time_to_travel() {
         let world = VisibleWorld {
             world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(20., 20., 20.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
         let prefab1 = Prefab {
             prefab_id: 1,
             position: nalgebra::zero(),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -8.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -72,6 +344,10 @@ mod tests {
             position: nalgebra::zero(),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -4.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -90,6 +366,10 @@ mod tests {
             position: nalgebra::zero(),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -2.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -116,10 +396,162 @@ mod tests {
             translate_y: false,
             translate_y_using_bounds: false,
             translate_y_bounds: nalgebra::zero(),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             missed_spawns: 0,
             last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
         let max_time_to_travel = feature.max_approach_time(&world, &Vector3::new(0., 0., 0.));
         assert_eq!(max_time_to_travel, 10.25);
     }
+
+    #[test]
+    fn test_validate_rejects_uniform_distribution_with_spawn_period_curve() {
+        let prefab = Prefab {
+            prefab_id: 1,
+            position: nalgebra::zero(),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+        };
+        let feature = Feature {
+            prefabs: vec![prefab],
+            spawn_period: 1.0,
+            is_spawn_period_strict: false,
+            spawn_count: 5,
+            trigger_time: 0.0,
+            priority: 0,
+            translate_x: false,
+            translate_x_using_bounds: false,
+            translate_x_bounds: nalgebra::zero(),
+            translate_y: false,
+            translate_y_using_bounds: false,
+            translate_y_bounds: nalgebra::zero(),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
+            missed_spawns: 0,
+            last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Uniform { min: 1., max: 2. },
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
+        };
+        assert!(feature.validate().is_empty());
+        let feature = Feature {
+            spawn_period_curve: Some(Curve {
+                keyframes: vec![CurveKeyframe { time: 0., value: 2. }, CurveKeyframe { time: 10., value: 1. }],
+                interpolation: CurveInterpolation::Linear,
+            }),
+            ..feature
+        };
+        assert_eq!(feature.validate().len(), 1);
+    }
+
+    #[test]
+    fn test_spawned_variant_copies_only_picked_prefabs() {
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(20., 20., 20.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
+        };
+        let prefab = |prefab_id: i32| Prefab {
+            prefab_id,
+            position: Vector3::new(prefab_id as f32, 1., 0.),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+        };
+        let feature = Feature {
+            prefabs: vec![],
+            spawn_period: 1.0,
+            is_spawn_period_strict: false,
+            spawn_count: 5,
+            trigger_time: 0.0,
+            priority: 2,
+            translate_x: false,
+            translate_x_using_bounds: false,
+            translate_x_bounds: nalgebra::zero(),
+            translate_y: false,
+            translate_y_using_bounds: false,
+            translate_y_bounds: nalgebra::zero(),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
+            missed_spawns: 0,
+            last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![
+                FeatureVariant { prefabs: vec![prefab(1)], weight: 0., weight_curve: None },
+                FeatureVariant { prefabs: vec![prefab(2), prefab(3)], weight: 1., weight_curve: None },
+            ],
+        };
+        let prefabs = feature.pick_variant(&mut StdRng::seed_from_u64(0), 0.).unwrap();
+        assert_eq!(prefabs, &[prefab(2), prefab(3)][..]);
+
+        let picked_feature = Feature { prefabs: prefabs.to_vec(), variants: vec![], ..feature.clone() };
+        assert_eq!(feature.spawned_variant(prefabs, 1., &FormationTransform::default(), &world), picked_feature);
+        let transform = FormationTransform { rotation_angle: 0.5, scale: 2., mirrored: true };
+        assert_eq!(
+            feature.spawned_variant(prefabs, 2., &transform, &world),
+            picked_feature.sped_up(2.).transformed(&transform, &world),
+        );
+    }
 }
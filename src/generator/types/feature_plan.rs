@@ -0,0 +1,95 @@
+use std::collections::VecDeque;
+use nalgebra::Vector3;
+use ncollide3d::shape::ShapeHandle;
+use ncollide3d::interpolation::RigidMotion;
+use crate::generator::types::{Feature, Prefab, VisibleWorld, CollidableEntity, GeneratorConfig, FormationTransform};
+use crate::generator::bi_arc_motion::BiArcCurveMotion;
+
+/// A feature prepared to be spawned with a given shift. It holds everything collision checks and
+/// spawning need that depends only on the shift, so world ray casts and motions are computed once
+/// per spawn attempt. All times are measured from the spawn attempt
+pub struct FeaturePlan<'a> {
+    pub feature: &'a Feature,
+    pub feature_shift: Vector3<f32>,
+    /// Time the slowest prefab needs to reach its shifted position from the world entry
+    pub max_approach_time: f32,
+    pub prefabs: Vec<PrefabPlan<'a>>,
+    /// Transformation the feature prefabs were randomized with. It is only recorded in spawned
+    /// entities, the prefabs are already transformed
+    pub transform: FormationTransform,
+}
+
+pub struct PrefabPlan<'a> {
+    pub prefab: &'a Prefab,
+    /// Motion that passes the shifted prefab position at `pass_time`
+    pub motion: BiArcCurveMotion,
+    /// Time at which the prefab passes its shifted position
+    pub pass_time: f32,
+    /// Time the prefab needs to leave the world from its shifted position
+    pub departure_time: f32,
+    /// Time from which the prefab is kept apart from other entities. It is when the prefab enters
+    /// the world grown by the safety margin, but never before the prefab is spawned
+    pub collision_start_time: f32,
+    /// Time until which the prefab is kept apart from other entities, when it leaves the world
+    /// grown by the safety margin
+    pub collision_end_time: f32,
+    pub shape: ShapeHandle<f32>,
+}
+
+impl<'a> FeaturePlan<'a> {
+    /// * `feature` - feature to spawn
+    /// * `world` - visible world
+    /// * `feature_shift` - shift applied to all prefabs of the feature
+    /// * `config` - generator settings providing the world safety margin
+    ///
+    pub fn new(feature: &'a Feature, world: &VisibleWorld, feature_shift: &Vector3<f32>, config: &GeneratorConfig) -> FeaturePlan<'a> {
+        let max_approach_time = feature.max_approach_time(world, feature_shift);
+        let pass_time = max_approach_time + feature.priority as f32;
+        FeaturePlan {
+            feature,
+            feature_shift: *feature_shift,
+            max_approach_time,
+            prefabs: feature.prefabs
+                .iter()
+                .map(|prefab| PrefabPlan {
+                    prefab,
+                    motion: prefab.motion(pass_time, feature_shift),
+                    pass_time,
+                    departure_time: prefab.find_departure_time_in_world(world, feature_shift),
+                    collision_start_time: (pass_time - prefab.find_approach_time_in_loosened_world(world, feature_shift, config.world_safety_margin))
+                        .max(feature.priority as f32),
+                    collision_end_time: pass_time + prefab.find_departure_time_in_loosened_world(world, feature_shift, config.world_safety_margin),
+                    shape: prefab.collision_shape_handle(),
+                })
+                .collect(),
+            transform: FormationTransform::default(),
+        }
+    }
+
+    /// Returns the smallest distance across the world flow between a prefab and an obstacle at the
+    /// time the prefab passes its shifted position. Returns infinity if there are no obstacles
+    ///
+    /// * `obstacles` - entities that are still inside the visible world
+    /// * `world` - visible world
+    /// * `time_travelled` - time at which the feature would be spawned
+    ///
+    pub fn lateral_gap(&self, obstacles: &VecDeque<CollidableEntity>, world: &VisibleWorld, time_travelled: f32) -> f32 {
+        let flow_direction = world.flow_direction.into_inner();
+        self.prefabs
+            .iter()
+            .flat_map(|prefab_plan| {
+                let position = prefab_plan.motion.position_at_time(prefab_plan.pass_time).translation.vector;
+                obstacles
+                    .iter()
+                    .filter(move |obstacle| prefab_plan.prefab.collides_with(&obstacle.prefab))
+                    .map(move |obstacle| {
+                        let offset = obstacle.motion
+                            .position_at_time(time_travelled + prefab_plan.pass_time)
+                            .translation
+                            .vector - position;
+                        (offset - flow_direction * offset.dot(&flow_direction)).magnitude()
+                    })
+            })
+            .fold(f32::INFINITY, f32::min)
+    }
+}
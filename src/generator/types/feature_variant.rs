@@ -0,0 +1,52 @@
+use serde::{Serialize, Deserialize};
+use crate::generator::types::{Prefab, Curve};
+
+/// An alternative formation of a feature. Each spawn of a feature that lists variants picks one of
+/// them with a probability proportional to its weight
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct FeatureVariant {
+    pub prefabs: Vec<Prefab>,
+    #[serde(default = "default_variant_weight")]
+    pub weight: f32,
+    /// Weight over the level time. Replaces `weight` when present
+    #[serde(default)]
+    pub weight_curve: Option<Curve>,
+}
+
+impl FeatureVariant {
+    /// Checks if this variant can be used by the generator. Returns a list of human-readable
+    /// problems that is empty if the variant is valid
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.prefabs.is_empty() {
+            problems.push("prefabs must not be empty".to_string());
+        }
+        if !(self.weight.is_finite() && self.weight >= 0.) {
+            problems.push(format!("weight must be finite and non-negative, got {}", self.weight));
+        }
+        if let Some(curve) = &self.weight_curve {
+            problems.extend(curve.validate().into_iter().map(|problem| format!("weight_curve: {}", problem)));
+            if curve.value_range().0 < 0. {
+                problems.push("weight_curve must not go below zero".to_string());
+            }
+        }
+        for (index, prefab) in self.prefabs.iter().enumerate() {
+            problems.extend(prefab.validate().into_iter().map(|problem| format!("prefab {}: {}", index, problem)));
+        }
+        problems
+    }
+
+    /// Returns the weight of this variant at a given level time
+    pub fn weight_at(&self, time: f32) -> f32 {
+        self.weight_curve.as_ref().map_or(self.weight, |curve| curve.evaluate(time))
+    }
+
+    /// Returns true if this variant can be picked at some point of the level
+    pub(crate) fn can_be_picked(&self) -> bool {
+        self.weight_curve.as_ref().map_or(self.weight > 0., |curve| curve.value_range().1 > 0.)
+    }
+}
+
+fn default_variant_weight() -> f32 {
+    1.
+}
@@ -0,0 +1,32 @@
+use nalgebra::UnitQuaternion;
+use crate::generator::types::VisibleWorld;
+
+/// Random transformation applied to prefabs of a feature at a single spawn. Prefabs are mirrored
+/// first, then scaled and finally rotated, all about the feature origin
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FormationTransform {
+    /// Angle of the rotation about the world flow axis, in radians
+    pub rotation_angle: f32,
+    /// Uniform scale factor
+    pub scale: f32,
+    /// Whether prefabs are reflected across the plane spanned by the world flow axis and the
+    /// second lateral axis
+    pub mirrored: bool,
+}
+
+impl Default for FormationTransform {
+    fn default() -> Self {
+        FormationTransform {
+            rotation_angle: 0.,
+            scale: 1.,
+            mirrored: false,
+        }
+    }
+}
+
+impl FormationTransform {
+    /// Returns the rotation about the flow axis of a world
+    pub fn rotation(&self, world: &VisibleWorld) -> UnitQuaternion<f32> {
+        UnitQuaternion::from_axis_angle(&world.flow_direction, self.rotation_angle)
+    }
+}
@@ -0,0 +1,24 @@
+use nalgebra::Vector3;
+use crate::generator::types::{CollidableEntity, SpawnRejection};
+
+/// Result of a generation together with the spawn attempts that were rejected on the way. It
+/// helps tuning levels by showing which features struggle to find space and why
+#[derive(Clone, PartialEq, Debug)]
+pub struct GenerationReport {
+    /// Generated entities. Each entity id is its index in this list
+    pub entities: Vec<CollidableEntity>,
+    /// Rejected spawn attempts in the order in which they happened
+    pub rejected_spawns: Vec<RejectedSpawn>,
+}
+
+/// A spawn attempt that was rejected
+#[derive(Clone, PartialEq, Debug)]
+pub struct RejectedSpawn {
+    /// Level time at which the feature would be spawned
+    pub spawn_time: f32,
+    /// Shift with which the feature would be spawned
+    pub feature_shift: Vector3<f32>,
+    /// Ids of the prefabs making up the rejected feature
+    pub prefab_ids: Vec<i32>,
+    pub rejection: SpawnRejection,
+}
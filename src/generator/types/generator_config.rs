@@ -0,0 +1,73 @@
+use serde::{Serialize, Deserialize};
+use crate::generator::types::ShiftSampling;
+
+/// Settings that control how the generator places features
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct GeneratorConfig {
+    /// Minimum distance kept between any two entities. Prefabs that declare their own clearance
+    /// override it and a pair of entities is kept apart by the larger of their clearances
+    #[serde(default)]
+    pub clearance: f32,
+    /// Skips exact collision checks between entities whose swept bounds don't overlap and looks up
+    /// obstacles close to a feature in a lateral grid instead of scanning all of them. It only
+    /// speeds up generation and never changes its results
+    #[serde(default = "default_broad_phase")]
+    pub broad_phase: bool,
+    /// Bounds the volume swept by each obstacle once when it is spawned instead of at every check
+    /// of the broad phase. It only speeds up generation and never changes its results
+    #[serde(default = "default_cache_swept_bounds")]
+    pub cache_swept_bounds: bool,
+    /// Number of shifts tried at each spawn attempt of a feature before the attempt is rejected
+    #[serde(default = "default_shift_attempts")]
+    pub shift_attempts: usize,
+    /// Strategy used to pick the shifts tried at each spawn attempt
+    #[serde(default)]
+    pub shift_sampling: ShiftSampling,
+    /// Distance by which the visible world is grown on every side for collision checks. Entities
+    /// are kept apart only while both of them are inside of the grown world
+    #[serde(default)]
+    pub world_safety_margin: f32,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        GeneratorConfig {
+            clearance: 0.,
+            broad_phase: default_broad_phase(),
+            cache_swept_bounds: default_cache_swept_bounds(),
+            shift_attempts: default_shift_attempts(),
+            shift_sampling: ShiftSampling::default(),
+            world_safety_margin: 0.,
+        }
+    }
+}
+
+impl GeneratorConfig {
+    /// Checks if this config can be used by the generator. Returns a list of human-readable
+    /// problems that is empty if the config is valid
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !(self.clearance.is_finite() && self.clearance >= 0.) {
+            problems.push(format!("clearance must be finite and non-negative, got {}", self.clearance));
+        }
+        if !(self.world_safety_margin.is_finite() && self.world_safety_margin >= 0.) {
+            problems.push(format!("world_safety_margin must be finite and non-negative, got {}", self.world_safety_margin));
+        }
+        if self.shift_attempts == 0 {
+            problems.push("shift_attempts must be at least 1".to_string());
+        }
+        problems
+    }
+}
+
+fn default_broad_phase() -> bool {
+    true
+}
+
+fn default_cache_swept_bounds() -> bool {
+    true
+}
+
+fn default_shift_attempts() -> usize {
+    1
+}
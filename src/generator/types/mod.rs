@@ -1,15 +1,52 @@
 mod collideable_entity;
 mod feature;
+mod feature_variant;
+mod formation_transform;
+mod spawn_period_distribution;
+mod curve;
 mod prefab;
 mod visible_world;
 mod movement;
 mod aabb_serialization;
+mod reflection;
+mod collision_shape;
+mod generator_config;
+mod exclusion_volume;
+mod player_lane;
+mod world_shape;
+mod spawn_constraint;
+mod spawn_rejection;
+mod generation_report;
+mod swept_bounds;
+mod feature_plan;
+mod obstacle_index;
+mod shift_sampling;
 
 
 pub use collideable_entity::CollidableEntity;
+pub(crate) use collideable_entity::is_entity_alive_at;
 pub use feature::Feature;
+pub use feature_variant::FeatureVariant;
+pub use formation_transform::FormationTransform;
+pub use spawn_period_distribution::SpawnPeriodDistribution;
+pub use curve::{Curve, CurveKeyframe, CurveInterpolation};
 pub use prefab::Prefab;
-pub use visible_world::VisibleWorld;
+pub(crate) use prefab::default_collision_mask;
+pub use visible_world::{VisibleWorld, default_flow_direction};
 pub use movement::Movement;
+pub use generator_config::GeneratorConfig;
+pub use shift_sampling::ShiftSampling;
+pub use exclusion_volume::ExclusionVolume;
+pub use player_lane::PlayerLane;
+pub use world_shape::{WorldShape, WorldShapeKind};
+pub use spawn_constraint::SpawnConstraint;
+pub use spawn_rejection::SpawnRejection;
+pub use generation_report::{GenerationReport, RejectedSpawn};
+pub(crate) use swept_bounds::{SweptBounds, SWEPT_BOUNDS_TOLERANCE, bounding_radius};
+pub(crate) use feature_plan::FeaturePlan;
+pub(crate) use obstacle_index::ObstacleIndex;
+pub use collision_shape::{CollisionShape, CompoundShapePart};
+pub(crate) use collision_shape::cylinder_prism_points;
 pub use aabb_serialization::{serialize_aabb, deserialize_aabb};
+pub(crate) use reflection::{mirror_vector, mirror_rotation};
 
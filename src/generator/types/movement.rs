@@ -1,5 +1,16 @@
-use nalgebra::{Vector3, Unit};
+use nalgebra::{Vector3, Unit, UnitQuaternion, Isometry3};
 use serde::{Serialize, Deserialize};
+use std::f32::consts::FRAC_PI_2;
+use crate::generator::types::mirror_vector;
+use crate::generator::bi_arc_motion::BiArcCurveMotion;
+
+/// Smallest allowed distance between an arc angle and ±90°. Closer than that `tan` of the angle
+/// grows so big that approach and departure lines become numerically meaningless
+const MAX_ARC_ANGLE_EPSILON: f32 = 0.001;
+
+/// Smallest allowed length of a cross product of an arcs plane normal and a baseline velocity
+/// direction
+const MIN_ARCS_PLANE_NORMAL_CROSS: f32 = 0.001;
 
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Movement {
@@ -14,3 +25,256 @@ pub struct Movement {
     pub departure_arc_radius: f32,
     pub departure_rotation_strength: f32,
 }
+
+impl Movement {
+    /// Checks if this movement describes a path that the generator can work with. Returns a list
+    /// of human-readable problems that is empty if the movement is valid
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let is_baseline_velocity_valid = if !self.baseline_velocity.iter().all(|value| value.is_finite()) {
+            problems.push(format!("baseline_velocity must be finite, got {:?}", self.baseline_velocity.as_slice()));
+            false
+        } else if self.baseline_velocity.magnitude() <= f32::EPSILON {
+            problems.push("baseline_velocity must not be zero".to_string());
+            false
+        } else {
+            true
+        };
+        if !self.arcs_plane_normal.iter().all(|value| value.is_finite()) {
+            problems.push(format!("arcs_plane_normal must be a finite non-zero vector, got {:?}", self.arcs_plane_normal.as_slice()));
+        } else if is_baseline_velocity_valid
+            && self.arcs_plane_normal.cross(&self.baseline_velocity.normalize()).magnitude() < MIN_ARCS_PLANE_NORMAL_CROSS {
+            problems.push("arcs_plane_normal must not be parallel to baseline_velocity".to_string());
+        }
+        validate_arc_angle(&mut problems, "approach_arc_angle", self.approach_arc_angle);
+        validate_non_negative(&mut problems, "approach_arc_center_distance", self.approach_arc_center_distance);
+        validate_non_negative(&mut problems, "approach_arc_radius", self.approach_arc_radius);
+        validate_finite(&mut problems, "approach_rotation_strength", self.approach_rotation_strength);
+        validate_arc_angle(&mut problems, "departure_arc_angle", self.departure_arc_angle);
+        validate_non_negative(&mut problems, "departure_arc_center_distance", self.departure_arc_center_distance);
+        validate_non_negative(&mut problems, "departure_arc_radius", self.departure_arc_radius);
+        validate_finite(&mut problems, "departure_rotation_strength", self.departure_rotation_strength);
+        problems
+    }
+
+    /// Returns a movement whose path is a mirror image of this one across a plane going through
+    /// the arcs center. Arcs bend to the other side and rotations are mirrored accordingly
+    ///
+    /// * `plane_normal` - normal of the mirror plane
+    ///
+    pub fn mirrored(&self, plane_normal: &Unit<Vector3<f32>>) -> Movement {
+        Movement {
+            baseline_velocity: mirror_vector(&self.baseline_velocity, plane_normal),
+            arcs_plane_normal: Unit::new_normalize(mirror_vector(&self.arcs_plane_normal, plane_normal)),
+            approach_arc_angle: -self.approach_arc_angle,
+            departure_arc_angle: -self.departure_arc_angle,
+            ..self.clone()
+        }
+    }
+
+    /// Returns a movement whose path is this one rotated about the arcs center
+    ///
+    /// * `rotation` - rotation applied to the path
+    ///
+    pub fn rotated(&self, rotation: &UnitQuaternion<f32>) -> Movement {
+        Movement {
+            baseline_velocity: rotation * self.baseline_velocity,
+            arcs_plane_normal: rotation * self.arcs_plane_normal,
+            ..self.clone()
+        }
+    }
+
+    /// Returns a movement whose path is this one uniformly scaled about the arcs center. The speed
+    /// and arc angles are kept, so arcs are only moved and resized
+    ///
+    /// * `scale` - positive scale factor
+    ///
+    pub fn scaled(&self, scale: f32) -> Movement {
+        Movement {
+            approach_arc_center_distance: self.approach_arc_center_distance * scale,
+            approach_arc_radius: self.approach_arc_radius * scale,
+            departure_arc_center_distance: self.departure_arc_center_distance * scale,
+            departure_arc_radius: self.departure_arc_radius * scale,
+            ..self.clone()
+        }
+    }
+
+    /// Returns a movement that follows the same path as this one at a multiplied speed
+    ///
+    /// * `multiplier` - positive speed multiplier
+    ///
+    pub fn sped_up(&self, multiplier: f32) -> Movement {
+        Movement {
+            baseline_velocity: self.baseline_velocity * multiplier,
+            ..self.clone()
+        }
+    }
+
+    /// Returns a movement that follows the same path as this one but in the opposite direction.
+    /// An entity passes the arcs center at the same rotation as before, the approach and the
+    /// departure arcs swap places
+    pub fn reversed(&self) -> Movement {
+        Movement {
+            baseline_velocity: -self.baseline_velocity,
+            arcs_plane_normal: -self.arcs_plane_normal,
+            approach_arc_angle: self.departure_arc_angle,
+            approach_arc_center_distance: self.departure_arc_center_distance,
+            approach_arc_radius: self.departure_arc_radius,
+            approach_rotation_strength: self.departure_rotation_strength,
+            departure_arc_angle: self.approach_arc_angle,
+            departure_arc_center_distance: self.approach_arc_center_distance,
+            departure_arc_radius: self.approach_arc_radius,
+            departure_rotation_strength: self.approach_rotation_strength,
+        }
+    }
+
+    /// Creates a motion following this movement that passes a given origin at a given time
+    ///
+    /// * `time` - time at which the motion passes its origin
+    /// * `origin` - position and rotation at which the arcs meet
+    ///
+    pub fn motion(&self, time: f32, origin: Isometry3<f32>) -> BiArcCurveMotion {
+        BiArcCurveMotion::new(
+            time,
+            origin,
+            self.baseline_velocity.clone(),
+            self.arcs_plane_normal.clone(),
+            self.approach_arc_angle,
+            self.approach_arc_center_distance,
+            self.approach_arc_radius,
+            self.approach_rotation_strength,
+            self.departure_arc_angle,
+            self.departure_arc_center_distance,
+            self.departure_arc_radius,
+            self.departure_rotation_strength,
+        )
+    }
+}
+
+fn validate_arc_angle(problems: &mut Vec<String>, name: &str, angle: f32) {
+    if !angle.is_finite() || angle.abs() >= FRAC_PI_2 - MAX_ARC_ANGLE_EPSILON {
+        problems.push(format!("{} must be within (-90°, 90°), got {}°", name, angle.to_degrees()));
+    }
+}
+
+fn validate_non_negative(problems: &mut Vec<String>, name: &str, value: f32) {
+    if !value.is_finite() || value < 0. {
+        problems.push(format!("{} must be a finite non-negative number, got {}", name, value));
+    }
+}
+
+fn validate_finite(problems: &mut Vec<String>, name: &str, value: f32) {
+    if !value.is_finite() {
+        problems.push(format!("{} must be finite, got {}", name, value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BiArcCurveMotion;
+    use crate::generator::types::mirror_rotation;
+    use nalgebra::{Isometry3, Translation3, UnitQuaternion};
+    use ncollide3d::interpolation::RigidMotion;
+
+    fn motion(movement: &Movement, start: Isometry3<f32>) -> BiArcCurveMotion {
+        BiArcCurveMotion::new(
+            0.,
+            start,
+            movement.baseline_velocity.clone(),
+            movement.arcs_plane_normal.clone(),
+            movement.approach_arc_angle,
+            movement.approach_arc_center_distance,
+            movement.approach_arc_radius,
+            movement.approach_rotation_strength,
+            movement.departure_arc_angle,
+            movement.departure_arc_center_distance,
+            movement.departure_arc_radius,
+            movement.departure_rotation_strength,
+        )
+    }
+
+    fn movement() -> Movement {
+        Movement {
+            baseline_velocity: Vector3::new(0., 0., -1.),
+            arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+            approach_arc_angle: 45.0_f32.to_radians(),
+            approach_arc_center_distance: 10.0,
+            approach_arc_radius: 10.0,
+            approach_rotation_strength: 1.0,
+            departure_arc_angle: -45.0_f32.to_radians(),
+            departure_arc_center_distance: 10.0,
+            departure_arc_radius: 10.0,
+            departure_rotation_strength: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_validate_valid_movement() {
+        assert!(movement().validate().is_empty());
+    }
+
+    #[test]
+    fn test_validate_invalid_movement() {
+        let zero_velocity = Movement {
+            baseline_velocity: nalgebra::zero(),
+            ..movement()
+        };
+        assert_eq!(zero_velocity.validate(), vec!["baseline_velocity must not be zero".to_string()]);
+
+        let right_angle = Movement {
+            approach_arc_angle: -90.0_f32.to_radians(),
+            ..movement()
+        };
+        assert_eq!(right_angle.validate().len(), 1);
+
+        let negative_radius = Movement {
+            departure_arc_radius: -1.,
+            ..movement()
+        };
+        assert_eq!(negative_radius.validate().len(), 1);
+
+        let parallel_normal = Movement {
+            arcs_plane_normal: Unit::new_normalize(Vector3::new(0., 0., 1.)),
+            ..movement()
+        };
+        assert_eq!(parallel_normal.validate(), vec!["arcs_plane_normal must not be parallel to baseline_velocity".to_string()]);
+    }
+
+    #[test]
+    fn test_mirrored() {
+        let plane_normal = Unit::new_normalize(Vector3::new(0., 1., 1.));
+        let start_rotation = UnitQuaternion::from_euler_angles(0.3, -0.2, 0.5);
+        let start = Isometry3::from_parts(Translation3::new(1., 2., 3.), start_rotation);
+        let mirrored_start = Isometry3::from_parts(
+            Translation3::from(mirror_vector(&Vector3::new(1., 2., 3.), &plane_normal)),
+            mirror_rotation(&start_rotation, &plane_normal),
+        );
+        let original_motion = motion(&movement(), start);
+        let mirrored_motion = motion(&movement().mirrored(&plane_normal), mirrored_start);
+
+        for time in [-30., -15., -5., 0., 5., 15., 30.].iter() {
+            let original_position = original_motion.position_at_time(*time).translation.vector;
+            let mirrored_position = mirrored_motion.position_at_time(*time).translation.vector;
+            assert_relative_eq!(mirror_vector(&original_position, &plane_normal), mirrored_position, epsilon = 1.0e-4);
+
+            let original_rotation = original_motion.rotation_at_time(*time);
+            let mirrored_rotation = mirrored_motion.rotation_at_time(*time);
+            assert!(mirror_rotation(&original_rotation, &plane_normal).angle_to(&mirrored_rotation) < 1.0e-3);
+        }
+    }
+
+    #[test]
+    fn test_reversed() {
+        let start = Isometry3::from_parts(Translation3::new(1., 2., 3.), UnitQuaternion::from_euler_angles(0.3, -0.2, 0.5));
+        let original_motion = motion(&movement(), start);
+        let reversed_motion = motion(&movement().reversed(), start);
+
+        for time in [-30., -15., -5., 0., 5., 15., 30.].iter() {
+            let original_position = original_motion.position_at_time(*time).translation.vector;
+            let reversed_position = reversed_motion.position_at_time(-*time).translation.vector;
+            assert_relative_eq!(original_position, reversed_position, epsilon = 1.0e-4);
+            assert!(original_motion.rotation_at_time(*time).angle_to(&reversed_motion.rotation_at_time(-*time)) < 1.0e-3);
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+use nalgebra::{Vector3, Unit};
+use ncollide3d::bounding_volume::{AABB, BoundingVolume};
+use crate::generator::types::{CollidableEntity, VisibleWorld, GeneratorConfig, SweptBounds, SWEPT_BOUNDS_TOLERANCE, bounding_radius};
+use crate::generator::calculate_feature_shift::project_aabb;
+
+/// Number of cells the lateral span of a world is split into along each lateral axis
+const OBSTACLE_INDEX_CELLS: usize = 16;
+
+/// Largest speed across the world flow at which an entity is still treated as keeping its lateral
+/// position. Over the time ranges checked by the generator the drift stays far below the tolerance
+/// of time of impact queries
+const LATERAL_SPEED_EPSILON: f32 = 1.0e-6;
+
+/// Live obstacles indexed by the lateral span of their paths. The plane perpendicular to the world
+/// flow is split into a grid of cells. Entities moving straight along the flow keep their lateral
+/// position at all times, so each of them is listed only in cells covered by its shape grown by
+/// its clearance. Paths outside of the world fall into the border cells. Entities that drift
+/// sideways can reach any cell given enough time and are always returned as candidates. With the
+/// broad phase and swept bounds caching enabled the volume swept by each obstacle is bounded once
+/// when it is inserted
+pub struct ObstacleIndex {
+    /// Obstacles in the order they were inserted
+    entities: VecDeque<CollidableEntity>,
+    /// Volumes swept by obstacles while they are checked against collisions, in the same order as
+    /// `entities`. Empty if they are not cached
+    swept_bounds: VecDeque<Option<SweptBounds>>,
+    /// Insertion numbers of obstacles, in the same order as `entities`
+    serials: VecDeque<usize>,
+    next_serial: usize,
+    lateral_axes: (Unit<Vector3<f32>>, Unit<Vector3<f32>>),
+    origin: (f32, f32),
+    cell_size: (f32, f32),
+    /// Insertion numbers of obstacles keeping their lateral position, listed per cell
+    cells: Vec<Vec<usize>>,
+    /// Insertion numbers of obstacles that drift sideways
+    drifting: Vec<usize>,
+    config: GeneratorConfig,
+}
+
+impl ObstacleIndex {
+    /// * `world` - visible world whose lateral span is split into cells
+    /// * `config` - generator settings providing the default clearance of obstacles
+    ///
+    pub fn new(world: &VisibleWorld, config: &GeneratorConfig) -> ObstacleIndex {
+        let lateral_axes = world.lateral_axes();
+        let (min_x, max_x) = world.project(&lateral_axes.0);
+        let (min_y, max_y) = world.project(&lateral_axes.1);
+        ObstacleIndex {
+            entities: VecDeque::new(),
+            swept_bounds: VecDeque::new(),
+            serials: VecDeque::new(),
+            next_serial: 0,
+            lateral_axes,
+            origin: (min_x, min_y),
+            cell_size: (cell_size(min_x, max_x), cell_size(min_y, max_y)),
+            cells: vec![Vec::new(); OBSTACLE_INDEX_CELLS * OBSTACLE_INDEX_CELLS],
+            drifting: Vec::new(),
+            config: config.clone(),
+        }
+    }
+
+    /// Returns all obstacles in the order they were inserted
+    pub fn entities(&self) -> &VecDeque<CollidableEntity> {
+        &self.entities
+    }
+
+    pub fn insert(&mut self, entity: CollidableEntity) {
+        let serial = self.next_serial;
+        self.next_serial += 1;
+        match self.lateral_bounds(&entity) {
+            Some(bounds) => {
+                let (columns, rows) = self.cell_ranges(&bounds);
+                for row in rows {
+                    for column in columns.clone() {
+                        self.cells[row * OBSTACLE_INDEX_CELLS + column].push(serial);
+                    }
+                }
+            }
+            None => self.drifting.push(serial),
+        }
+        let swept_bounds = if self.config.broad_phase && self.config.cache_swept_bounds {
+            Some(SweptBounds::from_entity(&entity))
+        } else {
+            None
+        };
+        self.entities.push_back(entity);
+        self.swept_bounds.push_back(swept_bounds);
+        self.serials.push_back(serial);
+    }
+
+    /// Keeps only obstacles for which a predicate holds
+    pub fn retain(&mut self, mut predicate: impl FnMut(&CollidableEntity) -> bool) {
+        let mut removed_serials = Vec::new();
+        let mut kept = Vec::with_capacity(self.entities.len());
+        let mut serials = self.serials.iter();
+        self.entities.retain(|entity| {
+            let serial = *serials.next().unwrap();
+            let keep = predicate(entity);
+            if !keep {
+                removed_serials.push(serial);
+            }
+            kept.push(keep);
+            keep
+        });
+        if removed_serials.is_empty() {
+            return;
+        }
+        let mut kept = kept.into_iter();
+        self.swept_bounds.retain(|_| kept.next().unwrap());
+        let is_kept = |serial: &usize| removed_serials.binary_search(serial).is_err();
+        self.serials.retain(is_kept);
+        self.drifting.retain(is_kept);
+        self.cells
+            .iter_mut()
+            .for_each(|cell| cell.retain(is_kept));
+    }
+
+    /// Returns obstacles whose paths may come close to a box sideways, in the order they were
+    /// inserted, together with their swept bounds if they are cached. The box has to be grown by
+    /// the clearance the caller keeps from obstacles
+    ///
+    /// * `bounds` - box bounding a path of a new entity
+    ///
+    pub fn candidates(&self, bounds: &AABB<f32>) -> Vec<(&CollidableEntity, Option<&SweptBounds>)> {
+        let (columns, rows) = self.cell_ranges(bounds);
+        let mut serials: Vec<usize> = rows
+            .flat_map(|row| columns
+                .clone()
+                .flat_map(move |column| self.cells[row * OBSTACLE_INDEX_CELLS + column].iter().cloned()))
+            .chain(self.drifting.iter().cloned())
+            .collect();
+        serials.sort_unstable();
+        serials.dedup();
+        serials
+            .iter()
+            .map(|serial| {
+                let position = self.serials.binary_search(serial).unwrap();
+                (&self.entities[position], self.swept_bounds[position].as_ref())
+            })
+            .collect()
+    }
+
+    /// Returns a box containing an entity at all times, grown by its clearance and the query
+    /// tolerance. Returns none if the entity drifts sideways
+    fn lateral_bounds(&self, entity: &CollidableEntity) -> Option<AABB<f32>> {
+        let velocity = &entity.motion.baseline_velocity;
+        if !entity.motion.is_linear()
+            || velocity.dot(&self.lateral_axes.0).abs() > LATERAL_SPEED_EPSILON
+            || velocity.dot(&self.lateral_axes.1).abs() > LATERAL_SPEED_EPSILON {
+            return None;
+        }
+        let margin = bounding_radius(&entity.prefab.collision_shape_handle())
+            + entity.prefab.clearance(&self.config)
+            + SWEPT_BOUNDS_TOLERANCE;
+        let position = entity.motion.start.translation.vector;
+        Some(AABB::new(position.into(), position.into()).loosened(margin))
+    }
+
+    fn cell_ranges(&self, bounds: &AABB<f32>) -> (Range<usize>, Range<usize>) {
+        let (min_x, max_x) = project_aabb(bounds, &self.lateral_axes.0);
+        let (min_y, max_y) = project_aabb(bounds, &self.lateral_axes.1);
+        (
+            cell_index(min_x, self.origin.0, self.cell_size.0)..cell_index(max_x, self.origin.0, self.cell_size.0) + 1,
+            cell_index(min_y, self.origin.1, self.cell_size.1)..cell_index(max_y, self.origin.1, self.cell_size.1) + 1,
+        )
+    }
+}
+
+fn cell_size(min: f32, max: f32) -> f32 {
+    ((max - min) / OBSTACLE_INDEX_CELLS as f32).max(f32::EPSILON)
+}
+
+/// Returns the cell containing a lateral coordinate. Coordinates outside of the world fall into the
+/// border cells
+fn cell_index(value: f32, origin: f32, cell_size: f32) -> usize {
+    ((value - origin) / cell_size)
+        .floor()
+        .max(0.)
+        .min((OBSTACLE_INDEX_CELLS - 1) as f32) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Point3, UnitQuaternion};
+    use crate::generator::types::{Prefab, Movement};
+
+    fn entity(entity_id: usize, x: f32, velocity: Vector3<f32>) -> CollidableEntity {
+        let prefab = Prefab {
+            prefab_id: 0,
+            position: Vector3::new(x, 0., 0.),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: velocity,
+                arcs_plane_normal: Vector3::x_axis(),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+        };
+        CollidableEntity::new(entity_id, prefab, nalgebra::zero(), 0., -10., 10., 0)
+    }
+
+    #[test]
+    fn test_candidates_overlap_lateral_span() {
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(16., 16., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
+        };
+        let mut index = ObstacleIndex::new(&world, &GeneratorConfig::default());
+        index.insert(entity(0, -10., Vector3::new(0., 0., -1.)));
+        index.insert(entity(1, 10., Vector3::new(0., 0., -1.)));
+        index.insert(entity(2, -10., Vector3::new(1., 0., -1.)));
+
+        let near_right = AABB::from_half_extents(Point3::new(10., 0., -5.), Vector3::new(1., 1., 5.));
+        let candidate_ids: Vec<usize> = index.candidates(&near_right).iter().map(|(entity, _)| entity.entity_id).collect();
+        assert_eq!(candidate_ids, vec![1, 2]);
+
+        index.retain(|entity| entity.entity_id != 1);
+        let candidate_ids: Vec<usize> = index.candidates(&near_right).iter().map(|(entity, _)| entity.entity_id).collect();
+        assert_eq!(candidate_ids, vec![2]);
+        let near_left = AABB::from_half_extents(Point3::new(-10., 0., -5.), Vector3::new(1., 1., 5.));
+        let candidate_ids: Vec<usize> = index.candidates(&near_left).iter().map(|(entity, _)| entity.entity_id).collect();
+        assert_eq!(candidate_ids, vec![0, 2]);
+        assert_eq!(index.entities().len(), 2);
+    }
+
+    #[test]
+    fn test_candidates_keep_cached_swept_bounds() {
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(16., 16., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
+        };
+        let whole_world = AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(16., 16., 10.));
+        let mut index = ObstacleIndex::new(&world, &GeneratorConfig::default());
+        index.insert(entity(0, -10., Vector3::new(0., 0., -1.)));
+        index.insert(entity(1, 10., Vector3::new(0., 0., -2.)));
+        index.retain(|entity| entity.entity_id != 0);
+        let candidates = index.candidates(&whole_world);
+        assert_eq!(candidates.len(), 1);
+        let (entity, swept_bounds) = candidates[0];
+        let expected_bounds = SweptBounds::from_entity(entity);
+        let slabs: Vec<(&AABB<f32>, &AABB<f32>)> = swept_bounds.unwrap().common_slabs(&expected_bounds).collect();
+        assert!(!slabs.is_empty());
+        assert!(slabs.iter().all(|(slab, expected_slab)| slab == expected_slab));
+
+        let mut uncached_index = ObstacleIndex::new(&world, &GeneratorConfig { cache_swept_bounds: false, ..GeneratorConfig::default() });
+        uncached_index.insert(entity(0, -10., Vector3::new(0., 0., -1.)));
+        assert!(uncached_index.candidates(&whole_world)[0].1.is_none());
+    }
+}
@@ -0,0 +1,92 @@
+use nalgebra::{Vector3, UnitQuaternion, Isometry3, Translation3};
+use ncollide3d::bounding_volume::{AABB, BoundingVolume};
+use serde::{Serialize, Deserialize};
+use crate::generator::types::{CollisionShape, Prefab, default_collision_mask};
+use crate::generator::bi_arc_motion::PathMotion;
+
+/// A region a player can occupy to get through the oncoming entities. The generator makes sure that
+/// at any time at least one of the world lanes is not blocked by any entity. A lane can move to
+/// describe a path the player is expected to follow
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct PlayerLane {
+    pub shape: CollisionShape,
+    /// Position of the lane at the level time zero
+    pub position: Vector3<f32>,
+    #[serde(default = "UnitQuaternion::identity")]
+    pub rotation: UnitQuaternion<f32>,
+    /// Velocity with which the lane moves. A lane with a zero velocity is a fixed region
+    #[serde(default)]
+    pub velocity: Vector3<f32>,
+    /// Bits of prefab layers that block this lane
+    #[serde(default = "default_collision_mask")]
+    pub collision_mask: u32,
+}
+
+impl PlayerLane {
+    /// Checks if this lane can be used by the generator. Returns a list of human-readable
+    /// problems that is empty if the lane is valid
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems: Vec<String> = self.shape.validate()
+            .into_iter()
+            .map(|problem| format!("shape: {}", problem))
+            .collect();
+        if !self.position.iter().all(|value| value.is_finite()) {
+            problems.push(format!("position must be finite, got {:?}", self.position.as_slice()));
+        }
+        if !self.velocity.iter().all(|value| value.is_finite()) {
+            problems.push(format!("velocity must be finite, got {:?}", self.velocity.as_slice()));
+        }
+        problems
+    }
+
+    /// Returns true if a prefab counts as an obstacle for a player in this lane
+    pub fn is_blocked_by(&self, prefab: &Prefab) -> bool {
+        prefab.collision_layer & self.collision_mask != 0
+    }
+
+    /// Returns a copy of this lane that reaches each position later by a given delay
+    pub fn delayed(&self, delay: f32) -> PlayerLane {
+        PlayerLane {
+            position: self.position - self.velocity * delay,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the motion of this lane with the time measured relatively to a given time
+    ///
+    /// * `time` - time that becomes zero for the returned motion
+    ///
+    pub fn motion_since(&self, time: f32) -> PathMotion<'static> {
+        PathMotion::Linear {
+            start: Isometry3::from_parts(Translation3::from(self.position + self.velocity * time), self.rotation),
+            velocity: self.velocity,
+        }
+    }
+
+    /// Returns a box containing this lane during a time range
+    pub fn bounds(&self, start_time: f32, end_time: f32) -> AABB<f32> {
+        let shape = self.shape.shape_handle();
+        shape
+            .aabb(&self.motion_since(start_time).position_at_time(0.))
+            .merged(&shape.aabb(&self.motion_since(end_time).position_at_time(0.)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delayed_motion() {
+        let lane = PlayerLane {
+            shape: CollisionShape::Ball { radius: 1. },
+            position: Vector3::new(0., 0., 0.),
+            rotation: UnitQuaternion::identity(),
+            velocity: Vector3::new(1., 0., 0.),
+            collision_mask: u32::MAX,
+        };
+        let delayed_lane = lane.delayed(2.);
+        assert_eq!(lane.motion_since(3.).position_at_time(1.), delayed_lane.motion_since(5.).position_at_time(1.));
+        assert_eq!(delayed_lane.motion_since(5.).position_at_time(1.).translation.vector, Vector3::new(4., 0., 0.));
+    }
+}
@@ -1,10 +1,12 @@
-use nalgebra::{Vector3, UnitQuaternion, Isometry3, Translation3};
+use nalgebra::{Vector3, UnitQuaternion, Isometry3, Translation3, Unit};
 use ncollide3d::bounding_volume::{AABB};
 use crate::generator::types::movement::Movement;
 use crate::VisibleWorld;
-use ncollide3d::query::{RayCast, Ray};
+use ncollide3d::query::Ray;
 use serde::{Serialize, Deserialize};
-use crate::generator::types::{serialize_aabb, deserialize_aabb};
+use crate::generator::types::{serialize_aabb, deserialize_aabb, mirror_vector, mirror_rotation, CollisionShape, GeneratorConfig};
+use ncollide3d::shape::{ShapeHandle, Cuboid};
+use crate::generator::bi_arc_motion::BiArcCurveMotion;
 
 /// Represents single smallest piece of a generated level
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
@@ -14,20 +16,159 @@ pub struct Prefab {
     pub rotation: UnitQuaternion<f32>,
     #[serde(serialize_with = "serialize_aabb", deserialize_with = "deserialize_aabb")]
     pub bounding_box: AABB<f32>,
+    /// Shape used to check this prefab against collisions. When it is not given, a cuboid with
+    /// the bounding box half extents is used
+    #[serde(default)]
+    pub collision_shape: Option<CollisionShape>,
+    /// Minimum distance kept between this prefab and other entities. When it is not given, the
+    /// generator config clearance is used
+    #[serde(default)]
+    pub clearance: Option<f32>,
+    /// Bits of layers this prefab belongs to
+    #[serde(default = "default_collision_layer")]
+    pub collision_layer: u32,
+    /// Bits of layers this prefab is checked against. Two entities are checked against collisions
+    /// only if each of them belongs to a layer the other one collides with
+    #[serde(default = "default_collision_mask")]
+    pub collision_mask: u32,
     pub movement: Movement,
 }
 
 impl Prefab {
+    /// Checks if this prefab can be used by the generator. Returns a list of human-readable
+    /// problems that is empty if the prefab is valid
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !self.position.iter().all(|value| value.is_finite()) {
+            problems.push(format!("position must be finite, got {:?}", self.position.as_slice()));
+        }
+        let half_extents = self.bounding_box.half_extents();
+        if !half_extents.iter().all(|value| value.is_finite() && *value >= 0.) {
+            problems.push(format!("bounding_box half extents must be finite and non-negative, got {:?}", half_extents.as_slice()));
+        }
+        if let Some(collision_shape) = &self.collision_shape {
+            problems.extend(collision_shape.validate().into_iter().map(|problem| format!("collision_shape: {}", problem)));
+        }
+        if let Some(clearance) = self.clearance {
+            if !(clearance.is_finite() && clearance >= 0.) {
+                problems.push(format!("clearance must be finite and non-negative, got {}", clearance));
+            }
+        }
+        problems.extend(self.movement.validate().into_iter().map(|problem| format!("movement: {}", problem)));
+        problems
+    }
+
+    /// Creates a shape used to check this prefab against collisions in ncollide queries
+    pub fn collision_shape_handle(&self) -> ShapeHandle<f32> {
+        match &self.collision_shape {
+            Some(collision_shape) => collision_shape.shape_handle(),
+            None => ShapeHandle::new(Cuboid::new(self.bounding_box.half_extents())),
+        }
+    }
+
+    /// Returns the minimum distance kept between this prefab and other entities
+    ///
+    /// * `config` - generator config providing the default clearance
+    ///
+    pub fn clearance(&self, config: &GeneratorConfig) -> f32 {
+        self.clearance.unwrap_or(config.clearance)
+    }
+
+    /// Returns true if this prefab and the other one must not intersect
+    ///
+    /// * `other` - a prefab to check against
+    ///
+    pub fn collides_with(&self, other: &Prefab) -> bool {
+        self.collision_layer & other.collision_mask != 0 && other.collision_layer & self.collision_mask != 0
+    }
+
+    /// Creates a motion of this prefab that passes its shifted position at a given time
+    ///
+    /// * `time` - time at which the prefab passes its position
+    /// * `feature_shift` - shift of the feature this prefab belongs to
+    ///
+    pub fn motion(&self, time: f32, feature_shift: &Vector3<f32>) -> BiArcCurveMotion {
+        self.movement.motion(time, Isometry3::from_parts(Translation3::from(self.position + feature_shift), self.rotation))
+    }
+
+    /// Returns a prefab that is a mirror image of this one across a plane going through the
+    /// feature origin. Rotations are mirrored by conjugating them with the reflection so the
+    /// bounding box stays exact only for planes perpendicular to the prefab local axes
+    ///
+    /// * `plane_normal` - normal of the mirror plane
+    ///
+    pub fn mirrored(&self, plane_normal: &Unit<Vector3<f32>>) -> Prefab {
+        Prefab {
+            position: mirror_vector(&self.position, plane_normal),
+            rotation: mirror_rotation(&self.rotation, plane_normal),
+            collision_shape: self.collision_shape.as_ref().map(|shape| shape.mirrored(plane_normal)),
+            movement: self.movement.mirrored(plane_normal),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a prefab rotated about the feature origin. The collision shape follows the prefab
+    /// rotation so it is left untouched
+    ///
+    /// * `rotation` - rotation applied to the prefab position, orientation and path
+    ///
+    pub fn rotated(&self, rotation: &UnitQuaternion<f32>) -> Prefab {
+        Prefab {
+            position: rotation * self.position,
+            rotation: rotation * self.rotation,
+            movement: self.movement.rotated(rotation),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a prefab uniformly scaled about the feature origin, including its bounding box,
+    /// collision shape and arcs of its path. Clearance and speed are kept
+    ///
+    /// * `scale` - positive scale factor
+    ///
+    pub fn scaled(&self, scale: f32) -> Prefab {
+        Prefab {
+            position: self.position * scale,
+            bounding_box: AABB::new(self.bounding_box.mins * scale, self.bounding_box.maxs * scale),
+            collision_shape: self.collision_shape.as_ref().map(|shape| shape.scaled(scale)),
+            movement: self.movement.scaled(scale),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a prefab that flies along the same path as this one at a multiplied speed
+    ///
+    /// * `multiplier` - positive speed multiplier
+    ///
+    pub fn sped_up(&self, multiplier: f32) -> Prefab {
+        Prefab {
+            movement: self.movement.sped_up(multiplier),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a prefab that flies along the same path as this one but in the opposite direction
+    pub fn reversed(&self) -> Prefab {
+        Prefab {
+            movement: self.movement.reversed(),
+            ..self.clone()
+        }
+    }
+
     pub fn find_approach_time_in_world(&self, world: &VisibleWorld, shift: &Vector3<f32>) -> f32 {
-        let extended_world_bounds = loosened_unequal(&world.world_bounds, &self.bounding_box.half_extents());
+        self.find_approach_time_in_loosened_world(world, shift, 0.)
+    }
+
+    /// Returns the time this prefab needs to reach its shifted position from the moment it enters
+    /// a world grown by a margin on every side
+    pub fn find_approach_time_in_loosened_world(&self, world: &VisibleWorld, shift: &Vector3<f32>, margin: f32) -> f32 {
+        let half_extents = self.bounding_box.half_extents().add_scalar(margin);
         let baseline_velocity_magnitude = self.movement.baseline_velocity.magnitude();
         let baseline_movement_line_length = self.movement.approach_arc_center_distance + self.movement.approach_arc_angle.sin().abs() * self.movement.approach_arc_radius;
-        let baseline_movement_toi = extended_world_bounds
-            .toi_with_ray(&Isometry3::from_parts(Translation3::identity(), UnitQuaternion::identity()),
-                          &Ray::new((&self.position + shift).into(), -self.movement.baseline_velocity.normalize()),
-                          f32::MAX,
-                          false)
-            .unwrap();
+        let baseline_movement_toi = world.loosened_exit_distance(
+            &Ray::new((&self.position + shift).into(), -self.movement.baseline_velocity.normalize()),
+            &half_extents,
+        );
         if baseline_movement_toi <= baseline_movement_line_length {
             let baseline_movement_velocity_toi = baseline_movement_toi / baseline_velocity_magnitude;
             return baseline_movement_velocity_toi;
@@ -38,29 +179,29 @@ impl Prefab {
             arc_direction * self.movement.approach_arc_angle.tan() * baseline_velocity_magnitude).normalize();
 
         let approach_movement_line_start = &self.position + shift - self.movement.baseline_velocity.normalize() * baseline_movement_line_length;
-        let approach_movement_toi = extended_world_bounds
-            .toi_with_ray(
-                &Isometry3::new(nalgebra::zero(), nalgebra::zero()),
-                &Ray::new(approach_movement_line_start.into(), approach_movement_line_direction),
-                f32::MAX,
-                false,
-            )
-            .unwrap();
+        let approach_movement_toi = world.loosened_exit_distance(
+            &Ray::new(approach_movement_line_start.into(), approach_movement_line_direction),
+            &half_extents,
+        );
         let baseline_movement_velocity_toi = baseline_movement_line_length / baseline_velocity_magnitude;
         let approach_movement_velocity_toi = approach_movement_toi * self.movement.approach_arc_angle.cos() / baseline_velocity_magnitude;
         baseline_movement_velocity_toi + approach_movement_velocity_toi
     }
 
     pub fn find_departure_time_in_world(&self, world: &VisibleWorld, shift: &Vector3<f32>) -> f32 {
-        let extended_world_bounds = loosened_unequal(&world.world_bounds, &self.bounding_box.half_extents());
+        self.find_departure_time_in_loosened_world(world, shift, 0.)
+    }
+
+    /// Returns the time this prefab needs to leave a world grown by a margin on every side from its
+    /// shifted position
+    pub fn find_departure_time_in_loosened_world(&self, world: &VisibleWorld, shift: &Vector3<f32>, margin: f32) -> f32 {
+        let half_extents = self.bounding_box.half_extents().add_scalar(margin);
         let baseline_velocity_magnitude = self.movement.baseline_velocity.magnitude();
         let baseline_movement_line_length = self.movement.departure_arc_center_distance + self.movement.departure_arc_angle.sin().abs() * self.movement.departure_arc_radius;
-        let baseline_movement_toi = extended_world_bounds
-            .toi_with_ray(&Isometry3::from_parts(Translation3::identity(), UnitQuaternion::identity()),
-                          &Ray::new((&self.position + shift).into(), self.movement.baseline_velocity.normalize()),
-                          f32::MAX,
-                          false)
-            .unwrap();
+        let baseline_movement_toi = world.loosened_exit_distance(
+            &Ray::new((&self.position + shift).into(), self.movement.baseline_velocity.normalize()),
+            &half_extents,
+        );
         if baseline_movement_toi <= baseline_movement_line_length {
             let baseline_movement_velocity_toi = baseline_movement_toi / baseline_velocity_magnitude;
             return baseline_movement_velocity_toi;
@@ -71,25 +212,24 @@ impl Prefab {
             arc_direction * self.movement.departure_arc_angle.tan() * baseline_velocity_magnitude).normalize();
 
         let departure_movement_line_start = &self.position + shift + self.movement.baseline_velocity.normalize() * baseline_movement_line_length;
-        let departure_movement_toi = extended_world_bounds
-            .toi_with_ray(
-                &Isometry3::new(nalgebra::zero(), nalgebra::zero()),
-                &Ray::new(departure_movement_line_start.into(), departure_movement_line_direction),
-                f32::MAX,
-                false,
-            )
-            .unwrap();
+        let departure_movement_toi = world.loosened_exit_distance(
+            &Ray::new(departure_movement_line_start.into(), departure_movement_line_direction),
+            &half_extents,
+        );
         let baseline_movement_velocity_toi = baseline_movement_line_length / baseline_velocity_magnitude;
         let departure_movement_velocity_toi = departure_movement_toi * self.movement.departure_arc_angle.cos() / baseline_velocity_magnitude;
         baseline_movement_velocity_toi + departure_movement_velocity_toi
     }
 }
 
-fn loosened_unequal(aabb: &AABB<f32>, amount: &Vector3<f32>) -> AABB<f32> {
-    AABB {
-        mins: &aabb.mins - amount,
-        maxs: &aabb.maxs + amount,
-    }
+/// Layer assigned to prefabs that don't declare one
+pub fn default_collision_layer() -> u32 {
+    1
+}
+
+/// Mask assigned to prefabs that don't declare one. It collides with every layer
+pub fn default_collision_mask() -> u32 {
+    u32::MAX
 }
 
 #[cfg(test)]
@@ -104,6 +244,10 @@ mod tests {
             position: Vector3::new(10., 10., 10.),
             rotation: Default::default(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(2., 2., 2.)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -2.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -118,7 +262,11 @@ mod tests {
             },
         };
         let world = VisibleWorld {
-            world_bounds: AABB::from_half_extents(Point3::new(-10., -10., -10.), Vector3::new(50., 50., 50.))
+            world_bounds: AABB::from_half_extents(Point3::new(-10., -10., -10.), Vector3::new(50., 50., 50.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
 
         let approach_time = prefab.find_approach_time_in_world(&world, &Vector3::new(10., 10., 10.));
@@ -132,6 +280,10 @@ mod tests {
             position: Vector3::new(10., 10., 10.),
             rotation: Default::default(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(2., 2., 2.)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -1.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -146,7 +298,11 @@ mod tests {
             },
         };
         let world = VisibleWorld {
-            world_bounds: AABB::from_half_extents(Point3::new(-10., -10., -10.), Vector3::new(50., 50., 50.))
+            world_bounds: AABB::from_half_extents(Point3::new(-10., -10., -10.), Vector3::new(50., 50., 50.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
 
         let approach_time = prefab.find_approach_time_in_world(&world, &Vector3::new(10., 10., 10.));
@@ -160,6 +316,10 @@ mod tests {
             position: Vector3::new(10., 10., 10.),
             rotation: Default::default(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(2., 2., 2.)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -2.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -174,7 +334,11 @@ mod tests {
             },
         };
         let world = VisibleWorld {
-            world_bounds: AABB::from_half_extents(Point3::new(-10., -10., -10.), Vector3::new(50., 50., 50.))
+            world_bounds: AABB::from_half_extents(Point3::new(-10., -10., -10.), Vector3::new(50., 50., 50.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
 
         let approach_time = prefab.find_departure_time_in_world(&world, &Vector3::new(10., 10., 10.));
@@ -188,6 +352,10 @@ mod tests {
             position: Vector3::new(10., 10., 10.),
             rotation: Default::default(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(2., 2., 2.)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -1.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -202,7 +370,11 @@ mod tests {
             },
         };
         let world = VisibleWorld {
-            world_bounds: AABB::from_half_extents(Point3::new(-10., -10., -10.), Vector3::new(50., 50., 50.))
+            world_bounds: AABB::from_half_extents(Point3::new(-10., -10., -10.), Vector3::new(50., 50., 50.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
 
         let approach_time = prefab.find_departure_time_in_world(&world, &Vector3::new(10., 10., 10.));
@@ -216,6 +388,10 @@ mod tests {
             position: Vector3::new(10., 10., 10.),
             rotation: Default::default(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(2., 2., 2.)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -1.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -230,11 +406,45 @@ mod tests {
             },
         };
         let world = VisibleWorld {
-            world_bounds: AABB::from_half_extents(Point3::new(-10., -10., -10.), Vector3::new(50., 50., 50.))
+            world_bounds: AABB::from_half_extents(Point3::new(-10., -10., -10.), Vector3::new(50., 50., 50.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
 
         let approach_time = prefab.find_approach_time_in_world(&world, &Vector3::new(10., 10., 10.));
         assert_eq!(approach_time, 22.);
     }
+    #[test]
+    fn test_clearance_overrides_config() {
+        let mut prefab = Prefab {
+            prefab_id: 0,
+            position: Vector3::new(0., 0., 0.),
+            rotation: Default::default(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(1., 1., 1.)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.0,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+        };
+        let config = GeneratorConfig { clearance: 0.5, ..GeneratorConfig::default() };
+        assert_eq!(prefab.clearance(&config), 0.5);
+        prefab.clearance = Some(2.);
+        assert_eq!(prefab.clearance(&config), 2.);
+        prefab.clearance = Some(-1.);
+        assert_eq!(prefab.validate().len(), 1);
+    }
 }
-
@@ -0,0 +1,15 @@
+use nalgebra::{Vector3, Unit, UnitQuaternion, Quaternion};
+
+/// Reflects a vector across a plane going through the origin
+pub fn mirror_vector(vector: &Vector3<f32>, plane_normal: &Unit<Vector3<f32>>) -> Vector3<f32> {
+    vector - plane_normal.into_inner() * 2. * vector.dot(plane_normal)
+}
+
+/// Conjugates a rotation with a reflection across a plane going through the origin. The result is
+/// the rotation that a mirrored object has to be rotated by to look like a mirror image of an
+/// object rotated by `rotation`, as long as the object itself is symmetric with respect to that
+/// plane
+pub fn mirror_rotation(rotation: &UnitQuaternion<f32>, plane_normal: &Unit<Vector3<f32>>) -> UnitQuaternion<f32> {
+    let mirrored_axis = mirror_vector(&rotation.imag(), plane_normal);
+    UnitQuaternion::new_unchecked(Quaternion::from_parts(rotation.scalar(), -mirrored_axis))
+}
@@ -0,0 +1,26 @@
+use serde::{Serialize, Deserialize};
+
+/// Strategy used to pick shifts tried when spawning a feature
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ShiftSampling {
+    /// Every shift is drawn from the whole allowed range and the first free one is spawned
+    Random,
+    /// The allowed range is split into equal strata along each lateral axis and one shift is drawn
+    /// from each stratum, so the shifts cover the whole range. The first free one is spawned
+    Stratified,
+    /// Shifts are drawn like with stratified sampling and all of them are checked. The free one
+    /// that comes closest to existing obstacles sideways is spawned, packing features tightly
+    BestFit,
+    /// Shifts are drawn only from intervals in which the volume swept by the feature doesn't
+    /// overlap the volume swept by any obstacle. Intervals are searched once per spawn attempt
+    /// along the lateral x axis, or the y axis if the feature is not shifted along x, while the
+    /// other axis is drawn randomly once for all shifts. Falls back to random shifts when no
+    /// interval is free
+    FreeLanes,
+}
+
+impl Default for ShiftSampling {
+    fn default() -> Self {
+        ShiftSampling::Random
+    }
+}
@@ -0,0 +1,39 @@
+use std::collections::VecDeque;
+use nalgebra::Vector3;
+use crate::generator::types::{Feature, CollidableEntity};
+
+/// A game-specific rule deciding whether a feature may be spawned, e.g. a limit of heavy enemies
+/// in the world. Constraints are checked together with the geometric tests, so rejected spawns
+/// are retried later just like spawns that would make entities intersect. Closures with the same
+/// signature as `can_spawn` implement this trait
+pub trait SpawnConstraint {
+    /// Returns true if a feature may be spawned
+    ///
+    /// * `feature` - feature to check
+    /// * `feature_shift` - shift with which the feature would be spawned
+    /// * `spawn_time` - time at which the feature would be spawned. It shares the timeline with
+    ///             the spawn times of obstacles, which is offset from the level time of the
+    ///             returned entities
+    /// * `obstacles` - entities that are still inside the visible world
+    ///
+    fn can_spawn(
+        &self,
+        feature: &Feature,
+        feature_shift: &Vector3<f32>,
+        spawn_time: f32,
+        obstacles: &VecDeque<CollidableEntity>,
+    ) -> bool;
+}
+
+impl<F> SpawnConstraint for F
+    where F: Fn(&Feature, &Vector3<f32>, f32, &VecDeque<CollidableEntity>) -> bool {
+    fn can_spawn(
+        &self,
+        feature: &Feature,
+        feature_shift: &Vector3<f32>,
+        spawn_time: f32,
+        obstacles: &VecDeque<CollidableEntity>,
+    ) -> bool {
+        self(feature, feature_shift, spawn_time, obstacles)
+    }
+}
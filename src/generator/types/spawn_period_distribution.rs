@@ -0,0 +1,133 @@
+use rand::{RngCore, Rng};
+use serde::{Serialize, Deserialize};
+
+/// Distribution of intervals between consecutive spawns of a feature. Except for the adaptive one,
+/// an interval is drawn after every successful spawn and the next spawn is attempted only once it
+/// passes, so it is the minimum spacing between spawns. Attempts rejected because of collisions
+/// or constraints are retried at every step without drawing a new interval, so the expected rates
+/// given below are only reached when spawns aren't rejected
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum SpawnPeriodDistribution {
+    /// A spawn is attempted exactly every spawn period if the period is strict. Otherwise it is
+    /// attempted at every step with a chance that makes the mean interval equal to the spawn
+    /// period and that grows with every missed spawn
+    Adaptive,
+    /// Intervals follow an exponential distribution with the mean equal to the spawn period, so
+    /// spawns form a Poisson process with the expected rate of one spawn per spawn period
+    Exponential,
+    /// Intervals are drawn uniformly from a range regardless of the spawn period. The expected rate
+    /// is `2 / (min + max)`
+    Uniform {
+        min: f32,
+        max: f32,
+    },
+    /// Intervals follow a normal distribution with the mean equal to the spawn period and are
+    /// clamped to at least `min`. The expected rate is close to one spawn per spawn period as long
+    /// as `min` is a few standard deviations below the period
+    Normal {
+        standard_deviation: f32,
+        min: f32,
+    },
+    /// Intervals equal the spawn period moved by up to `jitter` either way and never go below
+    /// zero. The expected rate is one spawn per spawn period as long as `jitter` doesn't exceed it
+    Jitter {
+        jitter: f32,
+    },
+}
+
+impl Default for SpawnPeriodDistribution {
+    fn default() -> Self {
+        SpawnPeriodDistribution::Adaptive
+    }
+}
+
+impl SpawnPeriodDistribution {
+    /// Checks if this distribution can be used by the generator. Returns a list of human-readable
+    /// problems that is empty if the distribution is valid
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        match *self {
+            SpawnPeriodDistribution::Uniform { min, max } => {
+                if !(min.is_finite() && max.is_finite() && 0. <= min && min <= max) {
+                    problems.push(format!("uniform range must be finite, non-negative and ordered, got [{}, {}]", min, max));
+                }
+            }
+            SpawnPeriodDistribution::Normal { standard_deviation, min } => {
+                if !(standard_deviation.is_finite() && standard_deviation >= 0.) {
+                    problems.push(format!("standard_deviation must be finite and non-negative, got {}", standard_deviation));
+                }
+                if !(min.is_finite() && min >= 0.) {
+                    problems.push(format!("min must be finite and non-negative, got {}", min));
+                }
+            }
+            SpawnPeriodDistribution::Jitter { jitter } => {
+                if !(jitter.is_finite() && jitter >= 0.) {
+                    problems.push(format!("jitter must be finite and non-negative, got {}", jitter));
+                }
+            }
+            SpawnPeriodDistribution::Adaptive | SpawnPeriodDistribution::Exponential => {}
+        }
+        problems
+    }
+
+    /// Draws an interval after which the next spawn is attempted. The adaptive distribution
+    /// doesn't draw any random numbers and returns the spawn period
+    ///
+    /// * `rng` - random number generator
+    /// * `spawn_period` - spawn period of the feature
+    ///
+    pub fn sample_interval(&self, rng: &mut impl RngCore, spawn_period: f32) -> f32 {
+        match *self {
+            SpawnPeriodDistribution::Adaptive => spawn_period,
+            SpawnPeriodDistribution::Exponential => -spawn_period * (1. - rng.gen::<f32>()).ln(),
+            SpawnPeriodDistribution::Uniform { min, max } => if min < max { rng.gen_range(min, max) } else { min },
+            SpawnPeriodDistribution::Normal { standard_deviation, min } => {
+                // Box-Muller transform of two uniform samples
+                let radius = (-2. * (1. - rng.gen::<f32>()).ln()).sqrt();
+                let angle = 2. * std::f32::consts::PI * rng.gen::<f32>();
+                (spawn_period + standard_deviation * radius * angle.cos()).max(min)
+            }
+            SpawnPeriodDistribution::Jitter { jitter } => {
+                let offset = if jitter > 0. { rng.gen_range(-jitter, jitter) } else { 0. };
+                (spawn_period + offset).max(0.)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn samples(distribution: SpawnPeriodDistribution, spawn_period: f32) -> Vec<f32> {
+        let mut rng = StdRng::seed_from_u64(17);
+        (0..20000).map(|_| distribution.sample_interval(&mut rng, spawn_period)).collect()
+    }
+
+    fn mean(samples: &[f32]) -> f32 {
+        samples.iter().sum::<f32>() / samples.len() as f32
+    }
+
+    #[test]
+    fn test_sample_interval_matches_expected_rates() {
+        let exponential = samples(SpawnPeriodDistribution::Exponential, 2.);
+        assert!(exponential.iter().all(|interval| *interval >= 0.));
+        assert_relative_eq!(mean(&exponential), 2., epsilon = 0.05);
+
+        let uniform = samples(SpawnPeriodDistribution::Uniform { min: 1., max: 3. }, 10.);
+        assert!(uniform.iter().all(|interval| (1.0..=3.).contains(interval)));
+        assert_relative_eq!(mean(&uniform), 2., epsilon = 0.05);
+
+        let normal = samples(SpawnPeriodDistribution::Normal { standard_deviation: 0.5, min: 1.5 }, 2.);
+        assert!(normal.iter().all(|interval| *interval >= 1.5));
+        assert!(normal.iter().any(|interval| *interval > 2.5));
+
+        let jitter = samples(SpawnPeriodDistribution::Jitter { jitter: 0.5 }, 2.);
+        assert!(jitter.iter().all(|interval| (1.5..=2.5).contains(interval)));
+        assert_relative_eq!(mean(&jitter), 2., epsilon = 0.05);
+
+        assert_eq!(samples(SpawnPeriodDistribution::Adaptive, 2.)[0], 2.);
+    }
+}
@@ -0,0 +1,38 @@
+use nalgebra::Point3;
+
+/// Reason why a feature could not be spawned
+#[derive(Clone, PartialEq, Debug)]
+pub enum SpawnRejection {
+    /// A prefab path comes closer to the path of an entity already in the world than allowed
+    ObstacleCollision {
+        /// Index of the colliding prefab in the feature prefabs
+        prefab_index: usize,
+        /// Id of the entity the prefab collides with
+        obstacle_entity_id: usize,
+        /// Time from the spawn attempt to the first contact
+        time_of_impact: f32,
+        /// Point of the prefab shape closest to the obstacle at the time of impact
+        contact_point: Point3<f32>,
+    },
+    /// A prefab path crosses an exclusion volume while the volume exists
+    ExclusionVolumeCollision {
+        /// Index of the colliding prefab in the feature prefabs
+        prefab_index: usize,
+        /// Index of the volume in the world exclusion volumes
+        volume_index: usize,
+        /// Time from the spawn attempt to the first contact
+        time_of_impact: f32,
+        /// Point of the prefab shape closest to the volume at the time of impact
+        contact_point: Point3<f32>,
+    },
+    /// Spawning would leave a player without any free lane
+    AllPlayerLanesBlocked,
+    /// None of the feature variants has a positive weight at the spawn time, so there was nothing
+    /// to spawn. Such a spawn has no shift and no prefab ids
+    NoVariantAvailable,
+    /// A custom spawn constraint rejected the feature
+    ConstraintFailed {
+        /// Index of the constraint in the list passed to the generator
+        constraint_index: usize,
+    },
+}
@@ -0,0 +1,94 @@
+use nalgebra::Isometry3;
+use ncollide3d::bounding_volume::{AABB, BoundingVolume};
+use ncollide3d::shape::ShapeHandle;
+use crate::generator::bi_arc_motion::BiArcCurveMotion;
+use crate::generator::types::CollidableEntity;
+
+/// Duration of a time slab a swept volume is split into. Shorter slabs prune more pairs of
+/// entities that cross the same place at different times, at the cost of computing more boxes
+const SWEPT_BOUNDS_SLAB_DURATION: f32 = 0.5;
+
+/// Distance added to the margin between swept volumes to make up for the tolerance of time of
+/// impact queries, so that the broad phase never skips a pair the exact check would reject
+pub(crate) const SWEPT_BOUNDS_TOLERANCE: f32 = 0.01;
+
+/// Space-time volume swept by a shape following a motion. Time is split into slabs of equal
+/// duration aligned to multiples of it, each bounded by a box containing the shape for the part of
+/// the slab inside the swept time range. Since all volumes share the slabs, volumes swept over
+/// different time ranges can be compared and two volumes can only collide if boxes of some common
+/// slab overlap
+#[derive(Clone, Debug)]
+pub struct SweptBounds {
+    first_slab: i64,
+    slabs: Vec<AABB<f32>>,
+}
+
+impl SweptBounds {
+    /// * `motion` - motion of the shape
+    /// * `shape` - swept shape. Its rotation is ignored by bounding it with a sphere around the
+    ///             motion origin
+    /// * `start_time` - start of the swept time range. Must be finite
+    /// * `end_time` - end of the swept time range. Must be finite
+    ///
+    pub fn new(motion: &BiArcCurveMotion, shape: &ShapeHandle<f32>, start_time: f32, end_time: f32) -> SweptBounds {
+        let shape_radius = bounding_radius(shape);
+        SweptBounds::from_slab_bounds(start_time, end_time, |slab_start, slab_end| {
+            motion
+                .translation_bounds(slab_start, slab_end)
+                .loosened(shape_radius)
+        })
+    }
+
+    /// * `start_time` - start of the swept time range. Must be finite
+    /// * `end_time` - end of the swept time range. Must be finite
+    /// * `slab_bounds` - returns a box containing the swept volume between two times
+    ///
+    pub fn from_slab_bounds(start_time: f32, end_time: f32, slab_bounds: impl Fn(f32, f32) -> AABB<f32>) -> SweptBounds {
+        let first_slab = (start_time / SWEPT_BOUNDS_SLAB_DURATION).floor() as i64;
+        let end_slab = if start_time <= end_time {
+            ((end_time / SWEPT_BOUNDS_SLAB_DURATION).floor() as i64 + 1).max(first_slab + 1)
+        } else {
+            first_slab
+        };
+        SweptBounds {
+            first_slab,
+            slabs: (first_slab..end_slab)
+                .map(|slab| slab_bounds(
+                    (slab as f32 * SWEPT_BOUNDS_SLAB_DURATION).max(start_time),
+                    ((slab + 1) as f32 * SWEPT_BOUNDS_SLAB_DURATION).min(end_time),
+                ))
+                .collect(),
+        }
+    }
+
+    /// Bounds the volume swept by an entity while it has to be checked against collisions. An entity
+    /// without a limit on that time is bounded while it is inside the visible world
+    pub fn from_entity(entity: &CollidableEntity) -> SweptBounds {
+        let start_time = if entity.collision_start_time.is_finite() { entity.collision_start_time } else { entity.spawn_time };
+        let end_time = if entity.collision_end_time.is_finite() { entity.collision_end_time } else { entity.world_exit_time() };
+        SweptBounds::new(&entity.motion, &entity.prefab.collision_shape_handle(), start_time, end_time)
+    }
+
+    /// Returns pairs of boxes bounding both volumes over the same slab, in the order of slabs
+    pub fn common_slabs<'a>(&'a self, other: &'a SweptBounds) -> impl Iterator<Item=(&'a AABB<f32>, &'a AABB<f32>)> {
+        let first_slab = self.first_slab.max(other.first_slab);
+        self.slabs
+            .iter()
+            .skip((first_slab - self.first_slab) as usize)
+            .zip(other.slabs.iter().skip((first_slab - other.first_slab) as usize))
+    }
+
+    /// Checks if two volumes come closer than a margin during the time they are both swept
+    pub fn intersects(&self, other: &SweptBounds, margin: f32) -> bool {
+        let margin = margin + SWEPT_BOUNDS_TOLERANCE;
+        self.common_slabs(other)
+            .any(|(slab, other_slab)| slab.loosened(margin).intersects(other_slab))
+    }
+}
+
+
+/// Returns the radius of a sphere around the shape origin that contains the shape in any rotation
+pub(crate) fn bounding_radius(shape: &ShapeHandle<f32>) -> f32 {
+    let shape_bounds = shape.aabb(&Isometry3::identity());
+    shape_bounds.mins.coords.abs().sup(&shape_bounds.maxs.coords.abs()).magnitude()
+}
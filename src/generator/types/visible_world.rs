@@ -1,11 +1,188 @@
 use ncollide3d::bounding_volume::AABB;
+use ncollide3d::query::{Ray, RayCast, PointQuery};
+use nalgebra::{Vector3, Unit, Point3, Isometry3};
 use serde::{Serialize, Deserialize};
-use crate::generator::types::{serialize_aabb, deserialize_aabb};
+use crate::generator::types::{serialize_aabb, deserialize_aabb, ExclusionVolume, PlayerLane, WorldShape};
+use crate::generator::calculate_feature_shift::project_aabb;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Shortest rejection of an axis from a flow direction that can still be used as a lateral axis
+const LATERAL_AXIS_EPSILON: f32 = 0.001;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct VisibleWorld {
+    /// Box bounding the world. It is ignored, and not validated, when the world has a shape
     #[serde(serialize_with = "serialize_aabb", deserialize_with = "deserialize_aabb")]
     pub world_bounds: AABB<f32>,
+    /// Shape of the world if it is not a box
+    #[serde(default)]
+    pub shape: Option<WorldShape>,
+    /// Direction in which the content of this world is streamed. Features are shifted only in a
+    /// plane perpendicular to it
+    #[serde(default = "default_flow_direction")]
+    pub flow_direction: Unit<Vector3<f32>>,
+    /// Regions that no entity may pass through while inside this world
+    #[serde(default)]
+    pub exclusion_volumes: Vec<ExclusionVolume>,
+    /// Regions a player can occupy. When there are any, at least one of them is kept free of
+    /// entities at all times
+    #[serde(default)]
+    pub player_lanes: Vec<PlayerLane>,
 }
 
+impl VisibleWorld {
+    /// Checks if this world can be used by the generator. Returns a list of human-readable
+    /// problems that is empty if the world is valid
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let half_extents = self.world_bounds.half_extents();
+        let bounds_valid = self.world_bounds.center().coords.iter().all(|value| value.is_finite())
+            && half_extents.iter().all(|value| value.is_finite() && *value > 0.);
+        if self.shape.is_none() && !bounds_valid {
+            problems.push(format!(
+                "world_bounds must be finite and have positive extents, got {:?} - {:?}",
+                self.world_bounds.mins.coords.as_slice(),
+                self.world_bounds.maxs.coords.as_slice(),
+            ));
+        }
+        if !self.flow_direction.iter().all(|value| value.is_finite()) {
+            problems.push(format!("flow_direction must be a finite non-zero vector, got {:?}", self.flow_direction.as_slice()));
+        }
+        if let Some(shape) = &self.shape {
+            problems.extend(shape.validate().into_iter().map(|problem| format!("shape: {}", problem)));
+        }
+        for (index, volume) in self.exclusion_volumes.iter().enumerate() {
+            problems.extend(volume.validate().into_iter().map(|problem| format!("exclusion volume {}: {}", index, problem)));
+        }
+        for (index, lane) in self.player_lanes.iter().enumerate() {
+            problems.extend(lane.validate().into_iter().map(|problem| format!("player lane {}: {}", index, problem)));
+        }
+        problems
+    }
+
+    /// Returns the range this world covers when projected onto an axis
+    pub fn project(&self, axis: &Unit<Vector3<f32>>) -> (f32, f32) {
+        match &self.shape {
+            Some(shape) => shape.project(axis),
+            None => project_aabb(&self.world_bounds, axis),
+        }
+    }
+
+    pub fn contains_point(&self, point: &Point3<f32>) -> bool {
+        match &self.shape {
+            Some(shape) => shape.contains_point(point),
+            None => self.world_bounds.contains_point(&Isometry3::identity(), point),
+        }
+    }
+
+    /// Casts a ray from inside of this world grown by a box and returns the distance at which the
+    /// ray leaves it. It tells how long a box moving along the ray stays inside the world
+    ///
+    /// * `ray` - ray to cast
+    /// * `half_extents` - half extents of the box the world is grown by
+    ///
+    pub fn loosened_exit_distance(&self, ray: &Ray<f32>, half_extents: &Vector3<f32>) -> f32 {
+        match &self.shape {
+            Some(shape) => shape.loosened_exit_distance(ray, half_extents),
+            None => loosened_unequal(&self.world_bounds, half_extents)
+                .toi_with_ray(&Isometry3::identity(), ray, f32::MAX, false)
+                .unwrap_or(0.),
+        }
+    }
+
+    /// Returns a copy of this world with all exclusion volumes and player lanes delayed by a given
+    /// time. The generator uses it to move them from level time to its own timeline
+    pub fn delayed(&self, delay: f32) -> VisibleWorld {
+        VisibleWorld {
+            exclusion_volumes: self.exclusion_volumes.iter().map(|volume| volume.delayed(delay)).collect(),
+            player_lanes: self.player_lanes.iter().map(|lane| lane.delayed(delay)).collect(),
+            ..self.clone()
+        }
+    }
 
+    /// Returns two perpendicular directions that span a plane perpendicular to the flow direction.
+    /// Features translated along x and y are shifted along the first and the second one. For a
+    /// flow along the Z axis these are exactly the X and Y axes
+    pub fn lateral_axes(&self) -> (Unit<Vector3<f32>>, Unit<Vector3<f32>>) {
+        let flow_direction = self.flow_direction.into_inner();
+        let reject_flow = |axis: Vector3<f32>| axis - flow_direction * axis.dot(&flow_direction);
+
+        let first_rejection = reject_flow(Vector3::x());
+        let first_axis = if first_rejection.magnitude() > LATERAL_AXIS_EPSILON {
+            Unit::new_normalize(first_rejection)
+        } else {
+            Unit::new_normalize(reject_flow(Vector3::z()))
+        };
+        let second_rejection = reject_flow(Vector3::y()) - first_axis.into_inner() * Vector3::y().dot(&first_axis);
+        let second_axis = if second_rejection.magnitude() > LATERAL_AXIS_EPSILON {
+            Unit::new_normalize(second_rejection)
+        } else {
+            Unit::new_normalize(flow_direction.cross(&first_axis))
+        };
+        (first_axis, second_axis)
+    }
+}
+
+fn loosened_unequal(aabb: &AABB<f32>, amount: &Vector3<f32>) -> AABB<f32> {
+    AABB {
+        mins: &aabb.mins - amount,
+        maxs: &aabb.maxs + amount,
+    }
+}
+
+pub fn default_flow_direction() -> Unit<Vector3<f32>> {
+    -Vector3::z_axis()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Point3;
+    use crate::generator::types::WorldShapeKind;
+
+    #[test]
+    fn test_lateral_axes() {
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
+        };
+        assert_eq!(world.lateral_axes(), (Vector3::x_axis(), Vector3::y_axis()));
+
+        let world = VisibleWorld {
+            flow_direction: Vector3::x_axis(),
+            ..world
+        };
+        let (first_axis, second_axis) = world.lateral_axes();
+        assert_relative_eq!(first_axis.dot(&world.flow_direction), 0.);
+        assert_relative_eq!(second_axis.dot(&world.flow_direction), 0.);
+        assert_relative_eq!(first_axis.dot(&second_axis), 0.);
+
+        let world = VisibleWorld {
+            flow_direction: Unit::new_normalize(Vector3::new(1., -1., -1.)),
+            ..world
+        };
+        let (first_axis, second_axis) = world.lateral_axes();
+        assert_relative_eq!(first_axis.dot(&world.flow_direction), 0., epsilon = 1.0e-6);
+        assert_relative_eq!(second_axis.dot(&world.flow_direction), 0., epsilon = 1.0e-6);
+        assert_relative_eq!(first_axis.dot(&second_axis), 0., epsilon = 1.0e-6);
+    }
+
+    #[test]
+    fn test_validate_ignores_bounds_of_shaped_world() {
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0., 0., 0.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
+        };
+        assert_eq!(world.validate().len(), 1);
+        let world = VisibleWorld {
+            shape: Some(WorldShape::new(WorldShapeKind::Sphere { center: nalgebra::zero(), radius: 10. })),
+            ..world
+        };
+        assert!(world.validate().is_empty());
+    }
+}
@@ -0,0 +1,297 @@
+use nalgebra::{Vector3, UnitQuaternion, Point3, Isometry3, Translation3, Unit};
+use ncollide3d::shape::{Ball, ConvexHull, Cuboid};
+use ncollide3d::query::{Ray, RayCast, PointQuery};
+use serde::{Serialize, Deserialize};
+use std::sync::Arc;
+use crate::generator::types::cylinder_prism_points;
+use crate::generator::bi_arc_motion::PathMotion;
+
+/// Kind and dimensions of a visible world shape other than an axis aligned box
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum WorldShapeKind {
+    Sphere {
+        center: Vector3<f32>,
+        radius: f32,
+    },
+    /// Cylinder with its axis along the local Y axis. It is approximated with a circumscribed prism
+    Cylinder {
+        position: Vector3<f32>,
+        rotation: UnitQuaternion<f32>,
+        half_height: f32,
+        radius: f32,
+    },
+    /// Camera frustum looking along the local -Z axis
+    Frustum {
+        position: Vector3<f32>,
+        rotation: UnitQuaternion<f32>,
+        /// Vertical field of view in radians
+        vertical_fov: f32,
+        /// Width divided by height
+        aspect_ratio: f32,
+        near: f32,
+        far: f32,
+    },
+    /// Convex hull of a cloud of points
+    ConvexPolyhedron {
+        points: Vec<Point3<f32>>,
+    },
+}
+
+/// Shape of a visible world other than an axis aligned box. The convex hull of a polytope is built
+/// once when the shape is created and shared by its copies, so the kind can't be changed afterwards.
+/// It is serialized as its kind
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "WorldShapeKind", into = "WorldShapeKind")]
+pub struct WorldShape {
+    kind: WorldShapeKind,
+    /// Hull of the polytope vertices. Spheres and degenerate polytopes have none
+    hull: Option<Arc<ConvexHull<f32>>>,
+}
+
+impl From<WorldShapeKind> for WorldShape {
+    fn from(kind: WorldShapeKind) -> Self {
+        WorldShape::new(kind)
+    }
+}
+
+impl From<WorldShape> for WorldShapeKind {
+    fn from(shape: WorldShape) -> Self {
+        shape.kind
+    }
+}
+
+/// The hull is derived from the kind, so shapes of the same kind are equal
+impl PartialEq for WorldShape {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
+impl WorldShapeKind {
+    /// Returns the vertices of this shape. Spheres have no vertices
+    pub fn vertices(&self) -> Vec<Point3<f32>> {
+        match self {
+            WorldShapeKind::Sphere { .. } => Vec::new(),
+            WorldShapeKind::Cylinder { position, rotation, half_height, radius } => {
+                let isometry = Isometry3::from_parts(Translation3::from(*position), *rotation);
+                cylinder_prism_points(*half_height, *radius)
+                    .iter()
+                    .map(|point| isometry * point)
+                    .collect()
+            }
+            WorldShapeKind::Frustum { position, rotation, vertical_fov, aspect_ratio, near, far } => {
+                let isometry = Isometry3::from_parts(Translation3::from(*position), *rotation);
+                let tan_half_fov = (vertical_fov / 2.).tan();
+                [*near, *far]
+                    .iter()
+                    .flat_map(|distance| {
+                        let half_height = distance * tan_half_fov;
+                        let half_width = half_height * aspect_ratio;
+                        vec![
+                            Point3::new(-half_width, -half_height, -distance),
+                            Point3::new(half_width, -half_height, -distance),
+                            Point3::new(-half_width, half_height, -distance),
+                            Point3::new(half_width, half_height, -distance),
+                        ]
+                    })
+                    .map(|point| isometry * point)
+                    .collect()
+            }
+            WorldShapeKind::ConvexPolyhedron { points } => points.clone(),
+        }
+    }
+}
+
+impl WorldShape {
+    /// Creates a shape of a given kind and builds the hull of its vertices
+    ///
+    /// * `kind` - kind and dimensions of the shape
+    ///
+    pub fn new(kind: WorldShapeKind) -> WorldShape {
+        let vertices = kind.vertices();
+        // Shapes with non-finite vertices are rejected by validation, so they need no hull
+        let hull = match &kind {
+            WorldShapeKind::Sphere { .. } => None,
+            _ if !vertices.iter().all(|point| point.coords.iter().all(|value| value.is_finite())) => None,
+            _ => ConvexHull::try_from_points(&vertices).map(Arc::new),
+        };
+        WorldShape { kind, hull }
+    }
+
+    /// Returns the kind and dimensions of this shape
+    pub fn kind(&self) -> &WorldShapeKind {
+        &self.kind
+    }
+
+    /// Checks if this shape can be used as a visible world. Returns a list of human-readable
+    /// problems that is empty if the shape is valid
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        match &self.kind {
+            WorldShapeKind::Sphere { center, radius } => {
+                if !center.iter().all(|value| value.is_finite()) {
+                    problems.push(format!("sphere center must be finite, got {:?}", center.as_slice()));
+                }
+                if !(radius.is_finite() && *radius > 0.) {
+                    problems.push(format!("sphere radius must be finite and positive, got {}", radius));
+                }
+            }
+            WorldShapeKind::Cylinder { position, half_height, radius, .. } => {
+                if !position.iter().all(|value| value.is_finite()) {
+                    problems.push(format!("cylinder position must be finite, got {:?}", position.as_slice()));
+                }
+                if !(half_height.is_finite() && *half_height > 0. && radius.is_finite() && *radius > 0.) {
+                    problems.push(format!("cylinder dimensions must be finite and positive, got half_height {} and radius {}", half_height, radius));
+                }
+            }
+            WorldShapeKind::Frustum { position, vertical_fov, aspect_ratio, near, far, .. } => {
+                if !position.iter().all(|value| value.is_finite()) {
+                    problems.push(format!("frustum position must be finite, got {:?}", position.as_slice()));
+                }
+                if !(*vertical_fov > 0. && *vertical_fov < std::f32::consts::PI) {
+                    problems.push(format!("frustum vertical_fov must be within (0, PI), got {}", vertical_fov));
+                }
+                if !(aspect_ratio.is_finite() && *aspect_ratio > 0.) {
+                    problems.push(format!("frustum aspect_ratio must be finite and positive, got {}", aspect_ratio));
+                }
+                if !(*near > 0. && near < far && far.is_finite()) {
+                    problems.push(format!("frustum planes must satisfy 0 < near < far, got near {} and far {}", near, far));
+                }
+            }
+            WorldShapeKind::ConvexPolyhedron { points } => {
+                if !points.iter().all(|point| point.coords.iter().all(|value| value.is_finite())) {
+                    problems.push("convex polyhedron points must be finite".to_string());
+                } else if self.hull.is_none() {
+                    problems.push(format!("convex polyhedron of {} points is degenerate, points must not be coplanar", points.len()));
+                }
+            }
+        }
+        problems
+    }
+
+    /// Returns the vertices of this shape. Spheres have no vertices
+    pub fn vertices(&self) -> Vec<Point3<f32>> {
+        self.kind.vertices()
+    }
+
+    /// Returns the range this shape covers when projected onto an axis
+    pub fn project(&self, axis: &Unit<Vector3<f32>>) -> (f32, f32) {
+        match &self.kind {
+            WorldShapeKind::Sphere { center, radius } => {
+                let projected_center = center.dot(axis);
+                (projected_center - radius, projected_center + radius)
+            }
+            _ => self.vertices()
+                .iter()
+                .map(|vertex| vertex.coords.dot(axis))
+                .fold((f32::MAX, f32::MIN), |(min, max), value| (min.min(value), max.max(value))),
+        }
+    }
+
+    pub fn contains_point(&self, point: &Point3<f32>) -> bool {
+        match &self.kind {
+            WorldShapeKind::Sphere { center, radius } => (point.coords - center).magnitude() <= *radius,
+            _ => self.hull.as_ref().map_or(false, |hull| hull.contains_point(&Isometry3::identity(), point)),
+        }
+    }
+
+    /// Casts a ray from inside of this shape grown by a box and returns the distance at which the
+    /// ray leaves it. Polytopes are grown exactly with a Minkowski sum, found by sweeping the box
+    /// back along the ray until it touches the hull, while spheres are grown by the box half
+    /// diagonal. Returns zero if the ray starts outside and never enters the shape
+    ///
+    /// * `ray` - ray to cast
+    /// * `half_extents` - half extents of the box the shape is grown by
+    ///
+    pub fn loosened_exit_distance(&self, ray: &Ray<f32>, half_extents: &Vector3<f32>) -> f32 {
+        let identity = Isometry3::identity();
+        let distance = match &self.kind {
+            WorldShapeKind::Sphere { center, radius } => {
+                Ball::new(radius + half_extents.magnitude())
+                    .toi_with_ray(&Isometry3::new(*center, nalgebra::zero()), ray, f32::MAX, false)
+            }
+            _ => self.hull.as_ref().and_then(|hull| {
+                let speed = ray.dir.magnitude();
+                if speed == 0. {
+                    return None;
+                }
+                // The sweep starts where the box is past the shape along the ray for sure
+                let (_, furthest) = self.project(&Unit::new_unchecked(ray.dir / speed));
+                let sweep_duration = (furthest - ray.origin.coords.dot(&ray.dir) / speed + half_extents.magnitude() + 1.) / speed;
+                if sweep_duration <= 0. {
+                    return None;
+                }
+                let sweep = PathMotion::Linear {
+                    start: Isometry3::from_parts(Translation3::from(ray.point_at(sweep_duration).coords), UnitQuaternion::identity()),
+                    velocity: -ray.dir,
+                };
+                let shape = PathMotion::Linear { start: identity, velocity: nalgebra::zero() };
+                sweep
+                    .time_of_impact(&Cuboid::new(*half_extents), &shape, hull.as_ref(), sweep_duration, 0.)
+                    .map(|time_of_impact| sweep_duration - time_of_impact.toi)
+            }),
+        };
+        distance.unwrap_or(0.)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frustum_projection() {
+        let frustum = WorldShape::new(WorldShapeKind::Frustum {
+            position: nalgebra::zero(),
+            rotation: UnitQuaternion::identity(),
+            vertical_fov: 90.0_f32.to_radians(),
+            aspect_ratio: 2.,
+            near: 1.,
+            far: 10.,
+        });
+        assert!(frustum.validate().is_empty());
+        let (min_x, max_x) = frustum.project(&Vector3::x_axis());
+        assert_relative_eq!(min_x, -20., epsilon = 1.0e-4);
+        assert_relative_eq!(max_x, 20., epsilon = 1.0e-4);
+        let (min_z, max_z) = frustum.project(&Vector3::z_axis());
+        assert_relative_eq!(min_z, -10., epsilon = 1.0e-4);
+        assert_relative_eq!(max_z, -1., epsilon = 1.0e-4);
+        assert!(frustum.contains_point(&Point3::new(0., 0., -5.)));
+        assert!(!frustum.contains_point(&Point3::new(6., 0., -5.)));
+    }
+
+    #[test]
+    fn test_loosened_exit_distance() {
+        let sphere = WorldShape::new(WorldShapeKind::Sphere { center: nalgebra::zero(), radius: 10. });
+        let ray = Ray::new(Point3::new(0., 0., 0.), Vector3::new(0., 0., -1.));
+        assert_relative_eq!(sphere.loosened_exit_distance(&ray, &Vector3::new(0., 0., 1.)), 11., epsilon = 1.0e-4);
+
+        let cube = WorldShape::new(WorldShapeKind::ConvexPolyhedron {
+            points: (0..8)
+                .map(|corner| Point3::new(
+                    if corner & 1 == 0 { -10. } else { 10. },
+                    if corner & 2 == 0 { -10. } else { 10. },
+                    if corner & 4 == 0 { -10. } else { 10. },
+                ))
+                .collect(),
+        });
+        assert_relative_eq!(cube.loosened_exit_distance(&ray, &Vector3::new(1., 2., 3.)), 13., epsilon = 1.0e-3);
+    }
+
+    #[test]
+    fn test_serialize_as_kind() {
+        let kind = WorldShapeKind::Cylinder {
+            position: nalgebra::zero(),
+            rotation: UnitQuaternion::identity(),
+            half_height: 2.,
+            radius: 3.,
+        };
+        let shape = WorldShape::new(kind.clone());
+        let json = serde_json::to_string(&shape).unwrap();
+        assert_eq!(json, serde_json::to_string(&kind).unwrap());
+        let deserialized: WorldShape = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, shape);
+        assert!(deserialized.contains_point(&Point3::new(0., 1., 2.)));
+    }
+}
@@ -0,0 +1,20 @@
+use crate::generator::types::{VisibleWorld, Feature, GeneratorConfig};
+
+/// Checks if a world, features and a config can be passed to #generate. Returns a list of human-readable
+/// problems prefixed with a place they were found in. The list is empty if everything is valid
+///
+/// * `world` - visible world
+/// * `features` - features that will be generated
+/// * `config` - generator settings
+///
+pub fn validate(world: &VisibleWorld, features: &[Feature], config: &GeneratorConfig) -> Vec<String> {
+    let mut problems: Vec<String> = world.validate()
+        .into_iter()
+        .map(|problem| format!("world: {}", problem))
+        .collect();
+    problems.extend(config.validate().into_iter().map(|problem| format!("config: {}", problem)));
+    for (index, feature) in features.iter().enumerate() {
+        problems.extend(feature.validate().into_iter().map(|problem| format!("feature {}: {}", index, problem)));
+    }
+    problems
+}
@@ -0,0 +1,51 @@
+use crate::interop::types::{EntityDescription, EntityTransformDescription};
+use crate::BiArcCurveMotion;
+use ncollide3d::interpolation::RigidMotion;
+use std::slice::{from_raw_parts, from_raw_parts_mut};
+use rayon::prelude::*;
+
+/// Calculates transforms of all entities that are inside a visible world at a given time and
+/// writes them into a caller provided buffer. An entity is considered to be inside from its
+/// `spawn_time` until it leaves the visible world
+///
+/// Returns a number of entities inside the world. If it is bigger than `transforms_capacity` only
+/// the first `transforms_capacity` transforms were written and the call should be repeated with a
+/// bigger buffer. Nothing is written if `transforms_ptr` is null and zero is returned if there
+/// are no entities
+#[no_mangle]
+pub unsafe extern fn bind_entities_at_time(
+    entities_ptr: *const EntityDescription,
+    entities_count: i32,
+    time: f32,
+    transforms_ptr: *mut EntityTransformDescription,
+    transforms_capacity: i32,
+) -> i32 {
+    if entities_ptr.is_null() || entities_count <= 0 {
+        return 0;
+    }
+    let entities = from_raw_parts(entities_ptr, entities_count as usize);
+    let alive_indices: Vec<usize> = entities
+        .par_iter()
+        .enumerate()
+        .filter(|(_, entity)| entity.is_alive_at(time))
+        .map(|(index, _)| index)
+        .collect();
+
+    if !transforms_ptr.is_null() && transforms_capacity > 0 {
+        let transforms_buffer = from_raw_parts_mut(transforms_ptr, transforms_capacity as usize);
+        transforms_buffer
+            .par_iter_mut()
+            .zip(alive_indices.par_iter())
+            .for_each(|(target, &index)| {
+                let entity = &entities[index];
+                let motion: BiArcCurveMotion = entity.clone().into();
+                *target = EntityTransformDescription {
+                    entity_index: index as i32,
+                    prefab_id: entity.prefab_id,
+                    position: motion.position_at_time(time).translation.vector,
+                    rotation: motion.rotation_at_time(time).coords.clone(),
+                };
+            });
+    }
+    alive_indices.len() as i32
+}
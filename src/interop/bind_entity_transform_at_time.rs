@@ -0,0 +1,18 @@
+use crate::interop::types::{EntityDescription, TransformDescription};
+use crate::BiArcCurveMotion;
+use ncollide3d::interpolation::RigidMotion;
+
+/// Calculates position and rotation of an entity returned from #bind_generate at a given time.
+/// The transform is evaluated with the same motion that the generator used for collision checks so
+/// callers don't have to reimplement it
+#[no_mangle]
+pub extern fn bind_entity_transform_at_time(
+    entity_description: EntityDescription,
+    time: f32,
+) -> TransformDescription {
+    let motion: BiArcCurveMotion = entity_description.into();
+    TransformDescription {
+        position: motion.position_at_time(time).translation.vector,
+        rotation: motion.rotation_at_time(time).coords.clone(),
+    }
+}
@@ -1,33 +1,97 @@
-use nalgebra::{Point3};
-use crate::interop::types::{VisibleWorldDescription, FeatureDescription, EntitiesArrayDescription, EntityDescription};
+use nalgebra::{Point3, Unit};
+use crate::interop::types::{VisibleWorldDescription, FeatureDescription, PrefabDescription, EntitiesArrayDescription, EntityDescription, GeneratorConfigDescription};
 use std::slice::from_raw_parts;
-use crate::{Feature, Prefab, VisibleWorld};
+use crate::{Feature, FeatureVariant, Prefab, VisibleWorld};
 use crate::generate;
+use crate::generator::default_flow_direction;
 use ncollide3d::bounding_volume::AABB;
 use rand::thread_rng;
 use std::mem;
 
 /// Unsafe wrapper around #generate() function. It is a callers responsibility to call
 /// #bind_deallocate_vec ona returned array. Otherwise this array will never be deallocated and
-/// will leak memory
+/// will leak memory. If the features or the world are invalid an empty array is returned. Use
+/// #bind_validate to get a description of the problems
 #[no_mangle]
 pub unsafe extern fn bind_generate(
     features_ptr: *const FeatureDescription,
     features_count: i32,
     world_description: VisibleWorldDescription,
+    config_description: GeneratorConfigDescription,
 ) -> EntitiesArrayDescription {
-    let features: Vec<Feature> = from_raw_parts(features_ptr, features_count as usize)
+    let config = config_description.into();
+    let generated_entities = match (features_from_descriptions(features_ptr, features_count), world_from_description(&world_description)) {
+        (Ok(features), Ok(world)) => generate(
+            &world,
+            features.as_slice(),
+            &config,
+            &mut thread_rng(),
+        ).unwrap_or_default(),
+        _ => Vec::new(),
+    };
+
+    let mut entities_descriptions: Vec<EntityDescription> = generated_entities.iter().map(|entity| entity.clone().into()).collect();
+    entities_descriptions.shrink_to_fit();
+    // dbg!(&entities_descriptions);
+    assert_eq!(entities_descriptions.capacity(), entities_descriptions.len());
+    let pointer = entities_descriptions.as_mut_ptr();
+    let length = entities_descriptions.len() as i32;
+    mem::forget(entities_descriptions);
+    EntitiesArrayDescription {
+        pointer,
+        length,
+    }
+}
+
+/// Converts features descriptions passed through FFI into features. Returns a list of
+/// human-readable problems prefixed like the ones returned from #validate if any of the
+/// descriptions can't be converted
+pub(crate) unsafe fn features_from_descriptions(
+    features_ptr: *const FeatureDescription,
+    features_count: i32,
+) -> Result<Vec<Feature>, Vec<String>> {
+    let mut problems = Vec::new();
+    let features = from_raw_parts(features_ptr, features_count as usize)
         .iter()
-        .map(|feature_description| {
-            let feature_prefabs: Vec<Prefab> = from_raw_parts(feature_description.prefabs, feature_description.prefabs_count as usize)
-                .iter()
-                .map(|prefab_description| prefab_description.clone().into())
-                .collect();
+        .enumerate()
+        .map(|(feature_index, feature_description)| {
+            let mut feature_problems = Vec::new();
+            let feature_prefabs = prefabs_from_descriptions(feature_description.prefabs, feature_description.prefabs_count)
+                .unwrap_or_else(|prefab_problems| {
+                    feature_problems.extend(prefab_problems);
+                    Vec::new()
+                });
+            let variants = if feature_description.variants.is_null() || feature_description.variants_count <= 0 {
+                Vec::new()
+            } else {
+                from_raw_parts(feature_description.variants, feature_description.variants_count as usize)
+                    .iter()
+                    .enumerate()
+                    .map(|(variant_index, variant_description)| FeatureVariant {
+                        prefabs: prefabs_from_descriptions(variant_description.prefabs, variant_description.prefabs_count)
+                            .unwrap_or_else(|prefab_problems| {
+                                feature_problems.extend(prefab_problems
+                                    .into_iter()
+                                    .map(|problem| format!("variant {}: {}", variant_index, problem)));
+                                Vec::new()
+                            }),
+                        weight: variant_description.weight,
+                        weight_curve: variant_description.weight_curve.to_curve(),
+                    })
+                    .collect()
+            };
+            problems.extend(feature_problems
+                .into_iter()
+                .map(|problem| format!("feature {}: {}", feature_index, problem)));
 
             Feature {
                 prefabs: feature_prefabs,
+                variants,
                 spawn_period: feature_description.spawn_period,
                 is_spawn_period_strict: feature_description.is_spawn_period_strict,
+                spawn_period_distribution: feature_description.spawn_period_distribution(),
+                spawn_period_curve: feature_description.spawn_period_curve.to_curve(),
+                speed_multiplier_curve: feature_description.speed_multiplier_curve.to_curve(),
                 spawn_count: feature_description.spawn_count,
                 trigger_time: feature_description.trigger_time,
                 priority: feature_description.priority,
@@ -37,37 +101,99 @@ pub unsafe extern fn bind_generate(
                 translate_y: feature_description.translate_y,
                 translate_y_using_bounds: feature_description.translate_y_using_bounds,
                 translate_y_bounds: feature_description.translate_y_bounds.clone(),
+                translate_z: feature_description.translate_z,
+                translate_z_using_bounds: feature_description.translate_z_using_bounds,
+                translate_z_bounds: feature_description.translate_z_bounds.clone(),
+                rotate: feature_description.rotate,
+                rotate_bounds: feature_description.rotate_bounds,
+                scale: feature_description.scale,
+                scale_bounds: feature_description.scale_bounds,
+                mirror: feature_description.mirror,
                 missed_spawns: 0,
                 last_spawn_attempt: f32::MIN,
+                next_spawn_time: 0.,
             }
         })
         .collect();
+    if problems.is_empty() { Ok(features) } else { Err(problems) }
+}
 
+/// Converts prefabs descriptions passed through FFI into prefabs. Returns a list of
+/// human-readable problems prefixed with the prefab index if any of the descriptions can't be
+/// converted
+unsafe fn prefabs_from_descriptions(
+    prefabs_ptr: *const PrefabDescription,
+    prefabs_count: i32,
+) -> Result<Vec<Prefab>, Vec<String>> {
+    if prefabs_ptr.is_null() || prefabs_count <= 0 {
+        return Ok(Vec::new());
+    }
+    let mut problems = Vec::new();
+    let prefabs = from_raw_parts(prefabs_ptr, prefabs_count as usize)
+        .iter()
+        .enumerate()
+        .filter_map(|(index, prefab_description)| match prefab_description.to_prefab() {
+            Ok(prefab) => Some(prefab),
+            Err(problem) => {
+                problems.push(format!("prefab {}: {}", index, problem));
+                None
+            }
+        })
+        .collect();
+    if problems.is_empty() { Ok(prefabs) } else { Err(problems) }
+}
 
-    let world = VisibleWorld {
+/// Converts a world description passed through FFI into a visible world. Returns a list of
+/// human-readable problems prefixed like the ones returned from #validate if any of the exclusion
+/// volumes or player lanes can't be converted
+pub(crate) unsafe fn world_from_description(world_description: &VisibleWorldDescription) -> Result<VisibleWorld, Vec<String>> {
+    let mut problems = Vec::new();
+    let exclusion_volumes = if world_description.exclusion_volumes.is_null() || world_description.exclusion_volumes_count <= 0 {
+        Vec::new()
+    } else {
+        from_raw_parts(world_description.exclusion_volumes, world_description.exclusion_volumes_count as usize)
+            .iter()
+            .enumerate()
+            .filter_map(|(index, volume_description)| match volume_description.to_exclusion_volume() {
+                Ok(volume) => Some(volume),
+                Err(problem) => {
+                    problems.push(format!("world: exclusion volume {}: {}", index, problem));
+                    None
+                }
+            })
+            .collect()
+    };
+    let player_lanes = if world_description.player_lanes.is_null() || world_description.player_lanes_count <= 0 {
+        Vec::new()
+    } else {
+        from_raw_parts(world_description.player_lanes, world_description.player_lanes_count as usize)
+            .iter()
+            .enumerate()
+            .filter_map(|(index, lane_description)| match lane_description.to_player_lane() {
+                Ok(lane) => Some(lane),
+                Err(problem) => {
+                    problems.push(format!("world: player lane {}: {}", index, problem));
+                    None
+                }
+            })
+            .collect()
+    };
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+    Ok(VisibleWorld {
         world_bounds: AABB::from_half_extents(Point3::new(
             world_description.position.x,
             world_description.position.y,
             world_description.position.z,
         ), world_description.half_extents).into(),
-    };
-
-
-    let generated_entities = generate(
-        &world,
-        features.as_slice(),
-        &mut thread_rng(),
-    );
-
-    let mut entities_descriptions: Vec<EntityDescription> = generated_entities.iter().map(|entity| entity.clone().into()).collect();
-    entities_descriptions.shrink_to_fit();
-    // dbg!(&entities_descriptions);
-    assert_eq!(entities_descriptions.capacity(), entities_descriptions.len());
-    let pointer = entities_descriptions.as_mut_ptr();
-    let length = entities_descriptions.len() as i32;
-    mem::forget(entities_descriptions);
-    EntitiesArrayDescription {
-        pointer,
-        length,
-    }
-}
\ No newline at end of file
+        flow_direction: if world_description.flow_direction.magnitude() > f32::EPSILON {
+            Unit::new_normalize(world_description.flow_direction)
+        } else {
+            default_flow_direction()
+        },
+        shape: world_description.shape.to_world_shape(),
+        exclusion_volumes,
+        player_lanes,
+    })
+}
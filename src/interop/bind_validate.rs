@@ -0,0 +1,38 @@
+use crate::interop::types::{VisibleWorldDescription, FeatureDescription, GeneratorConfigDescription};
+use crate::interop::bind_generate::{features_from_descriptions, world_from_description};
+use crate::validate;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::ptr;
+
+/// Checks if features, world and config passed to #bind_generate are valid. Returns a null pointer if they
+/// are, otherwise a null terminated string with one problem per line. It is a callers
+/// responsibility to call #bind_deallocate_string on a returned string
+#[no_mangle]
+pub unsafe extern fn bind_validate(
+    features_ptr: *const FeatureDescription,
+    features_count: i32,
+    world_description: VisibleWorldDescription,
+    config_description: GeneratorConfigDescription,
+) -> *mut c_char {
+    let config = config_description.into();
+    // Descriptions that can't be converted are reported before the converted values are validated
+    let problems = match (features_from_descriptions(features_ptr, features_count), world_from_description(&world_description)) {
+        (Ok(features), Ok(world)) => validate(&world, features.as_slice(), &config),
+        (features, world) => world.err().unwrap_or_default().into_iter().chain(features.err().unwrap_or_default()).collect(),
+    };
+    if problems.is_empty() {
+        return ptr::null_mut();
+    }
+    CString::new(problems.join("\n").replace('\0', ""))
+        .unwrap()
+        .into_raw()
+}
+
+/// Deallocates a string previously returned from #bind_validate function
+#[no_mangle]
+pub unsafe extern fn bind_deallocate_string(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
@@ -1,7 +1,13 @@
 mod types;
 mod bind_generate;
 mod bind_deallocate_vec;
+mod bind_entity_transform_at_time;
+mod bind_entities_at_time;
+mod bind_validate;
 
-pub use self::types::{VisibleWorldDescription, FeatureDescription, PrefabDescription, EntitiesArrayDescription, MovementDescription};
+pub use self::types::{VisibleWorldDescription, FeatureDescription, FeatureVariantDescription, CurveDescription, PrefabDescription, CollisionShapeDescription, EntitiesArrayDescription, EntityDescription, MovementDescription, TransformDescription, EntityTransformDescription, GeneratorConfigDescription, ExclusionVolumeDescription, PlayerLaneDescription, WorldShapeDescription};
 pub use self::bind_generate::bind_generate;
 pub use self::bind_deallocate_vec::bind_deallocate_vec;
+pub use self::bind_entity_transform_at_time::bind_entity_transform_at_time;
+pub use self::bind_entities_at_time::bind_entities_at_time;
+pub use self::bind_validate::{bind_validate, bind_deallocate_string};
@@ -1,6 +1,9 @@
-use nalgebra::{Vector3, Vector2, Vector4, Unit, Quaternion, UnitQuaternion, Point3};
-use crate::{Movement, Prefab, CollidableEntity};
+use nalgebra::{Vector3, Vector2, Vector4, Unit, Quaternion, UnitQuaternion, Point3, Isometry3, Translation3};
+use crate::{Movement, Prefab, SpawnPeriodDistribution, CollidableEntity, BiArcCurveMotion, CollisionShape, CompoundShapePart, GeneratorConfig, ExclusionVolume, PlayerLane, WorldShape, WorldShapeKind, ShiftSampling, Curve, CurveKeyframe, CurveInterpolation};
 use ncollide3d::bounding_volume::AABB;
+use crate::generator::is_entity_alive_at;
+use std::ptr;
+use std::slice::from_raw_parts;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[repr(C)]
@@ -10,6 +13,41 @@ pub struct PrefabDescription {
     pub rotation: Vector4<f32>,
     pub half_extents: Vector3<f32>,
     pub movement: MovementDescription,
+    pub collision_shape: CollisionShapeDescription,
+    /// When false, the generator config clearance is used instead of `clearance`
+    pub use_clearance: bool,
+    pub clearance: f32,
+    pub collision_layer: u32,
+    pub collision_mask: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[repr(C)]
+pub struct GeneratorConfigDescription {
+    pub clearance: f32,
+    /// Number of shifts tried at each spawn attempt. Zero stands for a single shift
+    pub shift_attempts: i32,
+    /// 0 - random, 1 - stratified, 2 - best fit, 3 - free lanes
+    pub shift_sampling: i32,
+    pub world_safety_margin: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct CollisionShapeDescription {
+    /// 0 - cuboid with the prefab half extents, 1 - cuboid, 2 - ball, 3 - capsule, 4 - cylinder,
+    /// 5 - convex hull, 6 - compound
+    pub shape_type: i32,
+    pub half_extents: Vector3<f32>,
+    pub radius: f32,
+    pub half_height: f32,
+    /// Offset of the shape relative to the prefab position. A zero rotation stands for identity
+    pub position: Vector3<f32>,
+    pub rotation: Vector4<f32>,
+    pub points: *const Vector3<f32>,
+    pub points_count: i32,
+    pub parts: *const CollisionShapeDescription,
+    pub parts_count: i32,
 }
 
 #[derive(Debug, Clone)]
@@ -19,6 +57,19 @@ pub struct FeatureDescription {
     pub prefabs_count: i32,
     pub spawn_period: f32,
     pub is_spawn_period_strict: bool,
+    /// 0 - adaptive, 1 - exponential, 2 - uniform between `spawn_period_min` and
+    /// `spawn_period_max`, 3 - normal with `spawn_period_standard_deviation` clamped to
+    /// `spawn_period_min`, 4 - spawn period with `spawn_period_jitter`
+    pub spawn_period_distribution: i32,
+    pub spawn_period_min: f32,
+    pub spawn_period_max: f32,
+    pub spawn_period_standard_deviation: f32,
+    pub spawn_period_jitter: f32,
+    /// Spawn period over the level time. Replaces `spawn_period` if it has any keyframes. It can't
+    /// be used with the uniform distribution
+    pub spawn_period_curve: CurveDescription,
+    /// Multiplier of the prefabs speed over the level time. No keyframes stand for a constant 1
+    pub speed_multiplier_curve: CurveDescription,
     pub spawn_count: i32,
     pub trigger_time: f32,
     pub priority: i32,
@@ -28,6 +79,40 @@ pub struct FeatureDescription {
     pub translate_y: bool,
     pub translate_y_using_bounds: bool,
     pub translate_y_bounds: Vector2<f32>,
+    pub translate_z: bool,
+    pub translate_z_using_bounds: bool,
+    pub translate_z_bounds: Vector2<f32>,
+    /// Angle range in radians of the rotation about the flow axis drawn at each spawn
+    pub rotate: bool,
+    pub rotate_bounds: Vector2<f32>,
+    /// Range of the uniform scale drawn at each spawn
+    pub scale: bool,
+    pub scale_bounds: Vector2<f32>,
+    pub mirror: bool,
+    /// Alternative formations picked by weight at each spawn. A null pointer or a zero count
+    /// stands for spawning `prefabs` every time
+    pub variants: *const FeatureVariantDescription,
+    pub variants_count: i32,
+}
+
+#[derive(Debug, Clone)]
+#[repr(C)]
+pub struct FeatureVariantDescription {
+    pub prefabs: *const PrefabDescription,
+    pub prefabs_count: i32,
+    pub weight: f32,
+    /// Weight over the level time. Replaces `weight` if it has any keyframes
+    pub weight_curve: CurveDescription,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct CurveDescription {
+    /// Keyframes ordered by time, x holds the time and y the value
+    pub keyframes: *const Vector2<f32>,
+    pub keyframes_count: i32,
+    /// 0 - linear, 1 - monotone cubic
+    pub interpolation: i32,
 }
 
 #[derive(Debug, Clone)]
@@ -35,6 +120,61 @@ pub struct FeatureDescription {
 pub struct VisibleWorldDescription {
     pub position: Vector3<f32>,
     pub half_extents: Vector3<f32>,
+    /// Direction in which the content is streamed. A zero vector stands for the default -Z
+    pub flow_direction: Vector3<f32>,
+    pub exclusion_volumes: *const ExclusionVolumeDescription,
+    pub exclusion_volumes_count: i32,
+    pub player_lanes: *const PlayerLaneDescription,
+    pub player_lanes_count: i32,
+    pub shape: WorldShapeDescription,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct WorldShapeDescription {
+    /// 0 - box given by the world position and half extents, 1 - sphere, 2 - cylinder,
+    /// 3 - frustum, 4 - convex polyhedron
+    pub shape_type: i32,
+    /// Center of a sphere or a pose of a cylinder and a frustum
+    pub position: Vector3<f32>,
+    /// A zero rotation stands for identity
+    pub rotation: Vector4<f32>,
+    pub radius: f32,
+    pub half_height: f32,
+    pub vertical_fov: f32,
+    pub aspect_ratio: f32,
+    pub near: f32,
+    pub far: f32,
+    pub points: *const Vector3<f32>,
+    pub points_count: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct ExclusionVolumeDescription {
+    /// Shape of the volume. The prefab bounding box shape type stands for a cuboid with the shape
+    /// half extents
+    pub shape: CollisionShapeDescription,
+    pub position: Vector3<f32>,
+    /// A zero rotation stands for identity
+    pub rotation: Vector4<f32>,
+    pub use_active_from: bool,
+    pub active_from: f32,
+    pub use_active_until: bool,
+    pub active_until: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct PlayerLaneDescription {
+    /// Shape of the lane. The prefab bounding box shape type stands for a cuboid with the shape
+    /// half extents
+    pub shape: CollisionShapeDescription,
+    pub position: Vector3<f32>,
+    /// A zero rotation stands for identity
+    pub rotation: Vector4<f32>,
+    pub velocity: Vector3<f32>,
+    pub collision_mask: u32,
 }
 
 #[derive(Debug, Clone)]
@@ -47,6 +187,20 @@ pub struct EntityDescription {
     pub movement_start_parameter: f32,
     pub movement_end_parameter: f32,
     pub movement: MovementDescription,
+    pub origin_position: Vector3<f32>,
+    pub origin_rotation: Vector4<f32>,
+    /// Uniform scale the entity was spawned with
+    pub scale: f32,
+    /// Whether the entity was spawned as a mirror image. It has to be reflected across its local
+    /// plane whose normal equals the world first lateral axis, the X axis for a flow along Z
+    pub mirrored: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct TransformDescription {
+    pub position: Vector3<f32>,
+    pub rotation: Vector4<f32>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -66,6 +220,15 @@ pub struct MovementDescription {
     pub departure_rotation_strength: f32,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct EntityTransformDescription {
+    pub entity_index: i32,
+    pub prefab_id: i32,
+    pub position: Vector3<f32>,
+    pub rotation: Vector4<f32>,
+}
+
 #[repr(C)]
 pub struct EntitiesArrayDescription {
     pub pointer: *mut EntityDescription,
@@ -106,15 +269,262 @@ impl From<Movement> for MovementDescription {
     }
 }
 
-impl Into<Prefab> for PrefabDescription {
-    fn into(self) -> Prefab {
-        Prefab {
+impl PrefabDescription {
+    /// Converts this description into a prefab. Returns a human-readable problem if the collision
+    /// shape description can't be converted
+    ///
+    /// # Safety
+    ///
+    /// The collision shape description must satisfy #CollisionShapeDescription::to_collision_shape
+    /// requirements
+    pub unsafe fn to_prefab(&self) -> Result<Prefab, String> {
+        let collision_shape = self.collision_shape
+            .to_collision_shape()
+            .map_err(|problem| format!("collision_shape: {}", problem))?;
+        Ok(Prefab {
             prefab_id: self.prefab_id,
             position: self.position,
             rotation: UnitQuaternion::from_quaternion(Quaternion::from(self.rotation)),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), self.half_extents),
+            collision_shape,
+            clearance: if self.use_clearance { Some(self.clearance) } else { None },
+            collision_layer: self.collision_layer,
+            collision_mask: self.collision_mask,
             movement: self.movement.into(),
+        })
+    }
+}
+
+impl Into<GeneratorConfig> for GeneratorConfigDescription {
+    fn into(self) -> GeneratorConfig {
+        GeneratorConfig {
+            clearance: self.clearance,
+            shift_attempts: self.shift_attempts.max(1) as usize,
+            shift_sampling: match self.shift_sampling {
+                1 => ShiftSampling::Stratified,
+                2 => ShiftSampling::BestFit,
+                3 => ShiftSampling::FreeLanes,
+                _ => ShiftSampling::Random,
+            },
+            world_safety_margin: self.world_safety_margin,
+            ..GeneratorConfig::default()
+        }
+    }
+}
+
+impl FeatureDescription {
+    /// Returns the distribution of intervals between spawns selected by this description
+    pub fn spawn_period_distribution(&self) -> SpawnPeriodDistribution {
+        match self.spawn_period_distribution {
+            1 => SpawnPeriodDistribution::Exponential,
+            2 => SpawnPeriodDistribution::Uniform { min: self.spawn_period_min, max: self.spawn_period_max },
+            3 => SpawnPeriodDistribution::Normal { standard_deviation: self.spawn_period_standard_deviation, min: self.spawn_period_min },
+            4 => SpawnPeriodDistribution::Jitter { jitter: self.spawn_period_jitter },
+            _ => SpawnPeriodDistribution::Adaptive,
+        }
+    }
+}
+
+impl Default for CollisionShapeDescription {
+    fn default() -> Self {
+        CollisionShapeDescription {
+            shape_type: 0,
+            half_extents: nalgebra::zero(),
+            radius: 0.,
+            half_height: 0.,
+            position: nalgebra::zero(),
+            rotation: nalgebra::zero(),
+            points: ptr::null(),
+            points_count: 0,
+            parts: ptr::null(),
+            parts_count: 0,
+        }
+    }
+}
+
+impl CollisionShapeDescription {
+    /// Converts this description into a collision shape. Returns None if the prefab bounding box
+    /// should be used instead. Returns a human-readable problem if a part of a compound shape
+    /// doesn't describe a shape, as there is no bounding box it could fall back to
+    ///
+    /// # Safety
+    ///
+    /// `points` and `parts` must point to arrays of at least `points_count` and `parts_count`
+    /// elements respectively whenever the shape type uses them
+    pub unsafe fn to_collision_shape(&self) -> Result<Option<CollisionShape>, String> {
+        let shape = match self.shape_type {
+            1 => CollisionShape::Cuboid { half_extents: self.half_extents },
+            2 => CollisionShape::Ball { radius: self.radius },
+            3 => CollisionShape::Capsule { half_height: self.half_height, radius: self.radius },
+            4 => CollisionShape::Cylinder { half_height: self.half_height, radius: self.radius },
+            5 => CollisionShape::ConvexHull {
+                points: from_raw_parts(self.points, self.points_count.max(0) as usize)
+                    .iter()
+                    .map(|point| Point3::from(*point))
+                    .collect(),
+            },
+            6 => CollisionShape::Compound {
+                parts: from_raw_parts(self.parts, self.parts_count.max(0) as usize)
+                    .iter()
+                    .enumerate()
+                    .map(|(index, part)| match part.to_collision_shape() {
+                        Ok(Some(shape)) => Ok(CompoundShapePart {
+                            position: nalgebra::zero(),
+                            rotation: UnitQuaternion::identity(),
+                            shape,
+                        }),
+                        Ok(None) => Err(format!("part {}: shape_type must be between 1 and 6, got {}", index, part.shape_type)),
+                        Err(problem) => Err(format!("part {}: {}", index, problem)),
+                    })
+                    .collect::<Result<Vec<CompoundShapePart>, String>>()?,
+            },
+            _ => return Ok(None),
+        };
+        let rotation = rotation_from_description(self.rotation);
+        if self.position == Vector3::zeros() && rotation == UnitQuaternion::identity() {
+            return Ok(Some(shape));
+        }
+        Ok(Some(match shape {
+            CollisionShape::Cuboid { half_extents } => CollisionShape::OrientedCuboid { half_extents, position: self.position, rotation },
+            shape => CollisionShape::Compound { parts: vec![CompoundShapePart { position: self.position, rotation, shape }] },
+        }))
+    }
+}
+
+impl Default for CurveDescription {
+    fn default() -> Self {
+        CurveDescription {
+            keyframes: ptr::null(),
+            keyframes_count: 0,
+            interpolation: 0,
+        }
+    }
+}
+
+impl CurveDescription {
+    /// Converts this description into a curve. Returns None if there are no keyframes
+    ///
+    /// # Safety
+    ///
+    /// `keyframes` must point to an array of at least `keyframes_count` elements
+    pub unsafe fn to_curve(&self) -> Option<Curve> {
+        if self.keyframes.is_null() || self.keyframes_count <= 0 {
+            return None;
         }
+        Some(Curve {
+            keyframes: from_raw_parts(self.keyframes, self.keyframes_count as usize)
+                .iter()
+                .map(|keyframe| CurveKeyframe { time: keyframe.x, value: keyframe.y })
+                .collect(),
+            interpolation: match self.interpolation {
+                1 => CurveInterpolation::Cubic,
+                _ => CurveInterpolation::Linear,
+            },
+        })
+    }
+}
+
+impl Default for WorldShapeDescription {
+    fn default() -> Self {
+        WorldShapeDescription {
+            shape_type: 0,
+            position: nalgebra::zero(),
+            rotation: nalgebra::zero(),
+            radius: 0.,
+            half_height: 0.,
+            vertical_fov: 0.,
+            aspect_ratio: 0.,
+            near: 0.,
+            far: 0.,
+            points: ptr::null(),
+            points_count: 0,
+        }
+    }
+}
+
+impl WorldShapeDescription {
+    /// Converts this description into a world shape. Returns None for a box world
+    ///
+    /// # Safety
+    ///
+    /// `points` must point to an array of at least `points_count` elements if the shape is a
+    /// convex polyhedron
+    pub unsafe fn to_world_shape(&self) -> Option<WorldShape> {
+        let rotation = rotation_from_description(self.rotation);
+        let kind = match self.shape_type {
+            1 => WorldShapeKind::Sphere { center: self.position, radius: self.radius },
+            2 => WorldShapeKind::Cylinder { position: self.position, rotation, half_height: self.half_height, radius: self.radius },
+            3 => WorldShapeKind::Frustum {
+                position: self.position,
+                rotation,
+                vertical_fov: self.vertical_fov,
+                aspect_ratio: self.aspect_ratio,
+                near: self.near,
+                far: self.far,
+            },
+            4 => WorldShapeKind::ConvexPolyhedron {
+                points: from_raw_parts(self.points, self.points_count.max(0) as usize)
+                    .iter()
+                    .map(|point| Point3::from(*point))
+                    .collect(),
+            },
+            _ => return None,
+        };
+        Some(WorldShape::new(kind))
+    }
+}
+
+impl ExclusionVolumeDescription {
+    /// Converts this description into an exclusion volume. Returns a human-readable problem if the
+    /// shape description can't be converted
+    ///
+    /// # Safety
+    ///
+    /// The shape description must satisfy #CollisionShapeDescription::to_collision_shape
+    /// requirements
+    pub unsafe fn to_exclusion_volume(&self) -> Result<ExclusionVolume, String> {
+        let shape = self.shape
+            .to_collision_shape()
+            .map_err(|problem| format!("shape: {}", problem))?
+            .unwrap_or(CollisionShape::Cuboid { half_extents: self.shape.half_extents });
+        Ok(ExclusionVolume {
+            shape,
+            position: self.position,
+            rotation: rotation_from_description(self.rotation),
+            active_from: if self.use_active_from { Some(self.active_from) } else { None },
+            active_until: if self.use_active_until { Some(self.active_until) } else { None },
+        })
+    }
+}
+
+impl PlayerLaneDescription {
+    /// Converts this description into a player lane. Returns a human-readable problem if the shape
+    /// description can't be converted
+    ///
+    /// # Safety
+    ///
+    /// The shape description must satisfy #CollisionShapeDescription::to_collision_shape
+    /// requirements
+    pub unsafe fn to_player_lane(&self) -> Result<PlayerLane, String> {
+        let shape = self.shape
+            .to_collision_shape()
+            .map_err(|problem| format!("shape: {}", problem))?
+            .unwrap_or(CollisionShape::Cuboid { half_extents: self.shape.half_extents });
+        Ok(PlayerLane {
+            shape,
+            position: self.position,
+            rotation: rotation_from_description(self.rotation),
+            velocity: self.velocity,
+            collision_mask: self.collision_mask,
+        })
+    }
+}
+
+fn rotation_from_description(rotation: Vector4<f32>) -> UnitQuaternion<f32> {
+    if rotation == Vector4::zeros() {
+        UnitQuaternion::identity()
+    } else {
+        UnitQuaternion::from_quaternion(Quaternion::from(rotation))
     }
 }
 
@@ -127,7 +537,32 @@ impl From<CollidableEntity> for EntityDescription {
             spawn_time: entity.spawn_time,
             movement_start_parameter: entity.movement_start_parameter,
             movement_end_parameter: entity.movement_end_parameter,
-            movement: entity.prefab.movement.into()
+            movement: entity.prefab.movement.clone().into(),
+            origin_position: entity.prefab.position + entity.spawn_feature_shift,
+            origin_rotation: entity.prefab.rotation.coords.clone(),
+            scale: entity.spawn_scale,
+            mirrored: entity.spawn_mirrored,
         }
     }
+}
+
+impl EntityDescription {
+    /// Checks if the described entity is already spawned and didn't yet leave the visible world.
+    /// Agrees with #CollidableEntity::is_alive_at
+    pub fn is_alive_at(&self, time: f32) -> bool {
+        is_entity_alive_at(self.spawn_time, self.movement_start_parameter, self.movement_end_parameter, time)
+    }
+}
+
+impl Into<BiArcCurveMotion> for EntityDescription {
+    fn into(self) -> BiArcCurveMotion {
+        let movement: Movement = self.movement.into();
+        movement.motion(
+            self.spawn_time - self.movement_start_parameter,
+            Isometry3::from_parts(
+                Translation3::from(self.origin_position),
+                UnitQuaternion::from_quaternion(Quaternion::from(self.origin_rotation)),
+            ),
+        )
+    }
 }
\ No newline at end of file
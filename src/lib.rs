@@ -3,8 +3,13 @@ mod interop;
 #[macro_use]
 extern crate approx;
 
-pub use generator::generate;
-pub use generator::{VisibleWorld, Feature, Prefab, CollidableEntity, Movement, BiArcCurveMotion};
+pub use generator::{generate, generate_with_constraints, generate_report};
+pub use generator::entities_at_time;
+pub use generator::validate;
+pub use generator::{VisibleWorld, Feature, FeatureVariant, FormationTransform, SpawnPeriodDistribution, Curve, CurveKeyframe, CurveInterpolation, Prefab, CollidableEntity, Movement, BiArcCurveMotion, CollisionShape, CompoundShapePart, GeneratorConfig, ExclusionVolume, PlayerLane, WorldShape, WorldShapeKind, SpawnConstraint, SpawnRejection, GenerationReport, RejectedSpawn, ShiftSampling};
 pub use interop::bind_generate;
 pub use interop::bind_deallocate_vec;
-pub use interop::{VisibleWorldDescription, FeatureDescription, PrefabDescription, MovementDescription};
+pub use interop::bind_entity_transform_at_time;
+pub use interop::bind_entities_at_time;
+pub use interop::{bind_validate, bind_deallocate_string};
+pub use interop::{VisibleWorldDescription, FeatureDescription, FeatureVariantDescription, CurveDescription, PrefabDescription, CollisionShapeDescription, MovementDescription, EntityDescription, TransformDescription, EntityTransformDescription, GeneratorConfigDescription, ExclusionVolumeDescription, PlayerLaneDescription, WorldShapeDescription};
@@ -2,9 +2,10 @@
 mod tests {
     extern crate arc_level_generator;
 
-    use self::arc_level_generator::{PrefabDescription, FeatureDescription, VisibleWorldDescription, MovementDescription};
-    use nalgebra::{Vector3, Vector2, UnitQuaternion, Unit};
+    use self::arc_level_generator::{PrefabDescription, WorldShapeDescription, GeneratorConfigDescription, CollisionShapeDescription, FeatureDescription, CurveDescription, VisibleWorldDescription, MovementDescription, EntityTransformDescription};
+    use nalgebra::{Vector3, Vector2, UnitQuaternion, Unit, Quaternion};
     use std::slice::from_raw_parts;
+    use std::ffi::CStr;
 
     #[test]
     fn test_bind_generate() {
@@ -25,6 +26,11 @@ mod tests {
                 departure_rotation_strength: 0.0,
             },
             half_extents: Vector3::new(0.5, 0.5, 0.5),
+            collision_shape: CollisionShapeDescription::default(),
+            use_clearance: false,
+            clearance: 0.,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
         };
         let description1 = PrefabDescription {
             prefab_id: 2,
@@ -43,6 +49,11 @@ mod tests {
                 departure_rotation_strength: 0.0,
             },
             half_extents: Vector3::new(0.5, 0.5, 0.5),
+            collision_shape: CollisionShapeDescription::default(),
+            use_clearance: false,
+            clearance: 0.,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
         };
         let description2 = PrefabDescription {
             prefab_id: 3,
@@ -61,6 +72,11 @@ mod tests {
                 departure_rotation_strength: 0.0,
             },
             half_extents: Vector3::new(0.5, 0.5, 0.5),
+            collision_shape: CollisionShapeDescription::default(),
+            use_clearance: false,
+            clearance: 0.,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
         };
         let description3 = PrefabDescription {
             prefab_id: 4,
@@ -79,6 +95,11 @@ mod tests {
                 departure_rotation_strength: 0.0,
             },
             half_extents: Vector3::new(0.5, 0.5, 0.5),
+            collision_shape: CollisionShapeDescription::default(),
+            use_clearance: false,
+            clearance: 0.,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
         };
         let description4 = PrefabDescription {
             prefab_id: 5,
@@ -97,6 +118,11 @@ mod tests {
                 departure_rotation_strength: 0.0,
             },
             half_extents: Vector3::new(0.5, 0.5, 0.5),
+            collision_shape: CollisionShapeDescription::default(),
+            use_clearance: false,
+            clearance: 0.,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
         };
         let features = [
             FeatureDescription {
@@ -106,13 +132,30 @@ mod tests {
                 translate_y: true,
                 translate_y_using_bounds: false,
                 translate_y_bounds: Vector2::new(1., 1.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
                 prefabs: [description, description1, description2].as_ptr(),
                 prefabs_count: 3,
                 spawn_period: 1.0,
                 is_spawn_period_strict: false,
+                spawn_period_distribution: 0,
+                spawn_period_min: 0.,
+                spawn_period_max: 0.,
+                spawn_period_standard_deviation: 0.,
+                spawn_period_jitter: 0.,
+                spawn_period_curve: CurveDescription::default(),
+                speed_multiplier_curve: CurveDescription::default(),
                 spawn_count: 10,
                 trigger_time: 10.0,
                 priority: 0,
+                variants: std::ptr::null(),
+                variants_count: 0,
             },
             FeatureDescription {
                 translate_x: true,
@@ -121,13 +164,30 @@ mod tests {
                 translate_y: true,
                 translate_y_using_bounds: false,
                 translate_y_bounds: Vector2::new(1., 1.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
                 prefabs: [description3].as_ptr(),
                 prefabs_count: 1,
                 spawn_period: 1.0,
                 is_spawn_period_strict: false,
+                spawn_period_distribution: 0,
+                spawn_period_min: 0.,
+                spawn_period_max: 0.,
+                spawn_period_standard_deviation: 0.,
+                spawn_period_jitter: 0.,
+                spawn_period_curve: CurveDescription::default(),
+                speed_multiplier_curve: CurveDescription::default(),
                 spawn_count: 10,
                 trigger_time: 20.0,
                 priority: 0,
+                variants: std::ptr::null(),
+                variants_count: 0,
             },
             FeatureDescription {
                 translate_x: true,
@@ -136,24 +196,48 @@ mod tests {
                 translate_y: true,
                 translate_y_using_bounds: false,
                 translate_y_bounds: Vector2::new(1., 1.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
                 prefabs: [description4].as_ptr(),
                 prefabs_count: 1,
                 spawn_period: 1.0,
                 is_spawn_period_strict: false,
+                spawn_period_distribution: 0,
+                spawn_period_min: 0.,
+                spawn_period_max: 0.,
+                spawn_period_standard_deviation: 0.,
+                spawn_period_jitter: 0.,
+                spawn_period_curve: CurveDescription::default(),
+                speed_multiplier_curve: CurveDescription::default(),
                 spawn_count: 10,
                 trigger_time: 30.0,
                 priority: 0,
+                variants: std::ptr::null(),
+                variants_count: 0,
             },
         ];
         let world = VisibleWorldDescription {
             position: Vector3::new(0., 0., 0.),
             half_extents: Vector3::new(10., 10., 10.),
+            flow_direction: Vector3::new(0., 0., -1.),
+            exclusion_volumes: std::ptr::null(),
+            exclusion_volumes_count: 0,
+            player_lanes: std::ptr::null(),
+            player_lanes_count: 0,
+            shape: WorldShapeDescription::default(),
         };
         unsafe {
             let generated_entities_description = arc_level_generator::bind_generate(
                 features.as_ptr(),
                 features.len() as i32,
                 world,
+                GeneratorConfigDescription::default(),
             );
             let entities = from_raw_parts(generated_entities_description.pointer, generated_entities_description.length as usize);
 
@@ -183,6 +267,11 @@ mod tests {
                 departure_rotation_strength: 0.0,
             },
             half_extents: Vector3::new(0.5, 0.5, 0.5),
+            collision_shape: CollisionShapeDescription::default(),
+            use_clearance: false,
+            clearance: 0.,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
         };
         let description1 = PrefabDescription {
             prefab_id: 2,
@@ -201,6 +290,11 @@ mod tests {
                 departure_rotation_strength: 0.0,
             },
             half_extents: Vector3::new(0.5, 0.5, 0.5),
+            collision_shape: CollisionShapeDescription::default(),
+            use_clearance: false,
+            clearance: 0.,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
         };
         let features = [
             FeatureDescription {
@@ -210,13 +304,30 @@ mod tests {
                 translate_y: false,
                 translate_y_using_bounds: false,
                 translate_y_bounds: Vector2::new(1., 1.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
                 prefabs: [description].as_ptr(),
                 prefabs_count: 1,
                 spawn_period: 0.1,
                 is_spawn_period_strict: false,
+                spawn_period_distribution: 0,
+                spawn_period_min: 0.,
+                spawn_period_max: 0.,
+                spawn_period_standard_deviation: 0.,
+                spawn_period_jitter: 0.,
+                spawn_period_curve: CurveDescription::default(),
+                speed_multiplier_curve: CurveDescription::default(),
                 spawn_count: 30,
                 trigger_time: 10.0,
                 priority: 0,
+                variants: std::ptr::null(),
+                variants_count: 0,
             },
             FeatureDescription {
                 translate_x: false,
@@ -225,24 +336,48 @@ mod tests {
                 translate_y: false,
                 translate_y_using_bounds: false,
                 translate_y_bounds: Vector2::new(1., 1.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
                 prefabs: [description1].as_ptr(),
                 prefabs_count: 1,
                 spawn_period: 0.1,
                 is_spawn_period_strict: false,
+                spawn_period_distribution: 0,
+                spawn_period_min: 0.,
+                spawn_period_max: 0.,
+                spawn_period_standard_deviation: 0.,
+                spawn_period_jitter: 0.,
+                spawn_period_curve: CurveDescription::default(),
+                speed_multiplier_curve: CurveDescription::default(),
                 spawn_count: 30,
                 trigger_time: 10.0,
                 priority: 0,
+                variants: std::ptr::null(),
+                variants_count: 0,
             },
         ];
         let world = VisibleWorldDescription {
             position: Vector3::new(0., 0., 0.),
             half_extents: Vector3::new(9., 9., 30.),
+            flow_direction: Vector3::new(0., 0., -1.),
+            exclusion_volumes: std::ptr::null(),
+            exclusion_volumes_count: 0,
+            player_lanes: std::ptr::null(),
+            player_lanes_count: 0,
+            shape: WorldShapeDescription::default(),
         };
         unsafe {
             let generated_entities_description = arc_level_generator::bind_generate(
                 features.as_ptr(),
                 features.len() as i32,
                 world,
+                GeneratorConfigDescription::default(),
             );
             let entities = from_raw_parts(generated_entities_description.pointer, generated_entities_description.length as usize);
 
@@ -272,6 +407,11 @@ mod tests {
                 departure_rotation_strength: 0.0,
             },
             half_extents: Vector3::new(4.0, 4.0, 4.0),
+            collision_shape: CollisionShapeDescription::default(),
+            use_clearance: false,
+            clearance: 0.,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
         };
         let description1 = PrefabDescription {
             prefab_id: 2,
@@ -290,6 +430,11 @@ mod tests {
                 departure_rotation_strength: 0.0,
             },
             half_extents: Vector3::new(4.0, 4.0, 4.0),
+            collision_shape: CollisionShapeDescription::default(),
+            use_clearance: false,
+            clearance: 0.,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
         };
         let features = [
             FeatureDescription {
@@ -299,13 +444,30 @@ mod tests {
                 translate_y: false,
                 translate_y_using_bounds: false,
                 translate_y_bounds: Vector2::new(1., 1.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
                 prefabs: [description].as_ptr(),
                 prefabs_count: 1,
                 spawn_period: 0.1,
                 is_spawn_period_strict: false,
+                spawn_period_distribution: 0,
+                spawn_period_min: 0.,
+                spawn_period_max: 0.,
+                spawn_period_standard_deviation: 0.,
+                spawn_period_jitter: 0.,
+                spawn_period_curve: CurveDescription::default(),
+                speed_multiplier_curve: CurveDescription::default(),
                 spawn_count: 1,
                 trigger_time: 10.0,
                 priority: 0,
+                variants: std::ptr::null(),
+                variants_count: 0,
             },
             FeatureDescription {
                 translate_x: false,
@@ -314,24 +476,48 @@ mod tests {
                 translate_y: false,
                 translate_y_using_bounds: false,
                 translate_y_bounds: Vector2::new(1., 1.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
                 prefabs: [description1].as_ptr(),
                 prefabs_count: 1,
                 spawn_period: 0.1,
                 is_spawn_period_strict: false,
+                spawn_period_distribution: 0,
+                spawn_period_min: 0.,
+                spawn_period_max: 0.,
+                spawn_period_standard_deviation: 0.,
+                spawn_period_jitter: 0.,
+                spawn_period_curve: CurveDescription::default(),
+                speed_multiplier_curve: CurveDescription::default(),
                 spawn_count: 1,
                 trigger_time: 10.0,
                 priority: 0,
+                variants: std::ptr::null(),
+                variants_count: 0,
             },
         ];
         let world = VisibleWorldDescription {
             position: Vector3::new(0., 0., 0.),
             half_extents: Vector3::new(9., 9., 30.),
+            flow_direction: Vector3::new(0., 0., -1.),
+            exclusion_volumes: std::ptr::null(),
+            exclusion_volumes_count: 0,
+            player_lanes: std::ptr::null(),
+            player_lanes_count: 0,
+            shape: WorldShapeDescription::default(),
         };
         unsafe {
             let generated_entities_description = arc_level_generator::bind_generate(
                 features.as_ptr(),
                 features.len() as i32,
                 world,
+                GeneratorConfigDescription::default(),
             );
             let entities = from_raw_parts(generated_entities_description.pointer, generated_entities_description.length as usize);
 
@@ -362,6 +548,11 @@ mod tests {
                 departure_rotation_strength: 0.0,
             },
             half_extents: Vector3::new(0.5, 0.5, 0.5),
+            collision_shape: CollisionShapeDescription::default(),
+            use_clearance: false,
+            clearance: 0.,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
         };
         let features = [
             FeatureDescription {
@@ -371,24 +562,48 @@ mod tests {
                 translate_y: false,
                 translate_y_using_bounds: false,
                 translate_y_bounds: Vector2::new(-4.5, 4.5),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
                 prefabs: [description].as_ptr(),
                 prefabs_count: 1,
                 spawn_period: 0.0001,
                 is_spawn_period_strict: false,
+                spawn_period_distribution: 0,
+                spawn_period_min: 0.,
+                spawn_period_max: 0.,
+                spawn_period_standard_deviation: 0.,
+                spawn_period_jitter: 0.,
+                spawn_period_curve: CurveDescription::default(),
+                speed_multiplier_curve: CurveDescription::default(),
                 spawn_count: 1,
                 trigger_time: 0.0,
                 priority: 8,
+                variants: std::ptr::null(),
+                variants_count: 0,
             },
         ];
         let world = VisibleWorldDescription {
             position: Vector3::new(0., 0., 175.),
             half_extents: Vector3::new(100., 100., 400.),
+            flow_direction: Vector3::new(0., 0., -1.),
+            exclusion_volumes: std::ptr::null(),
+            exclusion_volumes_count: 0,
+            player_lanes: std::ptr::null(),
+            player_lanes_count: 0,
+            shape: WorldShapeDescription::default(),
         };
         unsafe {
             let generated_entities_description = arc_level_generator::bind_generate(
                 features.as_ptr(),
                 features.len() as i32,
                 world,
+                GeneratorConfigDescription::default(),
             );
             let entities = from_raw_parts(generated_entities_description.pointer, generated_entities_description.length as usize);
 
@@ -418,6 +633,11 @@ mod tests {
                 departure_rotation_strength: 0.0,
             },
             half_extents: Vector3::new(12.43841, 8.28537, 30.5627),
+            collision_shape: CollisionShapeDescription::default(),
+            use_clearance: false,
+            clearance: 0.,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
         };
         let features = [
             FeatureDescription {
@@ -427,24 +647,48 @@ mod tests {
                 translate_y: false,
                 translate_y_using_bounds: false,
                 translate_y_bounds: Vector2::new(-4.5, 4.5),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
                 prefabs: [description].as_ptr(),
                 prefabs_count: 1,
                 spawn_period: 0.1,
                 is_spawn_period_strict: false,
+                spawn_period_distribution: 0,
+                spawn_period_min: 0.,
+                spawn_period_max: 0.,
+                spawn_period_standard_deviation: 0.,
+                spawn_period_jitter: 0.,
+                spawn_period_curve: CurveDescription::default(),
+                speed_multiplier_curve: CurveDescription::default(),
                 spawn_count: 10,
                 trigger_time: 0.0,
                 priority: 50,
+                variants: std::ptr::null(),
+                variants_count: 0,
             },
         ];
         let world = VisibleWorldDescription {
             position: Vector3::new(0., 0., 175.),
             half_extents: Vector3::new(100., 100., 400.),
+            flow_direction: Vector3::new(0., 0., -1.),
+            exclusion_volumes: std::ptr::null(),
+            exclusion_volumes_count: 0,
+            player_lanes: std::ptr::null(),
+            player_lanes_count: 0,
+            shape: WorldShapeDescription::default(),
         };
         unsafe {
             let generated_entities_description = arc_level_generator::bind_generate(
                 features.as_ptr(),
                 features.len() as i32,
                 world,
+                GeneratorConfigDescription::default(),
             );
             let entities = from_raw_parts(generated_entities_description.pointer, generated_entities_description.length as usize);
 
@@ -474,6 +718,11 @@ mod tests {
                 departure_rotation_strength: 0.0,
             },
             half_extents: Vector3::new(12.43841, 8.28537, 30.5627),
+            collision_shape: CollisionShapeDescription::default(),
+            use_clearance: false,
+            clearance: 0.,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
         };
         let features = [
             FeatureDescription {
@@ -483,24 +732,48 @@ mod tests {
                 translate_y: false,
                 translate_y_using_bounds: false,
                 translate_y_bounds: Vector2::new(-4.5, 4.5),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
                 prefabs: [description].as_ptr(),
                 prefabs_count: 1,
                 spawn_period: 0.1,
                 is_spawn_period_strict: false,
+                spawn_period_distribution: 0,
+                spawn_period_min: 0.,
+                spawn_period_max: 0.,
+                spawn_period_standard_deviation: 0.,
+                spawn_period_jitter: 0.,
+                spawn_period_curve: CurveDescription::default(),
+                speed_multiplier_curve: CurveDescription::default(),
                 spawn_count: 10,
                 trigger_time: 0.0,
                 priority: 50,
+                variants: std::ptr::null(),
+                variants_count: 0,
             },
         ];
         let world = VisibleWorldDescription {
             position: Vector3::new(0., 0., 175.),
             half_extents: Vector3::new(100., 100., 400.),
+            flow_direction: Vector3::new(0., 0., -1.),
+            exclusion_volumes: std::ptr::null(),
+            exclusion_volumes_count: 0,
+            player_lanes: std::ptr::null(),
+            player_lanes_count: 0,
+            shape: WorldShapeDescription::default(),
         };
         unsafe {
             let generated_entities_description = arc_level_generator::bind_generate(
                 features.as_ptr(),
                 features.len() as i32,
                 world,
+                GeneratorConfigDescription::default(),
             );
             let entities = from_raw_parts(generated_entities_description.pointer, generated_entities_description.length as usize);
 
@@ -510,4 +783,464 @@ mod tests {
             arc_level_generator::bind_deallocate_vec(generated_entities_description)
         }
     }
+
+    #[test]
+    fn test_bind_entity_transform_at_time() {
+        let description = PrefabDescription {
+            prefab_id: 0,
+            position: Vector3::new(0., 0., 0.),
+            rotation: UnitQuaternion::identity().coords,
+            movement: MovementDescription {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Vector3::new(1., 0., 0.),
+                approach_arc_angle: -45.0_f32.to_radians(),
+                approach_arc_center_distance: 2.0,
+                approach_arc_radius: 2.0,
+                approach_rotation_strength: 1.0,
+                departure_arc_angle: 30.0_f32.to_radians(),
+                departure_arc_center_distance: 2.0,
+                departure_arc_radius: 2.0,
+                departure_rotation_strength: 1.0,
+            },
+            half_extents: Vector3::new(0.5, 0.5, 0.5),
+            collision_shape: CollisionShapeDescription::default(),
+            use_clearance: false,
+            clearance: 0.,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+        };
+        let features = [
+            FeatureDescription {
+                translate_x: true,
+                translate_x_using_bounds: false,
+                translate_x_bounds: Vector2::new(0., 0.),
+                translate_y: false,
+                translate_y_using_bounds: false,
+                translate_y_bounds: Vector2::new(0., 0.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
+                prefabs: [description].as_ptr(),
+                prefabs_count: 1,
+                spawn_period: 1.0,
+                is_spawn_period_strict: false,
+                spawn_period_distribution: 0,
+                spawn_period_min: 0.,
+                spawn_period_max: 0.,
+                spawn_period_standard_deviation: 0.,
+                spawn_period_jitter: 0.,
+                spawn_period_curve: CurveDescription::default(),
+                speed_multiplier_curve: CurveDescription::default(),
+                spawn_count: 5,
+                trigger_time: 0.0,
+                priority: 0,
+                variants: std::ptr::null(),
+                variants_count: 0,
+            },
+        ];
+        let world = VisibleWorldDescription {
+            position: Vector3::new(0., 0., 0.),
+            half_extents: Vector3::new(10., 10., 10.),
+            flow_direction: Vector3::new(0., 0., -1.),
+            exclusion_volumes: std::ptr::null(),
+            exclusion_volumes_count: 0,
+            player_lanes: std::ptr::null(),
+            player_lanes_count: 0,
+            shape: WorldShapeDescription::default(),
+        };
+        unsafe {
+            let generated_entities_description = arc_level_generator::bind_generate(
+                features.as_ptr(),
+                features.len() as i32,
+                world,
+                GeneratorConfigDescription::default(),
+            );
+            let entities = from_raw_parts(generated_entities_description.pointer, generated_entities_description.length as usize);
+            assert_eq!(entities.len(), 5);
+
+            for entity in entities {
+                let spawn_transform = arc_level_generator::bind_entity_transform_at_time(entity.clone(), entity.spawn_time);
+                assert!((spawn_transform.position - entity.spawn_position).magnitude() < 0.001);
+                assert!(UnitQuaternion::from_quaternion(Quaternion::from(spawn_transform.rotation))
+                    .angle_to(&UnitQuaternion::from_quaternion(Quaternion::from(entity.spawn_rotation))) < 0.001);
+
+                let origin_time = entity.spawn_time - entity.movement_start_parameter;
+                let origin_transform = arc_level_generator::bind_entity_transform_at_time(entity.clone(), origin_time);
+                assert!((origin_transform.position - entity.origin_position).magnitude() < 0.001);
+            }
+            arc_level_generator::bind_deallocate_vec(generated_entities_description)
+        }
+    }
+
+    #[test]
+    fn test_bind_entities_at_time() {
+        let description = PrefabDescription {
+            prefab_id: 7,
+            position: Vector3::new(0., 0., 0.),
+            rotation: UnitQuaternion::identity().coords,
+            movement: MovementDescription {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Vector3::new(1., 0., 0.),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.0,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+            half_extents: Vector3::new(0.5, 0.5, 0.5),
+            collision_shape: CollisionShapeDescription::default(),
+            use_clearance: false,
+            clearance: 0.,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+        };
+        let features = [
+            FeatureDescription {
+                translate_x: true,
+                translate_x_using_bounds: false,
+                translate_x_bounds: Vector2::new(0., 0.),
+                translate_y: true,
+                translate_y_using_bounds: false,
+                translate_y_bounds: Vector2::new(0., 0.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
+                prefabs: [description].as_ptr(),
+                prefabs_count: 1,
+                spawn_period: 0.5,
+                is_spawn_period_strict: false,
+                spawn_period_distribution: 0,
+                spawn_period_min: 0.,
+                spawn_period_max: 0.,
+                spawn_period_standard_deviation: 0.,
+                spawn_period_jitter: 0.,
+                spawn_period_curve: CurveDescription::default(),
+                speed_multiplier_curve: CurveDescription::default(),
+                spawn_count: 20,
+                trigger_time: 0.0,
+                priority: 0,
+                variants: std::ptr::null(),
+                variants_count: 0,
+            },
+        ];
+        let world = VisibleWorldDescription {
+            position: Vector3::new(0., 0., 0.),
+            half_extents: Vector3::new(10., 10., 10.),
+            flow_direction: Vector3::new(0., 0., -1.),
+            exclusion_volumes: std::ptr::null(),
+            exclusion_volumes_count: 0,
+            player_lanes: std::ptr::null(),
+            player_lanes_count: 0,
+            shape: WorldShapeDescription::default(),
+        };
+        unsafe {
+            let generated_entities_description = arc_level_generator::bind_generate(
+                features.as_ptr(),
+                features.len() as i32,
+                world,
+                GeneratorConfigDescription::default(),
+            );
+            let entities = from_raw_parts(generated_entities_description.pointer, generated_entities_description.length as usize);
+            let time = entities[entities.len() / 2].spawn_time + 1.;
+            let expected_count = entities
+                .iter()
+                .filter(|entity| time >= entity.spawn_time && time < entity.spawn_time - entity.movement_start_parameter + entity.movement_end_parameter)
+                .count();
+
+            let mut transforms = vec![EntityTransformDescription {
+                entity_index: -1,
+                prefab_id: -1,
+                position: nalgebra::zero(),
+                rotation: nalgebra::zero(),
+            }; entities.len()];
+            let count = arc_level_generator::bind_entities_at_time(
+                generated_entities_description.pointer,
+                generated_entities_description.length,
+                time,
+                transforms.as_mut_ptr(),
+                transforms.len() as i32,
+            );
+            assert_eq!(count as usize, expected_count);
+            for transform in &transforms[..count as usize] {
+                let entity = &entities[transform.entity_index as usize];
+                assert_eq!(transform.prefab_id, 7);
+                let expected = arc_level_generator::bind_entity_transform_at_time(entity.clone(), time);
+                assert_eq!(transform.position, expected.position);
+            }
+            // Counting without a buffer and passing no entities are both safe
+            let counted = arc_level_generator::bind_entities_at_time(
+                generated_entities_description.pointer,
+                generated_entities_description.length,
+                time,
+                std::ptr::null_mut(),
+                0,
+            );
+            assert_eq!(counted, count);
+            assert_eq!(arc_level_generator::bind_entities_at_time(std::ptr::null(), -1, time, transforms.as_mut_ptr(), transforms.len() as i32), 0);
+            arc_level_generator::bind_deallocate_vec(generated_entities_description)
+        }
+    }
+
+    #[test]
+    fn test_bind_validate() {
+        let description = PrefabDescription {
+            prefab_id: 0,
+            position: Vector3::new(0., 0., 0.),
+            rotation: UnitQuaternion::identity().coords,
+            movement: MovementDescription {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Vector3::new(1., 0., 0.),
+                approach_arc_angle: 90.0_f32.to_radians(),
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.0,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: -1.0,
+                departure_rotation_strength: 0.0,
+            },
+            half_extents: Vector3::new(0.5, 0.5, 0.5),
+            collision_shape: CollisionShapeDescription::default(),
+            use_clearance: false,
+            clearance: 0.,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+        };
+        let features = [
+            FeatureDescription {
+                translate_x: false,
+                translate_x_using_bounds: false,
+                translate_x_bounds: Vector2::new(0., 0.),
+                translate_y: false,
+                translate_y_using_bounds: false,
+                translate_y_bounds: Vector2::new(0., 0.),
+                translate_z: false,
+                translate_z_using_bounds: false,
+                translate_z_bounds: Vector2::new(0., 0.),
+                rotate: false,
+                rotate_bounds: Vector2::new(0., 0.),
+                scale: false,
+                scale_bounds: Vector2::new(1., 1.),
+                mirror: false,
+                prefabs: [description].as_ptr(),
+                prefabs_count: 1,
+                spawn_period: 1.0,
+                is_spawn_period_strict: false,
+                spawn_period_distribution: 0,
+                spawn_period_min: 0.,
+                spawn_period_max: 0.,
+                spawn_period_standard_deviation: 0.,
+                spawn_period_jitter: 0.,
+                spawn_period_curve: CurveDescription::default(),
+                speed_multiplier_curve: CurveDescription::default(),
+                spawn_count: 5,
+                trigger_time: 0.0,
+                priority: 0,
+                variants: std::ptr::null(),
+                variants_count: 0,
+            },
+        ];
+        let world = VisibleWorldDescription {
+            position: Vector3::new(0., 0., 0.),
+            half_extents: Vector3::new(10., 10., 10.),
+            flow_direction: Vector3::new(0., 0., -1.),
+            exclusion_volumes: std::ptr::null(),
+            exclusion_volumes_count: 0,
+            player_lanes: std::ptr::null(),
+            player_lanes_count: 0,
+            shape: WorldShapeDescription::default(),
+        };
+        unsafe {
+            let problems = arc_level_generator::bind_validate(
+                features.as_ptr(),
+                features.len() as i32,
+                world.clone(),
+                GeneratorConfigDescription::default(),
+            );
+            assert!(!problems.is_null());
+            let problems_string = CStr::from_ptr(problems).to_str().unwrap().to_string();
+            assert_eq!(problems_string.lines().count(), 2);
+            assert!(problems_string.lines().all(|problem| problem.starts_with("feature 0: prefab 0: movement: ")));
+            arc_level_generator::bind_deallocate_string(problems);
+
+            let generated_entities_description = arc_level_generator::bind_generate(
+                features.as_ptr(),
+                features.len() as i32,
+                world,
+                GeneratorConfigDescription::default(),
+            );
+            assert_eq!(generated_entities_description.length, 0);
+            arc_level_generator::bind_deallocate_vec(generated_entities_description)
+        }
+    }
+    #[test]
+    fn test_bind_collision_shape() {
+        let flat_points = [Vector3::new(0., 0., 0.), Vector3::new(1., 0., 0.)];
+        let parts = [
+            CollisionShapeDescription {
+                shape_type: 2,
+                radius: 0.5,
+                position: Vector3::new(0., 0., 1.),
+                ..CollisionShapeDescription::default()
+            },
+            CollisionShapeDescription {
+                shape_type: 5,
+                points: flat_points.as_ptr(),
+                points_count: flat_points.len() as i32,
+                ..CollisionShapeDescription::default()
+            },
+        ];
+        let mut description = PrefabDescription {
+            prefab_id: 0,
+            position: Vector3::new(0., 0., 0.),
+            rotation: UnitQuaternion::identity().coords,
+            movement: MovementDescription {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Vector3::new(1., 0., 0.),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.0,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+            half_extents: Vector3::new(0.5, 0.5, 1.5),
+            collision_shape: CollisionShapeDescription {
+                shape_type: 6,
+                parts: parts.as_ptr(),
+                parts_count: parts.len() as i32,
+                ..CollisionShapeDescription::default()
+            },
+            use_clearance: false,
+            clearance: 0.,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+        };
+        let world = VisibleWorldDescription {
+            position: Vector3::new(0., 0., 0.),
+            half_extents: Vector3::new(10., 10., 10.),
+            flow_direction: Vector3::new(0., 0., -1.),
+            exclusion_volumes: std::ptr::null(),
+            exclusion_volumes_count: 0,
+            player_lanes: std::ptr::null(),
+            player_lanes_count: 0,
+            shape: WorldShapeDescription::default(),
+        };
+        unsafe {
+            let prefabs = [description];
+            let features = [
+                FeatureDescription {
+                    translate_x: false,
+                    translate_x_using_bounds: false,
+                    translate_x_bounds: Vector2::new(0., 0.),
+                    translate_y: false,
+                    translate_y_using_bounds: false,
+                    translate_y_bounds: Vector2::new(0., 0.),
+                    translate_z: false,
+                    translate_z_using_bounds: false,
+                    translate_z_bounds: Vector2::new(0., 0.),
+                    rotate: false,
+                    rotate_bounds: Vector2::new(0., 0.),
+                    scale: false,
+                    scale_bounds: Vector2::new(1., 1.),
+                    mirror: false,
+                    prefabs: prefabs.as_ptr(),
+                    prefabs_count: 1,
+                    spawn_period: 2.0,
+                    is_spawn_period_strict: false,
+                    spawn_period_distribution: 0,
+                    spawn_period_min: 0.,
+                    spawn_period_max: 0.,
+                    spawn_period_standard_deviation: 0.,
+                    spawn_period_jitter: 0.,
+                    spawn_period_curve: CurveDescription::default(),
+                    speed_multiplier_curve: CurveDescription::default(),
+                    spawn_count: 5,
+                    trigger_time: 0.0,
+                    priority: 0,
+                    variants: std::ptr::null(),
+                    variants_count: 0,
+                },
+            ];
+            let problems = arc_level_generator::bind_validate(
+                features.as_ptr(),
+                features.len() as i32,
+                world.clone(),
+                GeneratorConfigDescription::default(),
+            );
+            assert!(!problems.is_null());
+            let problems_string = CStr::from_ptr(problems).to_str().unwrap().to_string();
+            assert_eq!(problems_string.lines().count(), 1);
+            assert!(problems_string.starts_with("feature 0: prefab 0: collision_shape: part 1: "));
+            arc_level_generator::bind_deallocate_string(problems);
+
+            description.collision_shape.parts_count = 1;
+            let prefabs = [description];
+            let features = [
+                FeatureDescription {
+                    prefabs: prefabs.as_ptr(),
+                    ..features[0].clone()
+                },
+            ];
+            let problems = arc_level_generator::bind_validate(
+                features.as_ptr(),
+                features.len() as i32,
+                world.clone(),
+                GeneratorConfigDescription::default(),
+            );
+            assert!(problems.is_null());
+            let generated_entities_description = arc_level_generator::bind_generate(
+                features.as_ptr(),
+                features.len() as i32,
+                world.clone(),
+                GeneratorConfigDescription::default(),
+            );
+            assert_eq!(generated_entities_description.length, 5);
+            arc_level_generator::bind_deallocate_vec(generated_entities_description);
+
+            // A compound part has no bounding box to fall back to, so it must describe a shape
+            let empty_parts = [CollisionShapeDescription::default()];
+            description.collision_shape.parts = empty_parts.as_ptr();
+            let prefabs = [description];
+            let features = [
+                FeatureDescription {
+                    prefabs: prefabs.as_ptr(),
+                    ..features[0].clone()
+                },
+            ];
+            let problems = arc_level_generator::bind_validate(
+                features.as_ptr(),
+                features.len() as i32,
+                world.clone(),
+                GeneratorConfigDescription::default(),
+            );
+            assert!(!problems.is_null());
+            let problems_string = CStr::from_ptr(problems).to_str().unwrap().to_string();
+            assert_eq!(problems_string, "feature 0: prefab 0: collision_shape: part 0: shape_type must be between 1 and 6, got 0");
+            arc_level_generator::bind_deallocate_string(problems);
+            let generated_entities_description = arc_level_generator::bind_generate(
+                features.as_ptr(),
+                features.len() as i32,
+                world,
+                GeneratorConfigDescription::default(),
+            );
+            assert_eq!(generated_entities_description.length, 0);
+            arc_level_generator::bind_deallocate_vec(generated_entities_description)
+        }
+    }
 }
@@ -3,8 +3,11 @@ mod tests {
     use nalgebra::{Isometry3, Point, Point3, Quaternion, Translation3, Unit, UnitQuaternion, Vector2, Vector3};
     use ncollide3d::bounding_volume::AABB;
     use ncollide3d::interpolation::RigidMotion;
+    use std::collections::VecDeque;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
 
-    use self::arc_level_generator::{BiArcCurveMotion, Feature, Movement, Prefab, VisibleWorld};
+    use self::arc_level_generator::{BiArcCurveMotion, Feature, FeatureVariant, Movement, Prefab, VisibleWorld, GeneratorConfig, ExclusionVolume, CollisionShape, PlayerLane, WorldShape, WorldShapeKind, CollidableEntity, SpawnRejection, ShiftSampling, SpawnPeriodDistribution, Curve, CurveKeyframe, CurveInterpolation};
 
     extern crate arc_level_generator;
 
@@ -15,6 +18,10 @@ mod tests {
             position: Vector3::new(0., 0., 0.),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -1.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -33,6 +40,10 @@ mod tests {
             position: Vector3::new(4., 2., 0.),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -1.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -51,6 +62,10 @@ mod tests {
             position: Vector3::new(0., 3., 0.),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -1.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -71,6 +86,14 @@ mod tests {
             translate_y: true,
             translate_y_using_bounds: false,
             translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             prefabs: vec![prefab0, prefab1, prefab2],
             spawn_count: 100,
             spawn_period: 1.0,
@@ -79,16 +102,26 @@ mod tests {
             priority: 0,
             missed_spawns: 0,
             last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
 
         let world = VisibleWorld {
             world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
         let generated_entities = arc_level_generator::generate(
             &world,
             &[feature0],
+            &GeneratorConfig::default(),
             &mut rand::thread_rng(),
-        );
+        ).unwrap();
         for (index, entity) in generated_entities.iter().enumerate() {
             println!("Generated entitity {}: {:?}", index, entity)
         }
@@ -101,6 +134,10 @@ mod tests {
             position: Vector3::new(0., 0., 0.),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -1.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -121,6 +158,14 @@ mod tests {
             translate_y: true,
             translate_y_using_bounds: false,
             translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             prefabs: vec![prefab0],
             spawn_count: 10,
             spawn_period: 1.0,
@@ -129,16 +174,26 @@ mod tests {
             priority: 0,
             missed_spawns: 0,
             last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
 
         let world = VisibleWorld {
             world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
         let generated_entities = arc_level_generator::generate(
             &world,
             &[feature0],
+            &GeneratorConfig::default(),
             &mut rand::thread_rng(),
-        );
+        ).unwrap();
         for (index, entity) in generated_entities.iter().enumerate() {
             println!("Generated entitity {}: {:?}", index, entity)
         }
@@ -151,6 +206,10 @@ mod tests {
             position: Vector3::new(0., 0., 0.),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(4.0, 4.0, 4.0)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., -1., 0.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -169,6 +228,10 @@ mod tests {
             position: Vector3::new(0., 0., 0.),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(4.0, 4.0, 4.0)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., -1., 0.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -189,6 +252,14 @@ mod tests {
             translate_y: true,
             translate_y_using_bounds: false,
             translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             prefabs: vec![prefab0],
             spawn_count: 1,
             spawn_period: 1.0,
@@ -197,6 +268,11 @@ mod tests {
             missed_spawns: 0,
             is_spawn_period_strict: false,
             last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
         let feature1 = Feature {
             translate_x: true,
@@ -205,6 +281,14 @@ mod tests {
             translate_y: true,
             translate_y_using_bounds: false,
             translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             prefabs: vec![prefab1],
             spawn_count: 1,
             spawn_period: 1.0,
@@ -213,16 +297,26 @@ mod tests {
             missed_spawns: 0,
             is_spawn_period_strict: false,
             last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
 
         let world = VisibleWorld {
             world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(4.5, 30., 4.5)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
         let generated_entities = arc_level_generator::generate(
             &world,
             &[feature0, feature1],
+            &GeneratorConfig::default(),
             &mut rand::thread_rng(),
-        );
+        ).unwrap();
         for (index, entity) in generated_entities.iter().enumerate() {
             println!("Generated entity {}: {:?}", index, entity)
         }
@@ -235,6 +329,10 @@ mod tests {
             position: Vector3::new(0., 0., 0.),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(5.0, 5.0, 5.0)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., -1., 0.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -255,6 +353,14 @@ mod tests {
             translate_y: false,
             translate_y_using_bounds: false,
             translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             prefabs: vec![prefab0],
             spawn_count: 10,
             spawn_period: 0.1,
@@ -263,16 +369,26 @@ mod tests {
             missed_spawns: 0,
             is_spawn_period_strict: false,
             last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
 
         let world = VisibleWorld {
             world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(6., 30., 6.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
         let generated_entities = arc_level_generator::generate(
             &world,
             &[feature0],
+            &GeneratorConfig::default(),
             &mut rand::thread_rng(),
-        );
+        ).unwrap();
         for (index, entity) in generated_entities.iter().enumerate() {
             println!("Generated entitity {}: {:?}", index, entity.spawn_time)
         }
@@ -285,6 +401,10 @@ mod tests {
             position: Vector3::new(0., 0., 0.),
             rotation: UnitQuaternion::from_euler_angles(0., std::f32::consts::FRAC_PI_2, 0.),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(8.0, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -1.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -305,6 +425,14 @@ mod tests {
             translate_y: false,
             translate_y_using_bounds: false,
             translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             prefabs: vec![prefab0],
             spawn_count: 10,
             spawn_period: 0.1,
@@ -313,16 +441,26 @@ mod tests {
             missed_spawns: 0,
             is_spawn_period_strict: false,
             last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
 
         let world = VisibleWorld {
             world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 50.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
         let generated_entities = arc_level_generator::generate(
             &world,
             &[feature0],
+            &GeneratorConfig::default(),
             &mut rand::thread_rng(),
-        );
+        ).unwrap();
         for (index, entity) in generated_entities.iter().enumerate() {
             println!("Generated entity {}: {:?}", index, entity)
         }
@@ -335,6 +473,10 @@ mod tests {
             position: Vector3::new(0., 0., 0.),
             rotation: UnitQuaternion::from_euler_angles(0., std::f32::consts::FRAC_PI_2, 0.),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(8.0, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -1.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -355,6 +497,14 @@ mod tests {
             translate_y: false,
             translate_y_using_bounds: false,
             translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             prefabs: vec![prefab0],
             spawn_count: 10,
             spawn_period: 5.0,
@@ -363,16 +513,26 @@ mod tests {
             missed_spawns: 0,
             is_spawn_period_strict: true,
             last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
 
         let world = VisibleWorld {
             world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 50.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
         let generated_entities = arc_level_generator::generate(
             &world,
             &[feature0],
+            &GeneratorConfig::default(),
             &mut rand::thread_rng(),
-        );
+        ).unwrap();
         for (index, entity) in generated_entities.iter().enumerate() {
             println!("Generated entity {}: {:?}", index, entity)
         }
@@ -385,6 +545,10 @@ mod tests {
             position: Vector3::new(0., -9.38, 0.),
             rotation: UnitQuaternion::from_euler_angles(-std::f32::consts::FRAC_PI_2, 0., std::f32::consts::PI),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(8.0, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -1.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -405,6 +569,14 @@ mod tests {
             translate_y: false,
             translate_y_using_bounds: false,
             translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             prefabs: vec![prefab0],
             spawn_count: 10,
             spawn_period: 5.0,
@@ -413,16 +585,26 @@ mod tests {
             missed_spawns: 0,
             is_spawn_period_strict: true,
             last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
 
         let world = VisibleWorld {
             world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 50.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
         let generated_entities = arc_level_generator::generate(
             &world,
             &[feature0],
+            &GeneratorConfig::default(),
             &mut rand::thread_rng(),
-        );
+        ).unwrap();
         for (index, entity) in generated_entities.iter().enumerate() {
             println!("Generated entity {}: {:?}", index, entity)
         }
@@ -435,6 +617,10 @@ mod tests {
             position: Vector3::new(0., -20., 0.),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(7.5, 7., 11.)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -1.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -455,6 +641,14 @@ mod tests {
             translate_y: true,
             translate_y_using_bounds: true,
             translate_y_bounds: Vector2::new(-15., 6.2),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             prefabs: vec![prefab0],
             spawn_count: 10,
             spawn_period: 1.0,
@@ -463,16 +657,26 @@ mod tests {
             missed_spawns: 0,
             is_spawn_period_strict: true,
             last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
 
         let world = VisibleWorld {
             world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(50., 50., 50.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
         let generated_entities = arc_level_generator::generate(
             &world,
             &[feature0],
+            &GeneratorConfig::default(),
             &mut rand::thread_rng(),
-        );
+        ).unwrap();
         for (index, entity) in generated_entities.iter().enumerate() {
             println!("Generated entity {}: {:?}", index, entity.spawn_position.xy())
         }
@@ -487,6 +691,10 @@ mod tests {
             position: Vector3::new(0., 0., 0.),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(22.05775, 13.11225, 9.712485)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             // bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(22.05775, 13.11225, 9.5)),
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -8.),
@@ -508,6 +716,14 @@ mod tests {
             translate_y: false,
             translate_y_using_bounds: false,
             translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             prefabs: vec![prefab0],
             spawn_count: 2,
             spawn_period: 1.0,
@@ -516,16 +732,26 @@ mod tests {
             priority: 0,
             missed_spawns: 0,
             last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
 
         let world = VisibleWorld {
             world_bounds: AABB::from_half_extents(Point3::new(0., 0., 175.), Vector3::new(50., 50., 200.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
         let generated_entities = arc_level_generator::generate(
             &world,
             &[feature0],
+            &GeneratorConfig::default(),
             &mut rand::thread_rng(),
-        );
+        ).unwrap();
         for (index, entity) in generated_entities.iter().enumerate() {
             println!("Generated entitity {}: {:?}", index, entity)
         }
@@ -538,6 +764,10 @@ mod tests {
             position: Vector3::new(0., 0., 0.),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -8.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -558,6 +788,14 @@ mod tests {
             translate_y: false,
             translate_y_using_bounds: false,
             translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             prefabs: vec![prefab0],
             spawn_count: 1,
             spawn_period: 1.0,
@@ -566,16 +804,26 @@ mod tests {
             priority: 0,
             missed_spawns: 0,
             last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
 
         let world = VisibleWorld {
             world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(200., 200., 200.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
         let generated_entities = arc_level_generator::generate(
             &world,
             &[feature0],
+            &GeneratorConfig::default(),
             &mut rand::thread_rng(),
-        );
+        ).unwrap();
         for (index, entity) in generated_entities.iter().enumerate() {
             dbg!(entity.spawn_rotation.euler_angles());
             println!("Generated entitity {}: {:?}", index, entity)
@@ -589,6 +837,10 @@ mod tests {
             position: Vector3::new(0., 0., 0.),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -1.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -609,6 +861,14 @@ mod tests {
             translate_y: false,
             translate_y_using_bounds: false,
             translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             prefabs: vec![prefab0],
             spawn_count: 10,
             spawn_period: 1.0,
@@ -617,12 +877,21 @@ mod tests {
             priority: 1000,
             missed_spawns: 0,
             last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
         let prefab1 = Prefab {
             prefab_id: 0,
             position: Vector3::new(0., 20., 0.),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -1.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -643,6 +912,14 @@ mod tests {
             translate_y: false,
             translate_y_using_bounds: false,
             translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             prefabs: vec![prefab1],
             spawn_count: 10,
             spawn_period: 1.0,
@@ -651,16 +928,26 @@ mod tests {
             priority: 0,
             missed_spawns: 0,
             last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
 
         let world = VisibleWorld {
             world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(100., 100., 100.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
         let generated_entities = arc_level_generator::generate(
             &world,
             &[feature0, feature1],
+            &GeneratorConfig::default(),
             &mut rand::thread_rng(),
-        );
+        ).unwrap();
         for (index, entity) in generated_entities.iter().enumerate() {
             println!("Generated entitity {}: {:?}", index, entity)
         }
@@ -673,6 +960,10 @@ mod tests {
             position: Vector3::new(0., 0., 0.),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -10.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(-1., 0., 0.)),
@@ -693,6 +984,14 @@ mod tests {
             translate_y: false,
             translate_y_using_bounds: false,
             translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             prefabs: vec![prefab0],
             spawn_count: 1,
             spawn_period: 0.01,
@@ -701,15 +1000,25 @@ mod tests {
             priority: 1000,
             missed_spawns: 0,
             last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
         let world = VisibleWorld {
             world_bounds: AABB::from_half_extents(Point3::new(0., 0., 175.), Vector3::new(250., 250., 200.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
         let generated_entities = arc_level_generator::generate(
             &world,
             &[feature0],
+            &GeneratorConfig::default(),
             &mut rand::thread_rng(),
-        );
+        ).unwrap();
         for (index, entity) in generated_entities.iter().enumerate() {
             println!("Generated entitity {}: {:?}", index, entity)
         }
@@ -722,6 +1031,10 @@ mod tests {
             position: Vector3::new(0., 0., 0.),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(14.72452, 14.45472, 14.72456)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -8.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -742,6 +1055,14 @@ mod tests {
             translate_y: false,
             translate_y_using_bounds: false,
             translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             prefabs: vec![prefab0],
             spawn_count: 1,
             spawn_period: 0.01,
@@ -750,12 +1071,21 @@ mod tests {
             priority: 1000,
             missed_spawns: 0,
             last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
         let prefab1 = Prefab {
             prefab_id: 1,
             position: Vector3::new(4.81, 5.14, 0.),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(12.438405, 8.28537, 32.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -8.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 1., 0.)),
@@ -776,6 +1106,14 @@ mod tests {
             translate_y: false,
             translate_y_using_bounds: false,
             translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             prefabs: vec![prefab1],
             spawn_count: 1,
             spawn_period: 0.01,
@@ -784,12 +1122,21 @@ mod tests {
             priority: 1000,
             missed_spawns: 0,
             last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
         let prefab2 = Prefab {
             prefab_id: 2,
             position: Vector3::new(-3.8, -9.13, 0.),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(12.438405, 8.28537, 32.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(2., 0., -8.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -810,6 +1157,14 @@ mod tests {
             translate_y: false,
             translate_y_using_bounds: false,
             translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             prefabs: vec![prefab2],
             spawn_count: 1,
             spawn_period: 0.01,
@@ -818,16 +1173,26 @@ mod tests {
             priority: 1000,
             missed_spawns: 0,
             last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
 
         let world = VisibleWorld {
             world_bounds: AABB::from_half_extents(Point3::new(0., 0., 575.), Vector3::new(250., 250., 600.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
         let generated_entities = arc_level_generator::generate(
             &world,
             &[feature0, feature1, feature2],
+            &GeneratorConfig::default(),
             &mut rand::thread_rng(),
-        );
+        ).unwrap();
         let time = 17.0;
         for (index, entity) in generated_entities.iter().enumerate() {
             // let movement = BiArcCurveMotion::new();
@@ -845,6 +1210,10 @@ mod tests {
             position: Vector3::new(0., 0., 0.),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(1., 1., 1.)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -8.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -865,6 +1234,14 @@ mod tests {
             translate_y: false,
             translate_y_using_bounds: false,
             translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             prefabs: vec![prefab0],
             spawn_count: 5,
             spawn_period: 1.0,
@@ -873,15 +1250,25 @@ mod tests {
             priority: 1000,
             missed_spawns: 0,
             last_spawn_attempt: f32::MIN,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
         let world = VisibleWorld {
             world_bounds: AABB::from_half_extents(Point3::new(0., 0., 575.), Vector3::new(250., 250., 600.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
         let generated_entities = arc_level_generator::generate(
             &world,
             &[feature0],
+            &GeneratorConfig::default(),
             &mut rand::thread_rng(),
-        );
+        ).unwrap();
         let time = 100.0;
         for (index, entity) in generated_entities.iter().enumerate() {
             println!("Generated entitity {}: {:?}", index, entity);
@@ -898,6 +1285,10 @@ mod tests {
             position: Vector3::new(0., 0., 0.),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(5., 5., 5.)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., 1.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -916,6 +1307,10 @@ mod tests {
             position: Vector3::new(0., 0., 1.),
             rotation: UnitQuaternion::identity(),
             bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(5., 5., 5.)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
             movement: Movement {
                 baseline_velocity: Vector3::new(0., 0., -10.),
                 arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
@@ -936,6 +1331,14 @@ mod tests {
             translate_y: false,
             translate_y_using_bounds: false,
             translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             prefabs: vec![prefab0],
             spawn_count: 1,
             spawn_period: 0.01,
@@ -944,6 +1347,11 @@ mod tests {
             priority: 1000,
             missed_spawns: 0,
             last_spawn_attempt: f32::MIN,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
         let feature1 = Feature {
             translate_x: false,
@@ -952,6 +1360,14 @@ mod tests {
             translate_y: false,
             translate_y_using_bounds: false,
             translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
             prefabs: vec![prefab1],
             spawn_count: 1,
             spawn_period: 0.01,
@@ -960,15 +1376,25 @@ mod tests {
             priority: 1000,
             missed_spawns: 0,
             last_spawn_attempt: f32::MIN,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
         };
         let world = VisibleWorld {
             world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(250., 250., 500.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
         };
         let generated_entities = arc_level_generator::generate(
             &world,
             &[feature0, feature1],
+            &GeneratorConfig::default(),
             &mut rand::thread_rng(),
-        );
+        ).unwrap();
         let time = 50.;
         for (index, entity) in generated_entities.iter().enumerate() {
             println!("Generated entitity {}: {:?}", index, entity);
@@ -1002,8 +1428,9 @@ mod tests {
         let generated_entities = arc_level_generator::generate(
             &world,
             &features.as_slice(),
+            &GeneratorConfig::default(),
             &mut rand::thread_rng(),
-        );
+        ).unwrap();
         let time = 50.;
         for (index, entity) in generated_entities.iter().enumerate() {
             println!("Generated entitity {}: {:?}", index, entity);
@@ -1026,4 +1453,1114 @@ mod tests {
             println!("Prefab: {}, Priority:{}, time: {}, Position in time: {}, Position2 in time: {}", entity.prefab.prefab_id, entity.priority, time, position_in_0.z, motion.position_at_time(movement_time).translation.vector);
         }
     }
-}
\ No newline at end of file
+    #[test]
+    fn test_generate_with_constraints() {
+        let prefab0 = Prefab {
+            prefab_id: 0,
+            position: Vector3::new(0., 0., 0.),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+        };
+        let heavy_prefab = Prefab {
+            prefab_id: 1,
+            ..prefab0.clone()
+        };
+        let feature = |prefab: Prefab| Feature {
+            translate_x: true,
+            translate_x_using_bounds: false,
+            translate_x_bounds: Vector2::new(0., 0.),
+            translate_y: true,
+            translate_y_using_bounds: false,
+            translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
+            prefabs: vec![prefab],
+            spawn_count: 30,
+            spawn_period: 0.2,
+            is_spawn_period_strict: false,
+            trigger_time: 0.0,
+            priority: 0,
+            missed_spawns: 0,
+            last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
+        };
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
+        };
+        let max_heavy_count = 3;
+        let is_heavy = |prefab: &Prefab| prefab.prefab_id == 1;
+        let limit_heavy = |feature: &Feature, _: &Vector3<f32>, _: f32, obstacles: &VecDeque<CollidableEntity>| {
+            !feature.prefabs.iter().any(is_heavy)
+                || obstacles.iter().filter(|obstacle| is_heavy(&obstacle.prefab)).count() < max_heavy_count
+        };
+        let generated_entities = arc_level_generator::generate_with_constraints(
+            &world,
+            &[feature(prefab0), feature(heavy_prefab)],
+            &GeneratorConfig::default(),
+            &[&limit_heavy],
+            &mut rand::thread_rng(),
+        ).unwrap();
+        assert_eq!(generated_entities.len(), 60);
+        let heavy_entities: Vec<&CollidableEntity> = generated_entities
+            .iter()
+            .filter(|entity| is_heavy(&entity.prefab))
+            .collect();
+        for entity in &heavy_entities {
+            let alive_count = heavy_entities
+                .iter()
+                .filter(|other| other.is_alive_at(entity.spawn_time))
+                .count();
+            assert!(alive_count <= max_heavy_count, "{} heavy entities at {}", alive_count, entity.spawn_time);
+        }
+    }
+    #[test]
+    fn test_broad_phase_does_not_change_results() {
+        let prefab0 = Prefab {
+            prefab_id: 0,
+            position: Vector3::new(0., 0., 0.),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+        };
+        let feature0 = Feature {
+            translate_x: true,
+            translate_x_using_bounds: false,
+            translate_x_bounds: Vector2::new(0., 0.),
+            translate_y: true,
+            translate_y_using_bounds: false,
+            translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
+            prefabs: vec![prefab0],
+            spawn_count: 60,
+            spawn_period: 0.1,
+            is_spawn_period_strict: false,
+            trigger_time: 0.0,
+            priority: 0,
+            missed_spawns: 0,
+            last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
+        };
+
+        let arcing_prefab = Prefab {
+            prefab_id: 1,
+            movement: Movement {
+                approach_arc_angle: 30.0_f32.to_radians(),
+                approach_arc_center_distance: 5.,
+                approach_arc_radius: 5.,
+                approach_rotation_strength: 1.,
+                ..prefab0.movement.clone()
+            },
+            ..prefab0.clone()
+        };
+        let feature1 = Feature {
+            prefabs: vec![arcing_prefab],
+            ..feature0.clone()
+        };
+        // A slower formation overtaken by the other features
+        let formation_prefab = |x: f32| Prefab {
+            prefab_id: 2,
+            position: Vector3::new(x, 0., 0.),
+            movement: Movement { baseline_velocity: Vector3::new(0., 0., -0.5), ..prefab0.movement.clone() },
+            ..prefab0.clone()
+        };
+        let feature2 = Feature {
+            prefabs: vec![formation_prefab(-1.), formation_prefab(1.)],
+            spawn_count: 10,
+            spawn_period: 0.5,
+            ..feature0.clone()
+        };
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(5., 5., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
+        };
+        for seed in 0..5 {
+            let generate = |broad_phase: bool, cache_swept_bounds: bool| arc_level_generator::generate(
+                &world,
+                &[feature0.clone(), feature1.clone(), feature2.clone()],
+                &GeneratorConfig { broad_phase, cache_swept_bounds, ..GeneratorConfig::default() },
+                &mut StdRng::seed_from_u64(seed),
+            ).unwrap();
+            let exact_results = generate(false, false);
+            assert_eq!(generate(true, false), exact_results, "results differ for seed {}", seed);
+            assert_eq!(generate(true, true), exact_results, "results with cached swept bounds differ for seed {}", seed);
+        }
+    }
+    #[test]
+    fn test_generate_keeps_clearance() {
+        let prefab0 = Prefab {
+            prefab_id: 0,
+            position: Vector3::new(0., 0., 0.),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+        };
+        let feature0 = Feature {
+            translate_x: false,
+            translate_x_using_bounds: false,
+            translate_x_bounds: Vector2::new(0., 0.),
+            translate_y: false,
+            translate_y_using_bounds: false,
+            translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
+            prefabs: vec![prefab0],
+            spawn_count: 10,
+            spawn_period: 0.1,
+            is_spawn_period_strict: false,
+            trigger_time: 0.0,
+            priority: 0,
+            missed_spawns: 0,
+            last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
+        };
+
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
+        };
+        let generated_entities = arc_level_generator::generate(
+            &world,
+            &[feature0],
+            &GeneratorConfig { clearance: 1.0, ..GeneratorConfig::default() },
+            &mut rand::thread_rng(),
+        ).unwrap();
+        assert_eq!(generated_entities.len(), 10);
+        let mut spawn_times: Vec<f32> = generated_entities.iter().map(|entity| entity.spawn_time).collect();
+        spawn_times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        for pair in spawn_times.windows(2) {
+            // Boxes one unit long moving at unit speed must be two units apart
+            assert!(pair[1] - pair[0] >= 1.95, "Entities spawned too close: {:?}", pair);
+        }
+    }
+    #[test]
+    fn test_generate_report() {
+        let prefab0 = Prefab {
+            prefab_id: 0,
+            position: Vector3::new(0., 0., 0.),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+        };
+        let feature0 = Feature {
+            translate_x: false,
+            translate_x_using_bounds: false,
+            translate_x_bounds: Vector2::new(0., 0.),
+            translate_y: false,
+            translate_y_using_bounds: false,
+            translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
+            prefabs: vec![prefab0],
+            spawn_count: 10,
+            spawn_period: 0.1,
+            is_spawn_period_strict: false,
+            trigger_time: 0.0,
+            priority: 0,
+            missed_spawns: 0,
+            last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
+        };
+
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
+        };
+        let report = arc_level_generator::generate_report(
+            &world,
+            &[feature0],
+            &GeneratorConfig::default(),
+            &[],
+            &mut rand::thread_rng(),
+        ).unwrap();
+        assert_eq!(report.entities.len(), 10);
+        for (index, entity) in report.entities.iter().enumerate() {
+            assert_eq!(entity.entity_id, index);
+        }
+        // Spawning more often than the entities can fit behind each other must be rejected
+        assert!(!report.rejected_spawns.is_empty());
+        for rejected_spawn in &report.rejected_spawns {
+            assert_eq!(rejected_spawn.prefab_ids, vec![0]);
+            match rejected_spawn.rejection {
+                SpawnRejection::ObstacleCollision { prefab_index, obstacle_entity_id, .. } => {
+                    assert_eq!(prefab_index, 0);
+                    assert!(obstacle_entity_id < report.entities.len());
+                }
+                ref rejection => panic!("Unexpected rejection {:?}", rejection),
+            }
+        }
+    }
+    #[test]
+    fn test_generate_with_alternative_shifts() {
+        let prefab0 = Prefab {
+            prefab_id: 0,
+            position: Vector3::new(0., 0., 0.),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+        };
+        let feature0 = Feature {
+            translate_x: true,
+            translate_x_using_bounds: false,
+            translate_x_bounds: Vector2::new(0., 0.),
+            translate_y: false,
+            translate_y_using_bounds: false,
+            translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
+            prefabs: vec![prefab0],
+            spawn_count: 40,
+            spawn_period: 0.05,
+            is_spawn_period_strict: false,
+            trigger_time: 0.0,
+            priority: 0,
+            missed_spawns: 0,
+            last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
+        };
+
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(5., 5., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
+        };
+        let generate = |shift_attempts: usize, shift_sampling: ShiftSampling| arc_level_generator::generate_report(
+            &world,
+            &[feature0.clone()],
+            &GeneratorConfig { shift_attempts, shift_sampling, ..GeneratorConfig::default() },
+            &[],
+            &mut StdRng::seed_from_u64(11),
+        ).unwrap();
+        let single_shift = generate(1, ShiftSampling::Random);
+        assert_eq!(single_shift.entities.len(), 40);
+        for shift_sampling in &[ShiftSampling::Random, ShiftSampling::Stratified, ShiftSampling::BestFit, ShiftSampling::FreeLanes] {
+            let report = generate(8, *shift_sampling);
+            assert_eq!(report.entities.len(), 40);
+            assert!(
+                report.rejected_spawns.len() < single_shift.rejected_spawns.len(),
+                "{:?} sampling rejected {} spawns, a single shift rejected {}",
+                shift_sampling,
+                report.rejected_spawns.len(),
+                single_shift.rejected_spawns.len(),
+            );
+        }
+    }
+    #[test]
+    fn test_generate_picks_weighted_variants() {
+        let prefab = |prefab_id: i32| Prefab {
+            prefab_id,
+            position: Vector3::new(0., 0., 0.),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+        };
+        let feature0 = Feature {
+            translate_x: true,
+            translate_x_using_bounds: false,
+            translate_x_bounds: Vector2::new(0., 0.),
+            translate_y: false,
+            translate_y_using_bounds: false,
+            translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
+            prefabs: vec![],
+            spawn_count: 60,
+            spawn_period: 1.,
+            is_spawn_period_strict: true,
+            trigger_time: 0.0,
+            priority: 0,
+            missed_spawns: 0,
+            last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![
+                FeatureVariant { prefabs: vec![prefab(1)], weight: 3., weight_curve: None },
+                FeatureVariant { prefabs: vec![prefab(2), Prefab { position: Vector3::new(0., 2., 0.), ..prefab(2) }], weight: 1., weight_curve: None },
+                FeatureVariant { prefabs: vec![prefab(3)], weight: 0., weight_curve: None },
+            ],
+        };
+
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(5., 5., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
+        };
+        let generated_entities = arc_level_generator::generate(
+            &world,
+            &[feature0],
+            &GeneratorConfig::default(),
+            &mut StdRng::seed_from_u64(5),
+        ).unwrap();
+        let count = |prefab_id: i32| generated_entities.iter().filter(|entity| entity.prefab.prefab_id == prefab_id).count();
+        assert_eq!(count(3), 0);
+        // Every spawn of the second variant produces both of its prefabs
+        assert_eq!(count(2) % 2, 0);
+        assert_eq!(count(1) + count(2) / 2, 60);
+        assert!(count(1) > count(2) / 2, "picked the first variant {} times, the second {} times", count(1), count(2) / 2);
+        assert!(count(2) > 0);
+    }
+
+    #[test]
+    fn test_generate_with_random_formation_transforms() {
+        let prefab = |x: f32| Prefab {
+            prefab_id: 0,
+            position: Vector3::new(x, 0., 0.),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: Some(CollisionShape::Ball { radius: 0.5 }),
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+        };
+        let feature0 = Feature {
+            translate_x: true,
+            translate_x_using_bounds: false,
+            translate_x_bounds: Vector2::new(0., 0.),
+            translate_y: true,
+            translate_y_using_bounds: false,
+            translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: true,
+            rotate_bounds: Vector2::new(-std::f32::consts::PI, std::f32::consts::PI),
+            scale: true,
+            scale_bounds: Vector2::new(0.5, 1.5),
+            mirror: true,
+            prefabs: vec![prefab(-1.), prefab(2.)],
+            spawn_count: 30,
+            spawn_period: 0.5,
+            is_spawn_period_strict: false,
+            trigger_time: 0.0,
+            priority: 0,
+            missed_spawns: 0,
+            last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
+        };
+
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(6., 6., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
+        };
+        let generated_entities = arc_level_generator::generate(
+            &world,
+            &[feature0],
+            &GeneratorConfig::default(),
+            &mut StdRng::seed_from_u64(7),
+        ).unwrap();
+        assert_eq!(generated_entities.len(), 60);
+        assert!(generated_entities.iter().any(|entity| entity.spawn_mirrored));
+        assert!(generated_entities.iter().any(|entity| !entity.spawn_mirrored));
+        for pair in generated_entities.chunks(2) {
+            let scale = pair[0].spawn_scale;
+            assert!((0.5..=1.5).contains(&scale));
+            assert_eq!(pair[1].spawn_scale, scale);
+            assert_eq!(pair[0].prefab.collision_shape, Some(CollisionShape::Ball { radius: 0.5 * scale }));
+            // Both prefabs of a spawn still pass their positions at the same time, 3 units apart
+            assert!(((pair[1].spawn_position - pair[0].spawn_position).norm() - 3. * scale).abs() < 1.0e-3);
+        }
+    }
+
+    #[test]
+    fn test_generate_with_uniform_spawn_period_distribution() {
+        let prefab0 = Prefab {
+            prefab_id: 0,
+            position: Vector3::new(0., 0., 0.),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+        };
+        let feature0 = Feature {
+            translate_x: true,
+            translate_x_using_bounds: false,
+            translate_x_bounds: Vector2::new(0., 0.),
+            translate_y: true,
+            translate_y_using_bounds: false,
+            translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
+            prefabs: vec![prefab0],
+            spawn_count: 20,
+            spawn_period: 2.5,
+            is_spawn_period_strict: false,
+            trigger_time: 0.0,
+            priority: 0,
+            missed_spawns: 0,
+            last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Uniform { min: 2., max: 3. },
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
+        };
+
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(5., 5., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
+        };
+        let generated_entities = arc_level_generator::generate(
+            &world,
+            &[feature0],
+            &GeneratorConfig::default(),
+            &mut StdRng::seed_from_u64(3),
+        ).unwrap();
+        assert_eq!(generated_entities.len(), 20);
+        let intervals: Vec<f32> = generated_entities
+            .windows(2)
+            .map(|pair| pair[1].spawn_time - pair[0].spawn_time)
+            .collect();
+        // Intervals are only extended by the generation step
+        for interval in &intervals {
+            assert!(*interval >= 2. - 1.0e-3 && *interval <= 3. + 0.05, "interval {} is out of bounds", interval);
+        }
+        assert!(intervals.iter().any(|interval| (interval - intervals[0]).abs() > 0.1));
+    }
+
+    #[test]
+    fn test_generate_follows_feature_curves() {
+        let prefab = |prefab_id: i32| Prefab {
+            prefab_id,
+            position: Vector3::new(0., 0., 0.),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+        };
+        let curve = |start: f32, end: f32, interpolation: CurveInterpolation| Some(Curve {
+            keyframes: vec![CurveKeyframe { time: 0., value: start }, CurveKeyframe { time: 30., value: end }],
+            interpolation,
+        });
+        let feature0 = Feature {
+            translate_x: true,
+            translate_x_using_bounds: false,
+            translate_x_bounds: Vector2::new(0., 0.),
+            translate_y: true,
+            translate_y_using_bounds: false,
+            translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
+            prefabs: vec![],
+            spawn_count: 30,
+            spawn_period: 10.,
+            is_spawn_period_strict: true,
+            trigger_time: 0.0,
+            priority: 0,
+            missed_spawns: 0,
+            last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: curve(3., 1., CurveInterpolation::Cubic),
+            speed_multiplier_curve: curve(1., 2., CurveInterpolation::Linear),
+            next_spawn_time: 0.0,
+            variants: vec![
+                FeatureVariant { prefabs: vec![prefab(1)], weight: 1., weight_curve: curve(1., 0., CurveInterpolation::Linear) },
+                FeatureVariant { prefabs: vec![prefab(2)], weight: 0., weight_curve: curve(0., 1., CurveInterpolation::Linear) },
+            ],
+        };
+
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(5., 5., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
+        };
+        let generated_entities = arc_level_generator::generate(
+            &world,
+            &[feature0],
+            &GeneratorConfig::default(),
+            &mut StdRng::seed_from_u64(11),
+        ).unwrap();
+        assert_eq!(generated_entities.len(), 30);
+        // Only the first variant can be picked at the start and only the second one at the end
+        assert_eq!(generated_entities[0].prefab.prefab_id, 1);
+        assert_eq!(generated_entities[29].prefab.prefab_id, 2);
+        for entity in &generated_entities {
+            let speed_multiplier = 1. + (entity.spawn_time / 30.).max(0.).min(1.);
+            let speed = entity.prefab.movement.baseline_velocity.norm();
+            assert!((speed - speed_multiplier).abs() < 0.01, "speed {} at {} should be {}", speed, entity.spawn_time, speed_multiplier);
+        }
+        let first_interval = generated_entities[1].spawn_time - generated_entities[0].spawn_time;
+        let shortest_late_interval = generated_entities
+            .windows(2)
+            .filter(|pair| pair[0].spawn_time > 30.)
+            .map(|pair| pair[1].spawn_time - pair[0].spawn_time)
+            .fold(f32::INFINITY, f32::min);
+        assert!(first_interval > 2.5, "first interval is {}", first_interval);
+        assert!(shortest_late_interval < 1.1, "shortest late interval is {}", shortest_late_interval);
+    }
+
+    #[test]
+    fn test_generate_reports_spawns_without_variants() {
+        let prefab0 = Prefab {
+            prefab_id: 0,
+            position: Vector3::new(0., 0., 0.),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+        };
+        let feature0 = Feature {
+            translate_x: true,
+            translate_x_using_bounds: false,
+            translate_x_bounds: Vector2::new(0., 0.),
+            translate_y: false,
+            translate_y_using_bounds: false,
+            translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
+            prefabs: vec![],
+            spawn_count: 10,
+            spawn_period: 1.0,
+            is_spawn_period_strict: true,
+            trigger_time: 0.0,
+            priority: 0,
+            missed_spawns: 0,
+            last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![FeatureVariant {
+                prefabs: vec![prefab0],
+                weight: 0.,
+                weight_curve: Some(Curve {
+                    keyframes: vec![CurveKeyframe { time: 5., value: 0. }, CurveKeyframe { time: 6., value: 1. }],
+                    interpolation: CurveInterpolation::Linear,
+                }),
+            }],
+        };
+
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(5., 5., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
+        };
+        let report = arc_level_generator::generate_report(
+            &world,
+            &[feature0],
+            &GeneratorConfig::default(),
+            &[],
+            &mut StdRng::seed_from_u64(13),
+        ).unwrap();
+        assert_eq!(report.entities.len(), 10);
+        // Nothing is spawned while the variant can't be picked
+        for entity in &report.entities {
+            assert!(entity.spawn_time >= 5. - 0.05, "entity spawned at {}", entity.spawn_time);
+        }
+        assert!(!report.rejected_spawns.is_empty());
+        for rejected_spawn in &report.rejected_spawns {
+            assert_eq!(rejected_spawn.rejection, SpawnRejection::NoVariantAvailable);
+            assert!(rejected_spawn.prefab_ids.is_empty());
+            assert!(rejected_spawn.spawn_time <= 5., "rejected at {}", rejected_spawn.spawn_time);
+        }
+    }
+
+    #[test]
+    fn test_generate_avoids_exclusion_volumes() {
+        let prefab0 = Prefab {
+            prefab_id: 0,
+            position: Vector3::new(0., 0., 0.),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+        };
+        let feature0 = Feature {
+            translate_x: true,
+            translate_x_using_bounds: false,
+            translate_x_bounds: Vector2::new(0., 0.),
+            translate_y: true,
+            translate_y_using_bounds: false,
+            translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
+            prefabs: vec![prefab0],
+            spawn_count: 20,
+            spawn_period: 1.0,
+            is_spawn_period_strict: false,
+            trigger_time: 0.0,
+            priority: 0,
+            missed_spawns: 0,
+            last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
+        };
+        let volume_half_extents = Vector3::new(3., 3., 1.);
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![ExclusionVolume {
+                shape: CollisionShape::Cuboid { half_extents: volume_half_extents },
+                position: Vector3::new(0., 0., 0.),
+                rotation: UnitQuaternion::identity(),
+                active_from: None,
+                active_until: None,
+            }],
+            player_lanes: vec![],
+        };
+        let generated_entities = arc_level_generator::generate(
+            &world,
+            &[feature0],
+            &GeneratorConfig::default(),
+            &mut rand::thread_rng(),
+        ).unwrap();
+        assert_eq!(generated_entities.len(), 20);
+        for entity in &generated_entities {
+            // Entities moving along Z must stay out of the volume column widened by their size
+            let position = entity.transform_at_time(entity.spawn_time).translation.vector;
+            assert!(
+                position.x.abs() >= volume_half_extents.x + 0.5 - 0.01 || position.y.abs() >= volume_half_extents.y + 0.5 - 0.01,
+                "Entity passes through the exclusion volume at {:?}", position
+            );
+        }
+    }
+    #[test]
+    fn test_generate_in_sphere_world() {
+        let prefab0 = Prefab {
+            prefab_id: 0,
+            position: Vector3::new(0., 0., 0.),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(0.5, 0.5, 0.5)),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+        };
+        let feature0 = Feature {
+            translate_x: true,
+            translate_x_using_bounds: false,
+            translate_x_bounds: Vector2::new(0., 0.),
+            translate_y: true,
+            translate_y_using_bounds: false,
+            translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
+            prefabs: vec![prefab0],
+            spawn_count: 20,
+            spawn_period: 1.0,
+            is_spawn_period_strict: false,
+            trigger_time: 0.0,
+            priority: 0,
+            missed_spawns: 0,
+            last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
+        };
+        let radius = 10.;
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+            shape: Some(WorldShape::new(WorldShapeKind::Sphere { center: nalgebra::zero(), radius })),
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: vec![],
+        };
+        let generated_entities = arc_level_generator::generate(
+            &world,
+            &[feature0],
+            &GeneratorConfig::default(),
+            &mut rand::thread_rng(),
+        ).unwrap();
+        assert_eq!(generated_entities.len(), 20);
+        // Shifts are resampled a limited number of times, so an unlucky entity may still end up
+        // in a corner of the sphere bounding box
+        let outside_count = generated_entities
+            .iter()
+            .map(|entity| entity.transform_at_time(entity.spawn_time).translation.vector)
+            .filter(|position| position.x.hypot(position.y) > radius)
+            .count();
+        assert!(outside_count <= 1, "{} entities are spawned outside the sphere", outside_count);
+    }
+    #[test]
+    fn test_generate_keeps_player_corridor() {
+        let wall_half_extents = Vector3::new(4., 10., 0.5);
+        let wall = Prefab {
+            prefab_id: 0,
+            position: Vector3::new(0., 0., 0.),
+            rotation: UnitQuaternion::identity(),
+            bounding_box: AABB::from_half_extents(Point3::new(0., 0., 0.), wall_half_extents),
+            collision_shape: None,
+            clearance: None,
+            collision_layer: 1,
+            collision_mask: u32::MAX,
+            movement: Movement {
+                baseline_velocity: Vector3::new(0., 0., -1.),
+                arcs_plane_normal: Unit::new_normalize(Vector3::new(1., 0., 0.)),
+                approach_arc_angle: 0.0,
+                approach_arc_center_distance: 0.0,
+                approach_arc_radius: 0.0,
+                approach_rotation_strength: 0.,
+                departure_arc_angle: 0.0,
+                departure_arc_center_distance: 0.0,
+                departure_arc_radius: 0.0,
+                departure_rotation_strength: 0.0,
+            },
+        };
+        let feature0 = Feature {
+            translate_x: true,
+            translate_x_using_bounds: false,
+            translate_x_bounds: Vector2::new(0., 0.),
+            translate_y: false,
+            translate_y_using_bounds: false,
+            translate_y_bounds: Vector2::new(0., 0.),
+            translate_z: false,
+            translate_z_using_bounds: false,
+            translate_z_bounds: Vector2::new(0., 0.),
+            rotate: false,
+            rotate_bounds: Vector2::new(0., 0.),
+            scale: false,
+            scale_bounds: Vector2::new(1., 1.),
+            mirror: false,
+            prefabs: vec![wall],
+            spawn_count: 15,
+            spawn_period: 0.5,
+            is_spawn_period_strict: false,
+            trigger_time: 0.0,
+            priority: 0,
+            missed_spawns: 0,
+            last_spawn_attempt: 0.0,
+            spawn_period_distribution: SpawnPeriodDistribution::Adaptive,
+            spawn_period_curve: None,
+            speed_multiplier_curve: None,
+            next_spawn_time: 0.0,
+            variants: vec![],
+        };
+        let lane_half_extents = Vector3::new(1.5, 1., 0.5);
+        let lane_positions = [Vector3::new(-5., 0., -8.), Vector3::new(5., 0., -8.)];
+        let world = VisibleWorld {
+            world_bounds: AABB::from_half_extents(Point3::new(0., 0., 0.), Vector3::new(10., 10., 10.)),
+            shape: None,
+            flow_direction: -Vector3::z_axis(),
+            exclusion_volumes: vec![],
+            player_lanes: lane_positions
+                .iter()
+                .map(|position| PlayerLane {
+                    shape: CollisionShape::Cuboid { half_extents: lane_half_extents },
+                    position: *position,
+                    rotation: UnitQuaternion::identity(),
+                    velocity: Vector3::new(0., 0., 0.),
+                    collision_mask: u32::MAX,
+                })
+                .collect(),
+        };
+        let generated_entities = arc_level_generator::generate(
+            &world,
+            &[feature0],
+            &GeneratorConfig::default(),
+            &mut rand::thread_rng(),
+        ).unwrap();
+        assert_eq!(generated_entities.len(), 15);
+
+        let start_time = generated_entities.iter().map(|entity| entity.spawn_time).fold(f32::MAX, f32::min);
+        let end_time = generated_entities.iter().map(|entity| entity.world_exit_time()).fold(f32::MIN, f32::max);
+        let mut time = start_time;
+        while time < end_time {
+            let is_lane_blocked = |lane_position: &Vector3<f32>| {
+                generated_entities
+                    .iter()
+                    .filter(|entity| entity.is_alive_at(time))
+                    .any(|entity| {
+                        let overlap = wall_half_extents + lane_half_extents - (entity.transform_at_time(time).translation.vector - lane_position).abs();
+                        overlap.iter().all(|value| *value > 0.01)
+                    })
+            };
+            assert!(!lane_positions.iter().all(is_lane_blocked), "All lanes are blocked at {}", time);
+            time += 0.05;
+        }
+    }
+}